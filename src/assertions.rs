@@ -0,0 +1,291 @@
+//! Thresholds on the final stats of scenario runs, given with `--assert`,
+//! e.g. `--assert p99<20ms --assert error_rate<0.1%`, so a run can gate a CI
+//! pipeline.
+//!
+//! Every assertion is checked against every workload of every phase. Once
+//! the subcommand is done, each check prints a line `assert <pass|fail>
+//! <phase>/<workload> <assertion> <value>`, and the process exits non-zero
+//! if any of them failed.
+//!
+//! The metrics are `p50`, `p99`, `p999`, `min`, `max` and `stddev` of the
+//! latency, compared with a duration such as `20ms`, `1.5s` or `500us`;
+//! `throughput` in iterations per second; `errors`, the number of errors;
+//! and `error_rate`, the errors per attempt, as a fraction or a percentage.
+use crate::error::MyError;
+use crate::metrics::RunStats;
+use crate::scenario::PhaseReport;
+use crate::Result;
+use log::{error, info};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+static ASSERTIONS: OnceLock<Vec<Assertion>> = OnceLock::new();
+// a line per check so far, and whether it passed
+static CHECKS: Mutex<Vec<(String, bool)>> = Mutex::new(Vec::new());
+
+/// Check `assertions` after every scenario run from now on.
+pub fn set(assertions: Vec<Assertion>) {
+    let _ = ASSERTIONS.set(assertions);
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Metric {
+    Percentile(f64),
+    Min,
+    Max,
+    Stddev,
+    Throughput,
+    Errors,
+    ErrorRate,
+}
+
+impl Metric {
+    /// The value in `stats`, durations in milliseconds; `None` if there are
+    /// no latencies.
+    fn of(self, stats: &RunStats) -> Option<f64> {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        match self {
+            Metric::Percentile(p) => stats.percentile(p).map(ms),
+            Metric::Min => stats.latencies.min().map(ms),
+            Metric::Max => stats.latencies.max().map(ms),
+            Metric::Stddev => stats.latencies.stddev().map(ms),
+            Metric::Throughput => Some(stats.throughput()),
+            Metric::Errors => Some(stats.errors.total() as f64),
+            Metric::ErrorRate => {
+                let errors = stats.errors.total() as f64;
+                let attempts = stats.succeeded as f64 + errors;
+                Some(if attempts == 0.0 {
+                    0.0
+                } else {
+                    errors / attempts
+                })
+            }
+        }
+    }
+
+    fn is_latency(self) -> bool {
+        matches!(
+            self,
+            Metric::Percentile(_) | Metric::Min | Metric::Max | Metric::Stddev
+        )
+    }
+
+    /// `value` for the summary.
+    fn format(self, value: f64) -> String {
+        match self {
+            _ if self.is_latency() => format!("{:.3}ms", value),
+            Metric::Throughput => format!("{:.1}/s", value),
+            Metric::ErrorRate => format!("{:.4}%", value * 100.0),
+            _ => format!("{}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A threshold on a metric, e.g. `p99<20ms`.
+#[derive(Debug, Clone)]
+pub struct Assertion {
+    text: String,
+    metric: Metric,
+    op: Op,
+    limit: f64,
+}
+
+impl Assertion {
+    fn holds(&self, value: f64) -> bool {
+        match self.op {
+            Op::Lt => value < self.limit,
+            Op::Le => value <= self.limit,
+            Op::Gt => value > self.limit,
+            Op::Ge => value >= self.limit,
+        }
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl FromStr for Assertion {
+    type Err = MyError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = |why: &str| MyError::StringError(format!("invalid assertion {}: {}", s, why));
+        let at = s
+            .find(['<', '>'])
+            .ok_or_else(|| invalid("expected <, <=, > or >="))?;
+        let (name, rest) = s.split_at(at);
+        let (op, limit) = match rest.split_at(1) {
+            ("<", l) if l.starts_with('=') => (Op::Le, &l[1..]),
+            ("<", l) => (Op::Lt, l),
+            (_, l) if l.starts_with('=') => (Op::Ge, &l[1..]),
+            (_, l) => (Op::Gt, l),
+        };
+        let metric = match name.trim() {
+            "p50" => Metric::Percentile(50.0),
+            "p99" => Metric::Percentile(99.0),
+            "p999" | "p99.9" => Metric::Percentile(99.9),
+            "min" => Metric::Min,
+            "max" => Metric::Max,
+            "stddev" => Metric::Stddev,
+            "throughput" => Metric::Throughput,
+            "errors" => Metric::Errors,
+            "error_rate" => Metric::ErrorRate,
+            _ => return Err(invalid("unknown metric")),
+        };
+        let limit = limit.trim();
+        let number = |n: &str| {
+            n.trim()
+                .parse::<f64>()
+                .map_err(|_| invalid("invalid value"))
+        };
+        let limit = if metric.is_latency() {
+            let unit = limit
+                .find(|c: char| c.is_ascii_alphabetic())
+                .ok_or_else(|| invalid("expected a unit of ns, us, ms or s"))?;
+            let scale = match &limit[unit..] {
+                "ns" => 1e-6,
+                "us" => 1e-3,
+                "ms" => 1.0,
+                "s" => 1e3,
+                _ => return Err(invalid("expected a unit of ns, us, ms or s")),
+            };
+            number(&limit[..unit])? * scale
+        } else if let (Metric::ErrorRate, Some(percent)) = (metric, limit.strip_suffix('%')) {
+            number(percent)? / 100.0
+        } else {
+            number(limit)?
+        };
+        Ok(Assertion {
+            text: s.to_owned(),
+            metric,
+            op,
+            limit,
+        })
+    }
+}
+
+/// Check the assertions set with [`set`] against every workload of
+/// `reports`, for [`summarize`].
+pub fn check(reports: &[PhaseReport]) {
+    let Some(assertions) = ASSERTIONS.get() else {
+        return;
+    };
+    let mut checks = CHECKS.lock().unwrap();
+    for report in reports {
+        for (workload, stats) in &report.workloads {
+            for assertion in assertions {
+                let value = assertion.metric.of(stats);
+                let pass = value.is_some_and(|v| assertion.holds(v));
+                let line = format!(
+                    "assert {} {}/{} {} {}",
+                    if pass { "pass" } else { "fail" },
+                    report.label(),
+                    workload,
+                    assertion,
+                    value.map_or("-".to_owned(), |v| assertion.metric.format(v))
+                );
+                if pass {
+                    info!("{}", line);
+                } else {
+                    error!("{}", line);
+                }
+                checks.push((line, pass));
+            }
+        }
+    }
+}
+
+/// Print the outcome of every check so far. Fails if any check did, or if
+/// there were assertions but no scenario run to check them against, so a
+/// gate can't pass by accident.
+pub fn summarize() -> Result<()> {
+    let checks = CHECKS.lock().unwrap();
+    if ASSERTIONS.get().is_some_and(|a| !a.is_empty()) && checks.is_empty() {
+        return Err(MyError::StringError(
+            "no workload stats to check the assertions against".to_owned(),
+        ));
+    }
+    for (line, _) in checks.iter() {
+        println!("{}", line);
+    }
+    let failed = checks.iter().filter(|(_, pass)| !pass).count();
+    if failed > 0 {
+        return Err(MyError::StringError(format!(
+            "{} of {} assertion checks failed",
+            failed,
+            checks.len()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Assertion {
+        s.parse().unwrap_or_else(|e| panic!("{}: {:?}", s, e))
+    }
+
+    #[test]
+    fn accepted() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+        let a = parse("p99<20ms");
+        assert!(matches!((a.metric, a.op), (Metric::Percentile(p), Op::Lt) if p == 99.0));
+        assert!(close(a.limit, 20.0));
+        let a = parse("p99.9 <= 1.5s");
+        assert!(matches!((a.metric, a.op), (Metric::Percentile(p), Op::Le) if p == 99.9));
+        assert!(close(a.limit, 1500.0));
+        let a = parse("p999<500us");
+        assert!(matches!(a.metric, Metric::Percentile(p) if p == 99.9));
+        assert!(close(a.limit, 0.5));
+        let a = parse("stddev>=2000ns");
+        assert!(matches!((a.metric, a.op), (Metric::Stddev, Op::Ge)));
+        assert!(close(a.limit, 0.002));
+        let a = parse("throughput>1000");
+        assert!(matches!((a.metric, a.op), (Metric::Throughput, Op::Gt)));
+        assert!(close(a.limit, 1000.0));
+        let a = parse("error_rate<0.1%");
+        assert!(matches!(a.metric, Metric::ErrorRate));
+        assert!(close(a.limit, 0.001));
+        let a = parse("error_rate<0.001");
+        assert!(close(a.limit, 0.001));
+        let a = parse("errors<=0");
+        assert!(matches!((a.metric, a.op), (Metric::Errors, Op::Le)));
+        assert_eq!(a.to_string(), "errors<=0");
+        for s in ["min>1ms", "max<1s", "p50<1ms"] {
+            parse(s);
+        }
+    }
+
+    #[test]
+    fn rejected() {
+        for s in [
+            "",
+            "p99",
+            "p99=20ms",
+            "p95<20ms",
+            "p99<20",
+            "p99<20m",
+            "p99<ms",
+            "throughput>fast",
+            "throughput>10%",
+            "errors<",
+            "error_rate<%",
+        ] {
+            assert!(s.parse::<Assertion>().is_err(), "{} was accepted", s);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! All workloads in one binary, e.g. `scripts --url mysql://... dmlddl --pd 127.0.0.1:2379`.
+use clap::{Parser, Subcommand};
+use dmlddl::assertions;
+use dmlddl::charts;
+use dmlddl::cmd::{self, Common};
+use dmlddl::config;
+use dmlddl::events;
+use dmlddl::metadata;
+use dmlddl::metrics;
+use dmlddl::otlp;
+use dmlddl::output;
+use dmlddl::pushgateway;
+use dmlddl::regions;
+use dmlddl::results;
+use dmlddl::scenario;
+use dmlddl::server_metrics;
+use dmlddl::slow_query;
+use dmlddl::tui;
+use dmlddl::txn_ts;
+use dmlddl::Result;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[clap(name = "scripts")]
+struct Cli {
+    #[clap(flatten)]
+    common: Common,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// DML and DDL on the same table at the same time
+    Dmlddl(cmd::dmlddl::Opt),
+    /// Read-modify-write of one row checked against a client-side model
+    Update(cmd::update::Opt),
+    /// Reads next to write contention producing many rollback records
+    ContentionUpdate(cmd::contention_update::Opt),
+    /// Load 10 million rows, resumable
+    MillionWriter(cmd::million_writer::Opt),
+    /// Inserts and deletes of a sliding window of rows for days
+    Rollover(cmd::rollover::Opt),
+    /// Pessimistic updates of a single row by its unique key
+    SingleRowUpdate(cmd::single_row_update::Opt),
+    /// Execute the statements of an SQL file in one transaction
+    LargeInsert,
+    /// BATCH DML next to point writes
+    BatchDml(cmd::batch_dml::Opt),
+    /// Soft deletes, filtered reads and hard-delete cleanups
+    SoftDelete(cmd::soft_delete::Opt),
+    /// DML on tables linked by a foreign key while the constraint changes
+    ForeignKey(cmd::foreign_key::Opt),
+    /// Tenants with disjoint keys of one table, and the interference among them
+    Tenants(cmd::tenants::Opt),
+    /// DML on a range-partitioned table while its partitions change
+    PartitionDdl(cmd::partition_ddl::Opt),
+    /// Inserts and updates while partitions are split and merged
+    ReorganizePartition(cmd::reorganize_partition::Opt),
+    /// Updates of base columns while generated columns are added and dropped
+    GeneratedColumn(cmd::generated_column::Opt),
+    /// Inserts and updates while the TTL job deletes expired rows
+    Ttl(cmd::ttl::Opt),
+    /// Writes near the limits of column types while the types are narrowed and widened
+    LossyChange(cmd::lossy_change::Opt),
+    /// Inserts with AUTO_INCREMENT, client-side scattered, AUTO_RANDOM or string keys
+    Insert(cmd::insert::Opt),
+    /// Inserts, updates, deletes and selects blended by weight in every worker
+    Mix(cmd::mix::Opt),
+    /// Transactions of many rows with point reads alongside
+    LargeTxn(cmd::large_txn::Opt),
+    /// SELECT FOR UPDATE NOWAIT or SKIP LOCKED under contention
+    LockingRead(cmd::locking_read::Opt),
+    /// INSERT ON DUPLICATE KEY UPDATE or REPLACE of existing and new keys
+    Upsert(cmd::upsert::Opt),
+    /// Compare two results files written with --results
+    Compare(cmd::compare::Opt),
+    /// Re-execute the statements recorded with --record of dmlddl or update
+    Replay(cmd::replay::Opt),
+    /// Phases of workloads described in a TOML file
+    Scenario(cmd::scenario::Opt),
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli: Cli = config::parse()?;
+    let common = &cli.common;
+    output::init(common.output_dir.as_deref(), common.run_name.as_deref())?;
+    if let Some(label) = &common.label {
+        metadata::set_label(label);
+    }
+    metrics::use_sketch(common.latency_sketch);
+    if let Some(url) = &common.pushgateway_url {
+        pushgateway::init(url);
+    }
+    match &common.results {
+        Some(path) => results::write_to(&output::path(path)),
+        None if output::dir().is_some() => results::write_to(&output::path("results.csv")),
+        None => {}
+    }
+    if let Some(addr) = &common.tidb_status {
+        server_metrics::init(addr);
+    }
+    if common.region_snapshots {
+        regions::enable();
+    }
+    if let Some(top) = common.slow_queries {
+        slow_query::capture(top);
+    }
+    scenario::repeat(common.repetitions);
+    if common.charts {
+        charts::enable();
+    }
+    if let Some(ratio) = common.sample_txn_ts {
+        txn_ts::sample(ratio)?;
+    }
+    if let Some(path) = &common.event_log {
+        events::log_to(path)?;
+    }
+    if common.tui {
+        tui::enable();
+    }
+    if let Some(secs) = common.report_interval {
+        scenario::report_every(Duration::from_secs(secs));
+    }
+    if let Some(endpoint) = &common.otlp_endpoint {
+        otlp::init(endpoint);
+    }
+    assertions::set(common.assertions.clone());
+    let res = match cli.command {
+        Command::Dmlddl(opt) => cmd::dmlddl::run(common, opt).await,
+        Command::Update(opt) => cmd::update::run(common, opt).await,
+        Command::ContentionUpdate(opt) => cmd::contention_update::run(common, opt).await,
+        Command::MillionWriter(opt) => cmd::million_writer::run(common, opt).await,
+        Command::Rollover(opt) => cmd::rollover::run(common, opt).await,
+        Command::SingleRowUpdate(opt) => cmd::single_row_update::run(common, opt).await,
+        Command::LargeInsert => cmd::large_insert::run(common).await,
+        Command::BatchDml(opt) => cmd::batch_dml::run(common, opt).await,
+        Command::SoftDelete(opt) => cmd::soft_delete::run(common, opt).await,
+        Command::ForeignKey(opt) => cmd::foreign_key::run(common, opt).await,
+        Command::Tenants(opt) => cmd::tenants::run(common, opt).await,
+        Command::PartitionDdl(opt) => cmd::partition_ddl::run(common, opt).await,
+        Command::ReorganizePartition(opt) => cmd::reorganize_partition::run(common, opt).await,
+        Command::GeneratedColumn(opt) => cmd::generated_column::run(common, opt).await,
+        Command::Ttl(opt) => cmd::ttl::run(common, opt).await,
+        Command::LossyChange(opt) => cmd::lossy_change::run(common, opt).await,
+        Command::Insert(opt) => cmd::insert::run(common, opt).await,
+        Command::Mix(opt) => cmd::mix::run(common, opt).await,
+        Command::LargeTxn(opt) => cmd::large_txn::run(common, opt).await,
+        Command::LockingRead(opt) => cmd::locking_read::run(common, opt).await,
+        Command::Upsert(opt) => cmd::upsert::run(common, opt).await,
+        Command::Compare(opt) => cmd::compare::run(opt).await,
+        Command::Replay(opt) => cmd::replay::run(common, opt).await,
+        Command::Scenario(opt) => cmd::scenario::run(common, opt).await,
+    };
+    res?;
+    assertions::summarize()
+}
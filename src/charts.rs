@@ -0,0 +1,113 @@
+//! Charts of the throughput and latency percentiles over time of every
+//! workload in every scenario phase, with `--charts`: an SVG per workload
+//! and phase, next to the `--results` file if given, e.g.
+//! `results.load.insert.svg` for `results.csv`, else in the working
+//! directory.
+use crate::error::MyError;
+use crate::metrics::{RunStats, LATENCY_SERIES_INTERVAL};
+use crate::scenario::PhaseReport;
+use crate::Result;
+use log::info;
+use plotters::prelude::*;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const SIZE: (u32, u32) = (1000, 700);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Draw charts of the scenarios run from now on.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Draw a chart of each workload of `reports`, named after `results`, the
+/// results file if any.
+pub fn write(results: Option<&str>, reports: &[PhaseReport]) -> Result<()> {
+    let prefix = results
+        .map(|r| Path::new(r).with_extension("").display().to_string())
+        .unwrap_or_else(|| "charts".to_owned());
+    for report in reports {
+        for (workload, stats) in &report.workloads {
+            let path = format!("{}.{}.{}.svg", prefix, report.label(), workload);
+            draw(&path, &format!("{} {}", report.label(), workload), stats)
+                .map_err(|e| MyError::StringError(format!("failed to draw {}: {}", path, e)))?;
+            info!("chart written to {}", path);
+        }
+    }
+    Ok(())
+}
+
+fn draw(
+    path: &str,
+    title: &str,
+    stats: &RunStats,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let root = SVGBackend::new(path, SIZE).into_drawing_area();
+    root.fill(&WHITE)?;
+    let root = root.titled(title, ("sans-serif", 24))?;
+    let (top, bottom) = root.split_vertically(SIZE.1 / 2 - 20);
+
+    let per_second = stats.per_second.all();
+    let secs = per_second.len().max(1) as f64;
+    let max_ops = per_second.iter().copied().max().unwrap_or(0).max(1) as f64;
+    let mut chart = ChartBuilder::on(&top)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0.0..secs, 0.0..max_ops * 1.1)?;
+    chart
+        .configure_mesh()
+        .x_desc("seconds")
+        .y_desc("ops/s")
+        .draw()?;
+    chart.draw_series(LineSeries::new(
+        per_second
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| (i as f64, n as f64)),
+        &BLUE,
+    ))?;
+
+    let interval = LATENCY_SERIES_INTERVAL.as_secs_f64();
+    let ms = |p| -> Vec<(f64, f64)> {
+        stats
+            .latency_series
+            .percentiles(p)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, l)| Some(((i as f64 + 0.5) * interval, l?.as_secs_f64() * 1000.0)))
+            .collect()
+    };
+    let (p50, p99) = (ms(50.0), ms(99.0));
+    let max_ms = p99.iter().map(|(_, v)| *v).fold(0.0, f64::max).max(1.0);
+    let mut chart = ChartBuilder::on(&bottom)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0.0..secs, 0.0..max_ms * 1.1)?;
+    chart
+        .configure_mesh()
+        .x_desc("seconds")
+        .y_desc("latency ms")
+        .draw()?;
+    chart
+        .draw_series(LineSeries::new(p50, &GREEN))?
+        .label("p50")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], GREEN));
+    chart
+        .draw_series(LineSeries::new(p99, &RED))?
+        .label("p99")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+    chart
+        .configure_series_labels()
+        .background_style(WHITE)
+        .border_style(BLACK)
+        .draw()?;
+    root.present()?;
+    Ok(())
+}
@@ -0,0 +1,154 @@
+//! Subcommands of the `scripts` binary, one per workload.
+use crate::assertions::Assertion;
+use crate::connection::ConnectionOpts;
+use crate::runner::MeasureWindow;
+use crate::Result;
+use clap::Args;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+pub mod batch_dml;
+pub mod compare;
+pub mod contention_update;
+pub mod dmlddl;
+pub mod foreign_key;
+pub mod generated_column;
+pub mod insert;
+pub mod large_insert;
+pub mod large_txn;
+pub mod locking_read;
+pub mod lossy_change;
+pub mod million_writer;
+pub mod mix;
+pub mod partition_ddl;
+pub mod reorganize_partition;
+pub mod replay;
+pub mod rollover;
+pub mod scenario;
+pub mod single_row_update;
+pub mod soft_delete;
+pub mod tenants;
+pub mod ttl;
+pub mod update;
+pub mod upsert;
+
+/// Flags shared by all subcommands. A subcommand ignores those that make no
+/// sense for it.
+#[derive(Args)]
+pub struct Common {
+    #[clap(flatten)]
+    pub connection: ConnectionOpts,
+    /// Number of workers, overriding the subcommand's default
+    #[clap(long, global = true)]
+    pub concurrency: Option<usize>,
+    /// How long to run in seconds, overriding the subcommand's default. For
+    /// scenarios this is the duration of each phase
+    #[clap(long, global = true)]
+    pub duration: Option<u64>,
+    /// Seed from which each worker's RNG is derived; random if not given
+    #[clap(long, global = true)]
+    pub seed: Option<u64>,
+    /// Estimate latency percentiles within 1% in constant memory instead of
+    /// keeping every latency, for runs of hundreds of millions of operations
+    #[clap(long, global = true)]
+    pub latency_sketch: bool,
+    /// Prometheus Pushgateway to push the throughput, latency and errors of
+    /// scenario phases to, e.g. http://127.0.0.1:9091
+    #[clap(long, global = true)]
+    pub pushgateway_url: Option<String>,
+    /// Show a live dashboard of each scenario phase instead of only
+    /// reporting it at the end
+    #[clap(long, global = true)]
+    pub tui: bool,
+    /// Write the results of the scenario phases to this CSV file, for the
+    /// compare subcommand; results.csv in the run's directory with
+    /// --output-dir or --run-name
+    #[clap(long, global = true)]
+    pub results: Option<String>,
+    /// Status address of the TiDB under test, e.g. 127.0.0.1:10080, to
+    /// report its KV request duration, retries and conflicts of each
+    /// scenario phase
+    #[clap(long, global = true)]
+    pub tidb_status: Option<String>,
+    /// Report the regions of the database and their leaders by store at
+    /// the start, the middle and the end of each scenario phase
+    #[clap(long, global = true)]
+    pub region_snapshots: bool,
+    /// Report the plans with the most time in the slow log of each scenario
+    /// phase, this many of them
+    #[clap(long, global = true)]
+    pub slow_queries: Option<u32>,
+    /// Run scenarios this many times and report the mean, standard deviation
+    /// and range of the throughput and p99 of each workload over them
+    #[clap(long, global = true, default_value = "1")]
+    pub repetitions: usize,
+    /// Draw SVG charts of the throughput and latency over time of each
+    /// workload of each scenario phase, next to the --results file if given
+    #[clap(long, global = true)]
+    pub charts: bool,
+    /// Write the logs, results, charts and recordings of the run to a
+    /// directory named after it in this directory
+    #[clap(long, global = true)]
+    pub output_dir: Option<String>,
+    /// Name of the run, its start time in seconds since the epoch if not
+    /// given. Also names its Pushgateway group
+    #[clap(long, global = true)]
+    pub run_name: Option<String>,
+    /// A note on the run written into its results files, e.g. what was
+    /// changed in the cluster
+    #[clap(long, global = true)]
+    pub label: Option<String>,
+    /// Record the start_ts and commit_ts of this ratio of the transactions
+    /// of transactional workloads, with their latency, to txn_ts.tsv
+    #[clap(long, global = true)]
+    pub sample_txn_ts: Option<f64>,
+    /// Log every iteration of the workers as a JSON line to this file or
+    /// pipe: worker, workload, latency, result and error class
+    #[clap(long, global = true)]
+    pub event_log: Option<String>,
+    /// Print the throughput, p99 and errors of each workload of the running
+    /// scenario phase every this many seconds
+    #[clap(long, global = true)]
+    pub report_interval: Option<u64>,
+    /// Leave out the iterations that finish within this many seconds of the
+    /// start of a run from its totals, throughput and latencies
+    #[clap(long, global = true, default_value = "0")]
+    pub measure_start: u64,
+    /// Leave out the iterations that finish this many seconds or more after
+    /// the start of a run from its totals, throughput and latencies
+    #[clap(long, global = true)]
+    pub measure_end: Option<u64>,
+    /// A threshold on the final stats of every workload, e.g. p99<20ms or
+    /// error_rate<0.1%; can be repeated. The run exits non-zero if one is
+    /// not met
+    #[clap(long = "assert", global = true)]
+    pub assertions: Vec<Assertion>,
+    /// Trace every iteration of the workers, with its statements, to this
+    /// OpenTelemetry collector as OTLP/HTTP, e.g. http://127.0.0.1:4318
+    #[clap(long, global = true)]
+    pub otlp_endpoint: Option<String>,
+}
+
+impl Common {
+    pub fn concurrency(&self, default: usize) -> usize {
+        self.concurrency.unwrap_or(default)
+    }
+
+    pub fn duration(&self, default: Duration) -> Duration {
+        self.duration.map_or(default, Duration::from_secs)
+    }
+
+    /// The seed given with `--seed`, else one drawn once per process.
+    pub fn seed(&self) -> u64 {
+        static RANDOM: OnceLock<u64> = OnceLock::new();
+        self.seed
+            .unwrap_or_else(|| *RANDOM.get_or_init(rand::random))
+    }
+
+    pub fn measure_window(&self) -> Result<MeasureWindow> {
+        MeasureWindow::new(
+            Duration::from_secs(self.measure_start),
+            self.measure_end.map(Duration::from_secs),
+        )
+    }
+}
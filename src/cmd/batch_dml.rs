@@ -0,0 +1,120 @@
+//! Coverage of non-transactional DML (`BATCH ON ... DELETE/UPDATE`) under
+//! concurrent point writes.
+//!
+//! Phase "update" runs batch updates over the whole table, phase "delete" runs
+//! batch deletes of half of it, both next to point writers. Afterwards every
+//! row is checked against a client-side model.
+//!
+//! `--concurrency` is the number of point writers, `--duration` the duration
+//! of each phase. `--repetitions` isn't supported.
+use crate::cmd::Common;
+use crate::error::MyError;
+use crate::invariant::InvariantOpts;
+use crate::output;
+use crate::placement::Placement;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, Scenario};
+use crate::workload::batch_dml::{BatchDelete, BatchTable, BatchUpdate, PointWrite};
+use crate::workload::Workload;
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WRITERS: usize = 8;
+const PHASE_DURATION: Duration = Duration::from_secs(300);
+
+#[derive(Args)]
+pub struct Opt {
+    /// Number of rows of the table
+    #[clap(long, default_value = "1000000")]
+    rows: u64,
+    /// Rows per batch of a BATCH statement
+    #[clap(long, default_value = "1000")]
+    batch_size: u64,
+    /// Options of a placement policy to apply to the table before the first
+    /// phase, e.g. 'LEADER_CONSTRAINTS="[+zone=z1]"'
+    #[clap(long)]
+    placement: Option<String>,
+    /// Seconds to wait for the placement to be scheduled
+    #[clap(long, default_value = "600")]
+    placement_timeout: u64,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    if opt.rows < 2 {
+        return Err(MyError::StringError(format!(
+            "--rows must be at least 2, got {}",
+            opt.rows
+        )));
+    }
+    // each repetition recreates the table, but only the last one is verified
+    if common.repetitions > 1 {
+        return Err(MyError::StringError(
+            "batch-dml doesn't support --repetitions".to_string(),
+        ));
+    }
+    simple_logging::log_to_file(output::path("batch_dml.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let writers = common.concurrency(WRITERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(writers as u32 + 2)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let table = Arc::new(BatchTable::new(opt.rows, opt.batch_size));
+    let update: Arc<dyn Workload> = Arc::new(BatchUpdate(table.clone()));
+    let delete: Arc<dyn Workload> = Arc::new(BatchDelete(table.clone()));
+    let phase_duration = common.duration(PHASE_DURATION);
+    let mut scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .invariants(opt.invariants)
+        .phase(Phase::new("update", phase_duration).with(update, 1).with(
+            Arc::new(PointWrite {
+                table: table.clone(),
+                odd_only: false,
+            }),
+            writers,
+        ))
+        .phase(Phase::new("delete", phase_duration).with(delete, 1).with(
+            Arc::new(PointWrite {
+                table: table.clone(),
+                odd_only: true,
+            }),
+            writers,
+        ));
+    if let Some(options) = opt.placement {
+        scenario = scenario.placement(Placement {
+            options,
+            tables: vec!["batch_dml".to_owned()],
+            timeout: Duration::from_secs(opt.placement_timeout),
+        });
+    }
+    let retry = RetryPolicy {
+        max_retries: 0,
+        base_backoff: Duration::ZERO,
+    };
+    let reports = run_scenario(&pool, &scenario, seed, retry).await?;
+    for report in &reports {
+        println!("{}", report);
+    }
+    let (_, updates) = &reports[0].workloads[0];
+    let msg = format!(
+        "batch update rows/s: {:.0}",
+        updates.throughput() * opt.rows as f64
+    );
+    info!("{}", msg);
+    println!("{}", msg);
+
+    let mut conn = pool.acquire().await?;
+    table.verify(&mut conn).await?;
+    println!("final state verified");
+    Ok(())
+}
@@ -0,0 +1,105 @@
+//! Compare two results files written with `--results`, e.g. of the same
+//! scenario against two TiDB versions. Every workload of every phase in
+//! both gets a table of its throughput, latencies and errors with the
+//! change in percent; changes for the worse beyond `--threshold` are
+//! flagged as regressions.
+//!
+//! A single run's difference may be noise, so the throughput of each second
+//! of the two is compared with a Mann-Whitney U test too. A change of the
+//! throughput whose p-value is not below `--alpha` is marked as within noise
+//! and not counted as a regression.
+use crate::metrics::mann_whitney;
+use crate::results::{read, Measurement};
+use crate::Result;
+use clap::Args;
+
+#[derive(Args)]
+pub struct Opt {
+    /// Results of the baseline
+    old: String,
+    /// Results to compare with the baseline
+    new: String,
+    /// Flag changes for the worse by more than this many percent
+    #[clap(long, default_value = "5")]
+    threshold: f64,
+    /// Significance level of the test of the per-second throughputs
+    #[clap(long, default_value = "0.05")]
+    alpha: f64,
+}
+
+pub async fn run(opt: Opt) -> Result<()> {
+    let old = read(&opt.old)?;
+    let new = read(&opt.new)?;
+    let mut regressions = 0;
+    for n in &new {
+        let Some(o) = old.iter().find(|o| o.key() == n.key()) else {
+            println!("{}: only in {}", n.key(), opt.new);
+            continue;
+        };
+        println!("{}:", n.key());
+        let samples = |m: &Measurement| m.per_second.iter().map(|&n| n as f64).collect::<Vec<_>>();
+        let p = mann_whitney(&samples(o), &samples(n));
+        let rows = [
+            (
+                "throughput",
+                Some(o.throughput),
+                Some(n.throughput),
+                true,
+                p,
+            ),
+            ("p50 ms", o.p50_ms, n.p50_ms, false, None),
+            ("p99 ms", o.p99_ms, n.p99_ms, false, None),
+            ("p99.9 ms", o.p999_ms, n.p999_ms, false, None),
+            (
+                "errors",
+                Some(o.errors as f64),
+                Some(n.errors as f64),
+                false,
+                None,
+            ),
+        ];
+        for (metric, o, n, higher_is_better, p) in rows {
+            let change = match (o, n) {
+                (Some(o), Some(n)) if o != 0.0 => Some((n / o - 1.0) * 100.0),
+                _ => None,
+            };
+            let worse = change.is_some_and(|c| {
+                if higher_is_better {
+                    c < -opt.threshold
+                } else {
+                    c > opt.threshold
+                }
+            });
+            let noise = p.is_some_and(|p| p >= opt.alpha);
+            if worse && !noise {
+                regressions += 1;
+            }
+            let value = |v: Option<f64>| v.map_or("-".to_owned(), |v| format!("{:.2}", v));
+            println!(
+                "  {:<12}{:>14}{:>14}{:>10}{}",
+                metric,
+                value(o),
+                value(n),
+                change.map_or("-".to_owned(), |c| format!("{:+.1}%", c)),
+                match p {
+                    Some(p) if noise => format!("  within noise (p={:.3})", p),
+                    Some(p) if worse => format!("  REGRESSION (p={:.3})", p),
+                    Some(p) => format!("  (p={:.3})", p),
+                    None if worse => "  REGRESSION".to_owned(),
+                    None => String::new(),
+                }
+            );
+        }
+    }
+    for o in old
+        .iter()
+        .filter(|o| !new.iter().any(|n| n.key() == o.key()))
+    {
+        println!("{}: only in {}", o.key(), opt.old);
+    }
+    println!(
+        "{} regressions beyond {}% of {} against {}",
+        regressions, opt.threshold, opt.new, opt.old
+    );
+    Ok(())
+}
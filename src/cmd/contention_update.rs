@@ -0,0 +1,95 @@
+//! To produce many rollback records to benchmark the effect of collapsing rollbacks.
+//! Write transactions keeps updating a set of keys that reside in different regions.
+//! Read transactions read these keys.
+//!
+//! We check:
+//! (1) there are a lot of rollback records in MVCC (if we use a patched TiKV that doesn't collapse rollbacks)
+//! (2) the read performance degrades as the number of rollback records increases.
+//!
+//! The reads run alone before and after the contended phase, so their
+//! throughput in the three phases can be compared. `--concurrency` is the
+//! number of write workers, `--duration` the duration of each phase.
+use crate::cmd::Common;
+use crate::invariant::InvariantOpts;
+use crate::placement::Placement;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, Scenario};
+use crate::session::{with_isolation, Isolation};
+use crate::workload::contention::{ContentionRead, ContentionUpdate};
+use crate::workload::Workload;
+use crate::Result;
+use clap::Args;
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WRITERS: usize = 15;
+const PHASE_DURATION: Duration = Duration::from_secs(60);
+
+#[derive(Args)]
+pub struct Opt {
+    /// Retry a transaction up to this many times after a retryable error
+    #[clap(long, default_value = "0")]
+    retries: u32,
+    /// Backoff before the first retry in milliseconds, doubled on each retry
+    #[clap(long, default_value = "10")]
+    backoff_ms: u64,
+    /// Number of read workers
+    #[clap(long, default_value = "5")]
+    readers: usize,
+    /// Options of a placement policy to apply to the table before the first
+    /// phase, e.g. 'LEADER_CONSTRAINTS="[+zone=z1]"'
+    #[clap(long)]
+    placement: Option<String>,
+    /// Seconds to wait for the placement to be scheduled
+    #[clap(long, default_value = "600")]
+    placement_timeout: u64,
+    /// Transaction isolation level of the workers, repeatable-read or
+    /// read-committed; the server's default if not given
+    #[clap(long)]
+    isolation: Option<Isolation>,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    let seed = common.seed();
+    println!("seed: {}", seed);
+    let writers = common.concurrency(WRITERS);
+    let pool = with_isolation(MySqlPoolOptions::new(), opt.isolation)
+        .max_connections((writers + opt.readers) as u32 + 1)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let write: Arc<dyn Workload> = Arc::new(ContentionUpdate);
+    let read: Arc<dyn Workload> = Arc::new(ContentionRead);
+    let phase_duration = common.duration(PHASE_DURATION);
+    let mut scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .setup(write.clone())
+        .invariants(opt.invariants)
+        .phase(Phase::new("read-before", phase_duration).with(read.clone(), opt.readers))
+        .phase(
+            Phase::new("contention", phase_duration)
+                .with(write, writers)
+                .with(read.clone(), opt.readers),
+        )
+        .phase(Phase::new("read-after", phase_duration).with(read, opt.readers));
+    if let Some(options) = opt.placement {
+        scenario = scenario.placement(Placement {
+            options,
+            tables: vec!["t".to_owned()],
+            timeout: Duration::from_secs(opt.placement_timeout),
+        });
+    }
+    let retry = RetryPolicy {
+        max_retries: opt.retries,
+        base_backoff: Duration::from_millis(opt.backoff_ms),
+    };
+    for report in run_scenario(&pool, &scenario, seed, retry).await? {
+        println!("{}", report);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cmd::Common;
+use crate::control::{reset_stop_flag, wait_for_stop_flag};
+use crate::output;
+use crate::pd::PdClient;
+use crate::probe::admin_probes;
+use crate::record::Recorder;
+use crate::session::{set_session_alias, set_session_var_sql, set_session_vars};
+use crate::workload::ddl_gen::DdlWeights;
+use crate::workload::ddl_worker;
+use crate::workload::schema::Schema;
+use crate::workload::DML_SESSION_VARS;
+use crate::workload::{dml_worker, DmlOutcome};
+use crate::Result;
+use clap::Args;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use sqlx::Executor;
+use tokio::sync::broadcast::channel;
+
+const RUN_DURATION: Duration = Duration::from_secs(60 * 60 * 24);
+
+#[derive(Args)]
+pub struct Opt {
+    /// Also stop when `bench_control.stop_flag` is set for "dmlddl"
+    #[clap(long)]
+    stop_flag: bool,
+    /// PD address. Print the stores at start and log when one changes state
+    #[clap(long)]
+    pd: Option<String>,
+    /// Time admin statements (SHOW TABLES, information_schema, BEGIN on a new
+    /// connection) every this many seconds
+    #[clap(long)]
+    probe_secs: Option<u64>,
+    /// Relative weights of the DDL the DDL worker picks from, e.g.
+    /// add-index=3,drop-index=3,add-column=1. Kinds: add-index, drop-index,
+    /// add-column, drop-column, modify-column, rename-column, change-charset,
+    /// multi-change (index, a column and a type in one ALTER). All 1 if not
+    /// given
+    #[clap(long)]
+    ddl_weights: Option<DdlWeights>,
+    /// Number of DML workers writing the same rows concurrently
+    #[clap(long, default_value = "1")]
+    dml_workers: usize,
+    /// File to log to
+    #[clap(long, default_value = "dmlddl.log")]
+    log_file: String,
+    /// One of off, error, warn, info, debug, trace
+    #[clap(long, default_value = "info", parse(try_from_str = parse_level))]
+    log_level: LevelFilter,
+    /// Maximum connections of the pool; the DML and DDL workers, the probes
+    /// and the stop flag each hold one. 32 more than --dml-workers if not
+    /// given
+    #[clap(long)]
+    pool_size: Option<u32>,
+    /// Record the statements of the workers to this file, for the replay
+    /// subcommand
+    #[clap(long)]
+    record: Option<String>,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path(&opt.log_file), opt.log_level)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    let options = common.connection.connect_options()?;
+    let run_duration = common.duration(RUN_DURATION);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(opt.pool_size.unwrap_or(opt.dml_workers as u32 + 32))
+        .connect_with(options.clone())
+        .await?;
+    let pool = Arc::new(pool);
+    let pd = opt.pd.as_deref().map(PdClient::new);
+    if let Some(pd) = &pd {
+        for store in pd.stores().await? {
+            info!("{}", store);
+            println!("{}", store);
+        }
+        let pd = pd.clone();
+        tokio::spawn(async move { pd.watch_stores().await });
+    }
+    if let Some(secs) = opt.probe_secs {
+        let pool = pool.clone();
+        tokio::spawn(async move { admin_probes(&pool, &options, Duration::from_secs(secs)).await });
+    }
+    let mut conn2 = pool.acquire().await?;
+    set_session_alias(&mut conn2, "dmlddl-ddl", 0).await?;
+
+    let recorder = opt
+        .record
+        .as_deref()
+        .map(|r| Recorder::create(&output::path(r)))
+        .transpose()?;
+    let ddl_recorder = match &recorder {
+        Some(r) => Some(r.connection(&mut conn2, "dmlddl-ddl-0").await?),
+        None => None,
+    };
+
+    // init
+    let schema = Schema::default();
+    schema.create_table(&mut conn2).await?;
+    if let Some(r) = &ddl_recorder {
+        for sql in schema.create_table_sql() {
+            r.record(&sql);
+        }
+    }
+    if let Some(pd) = &pd {
+        pd.report_table_regions(&mut conn2, &schema.quoted_table())
+            .await;
+    }
+    if opt.stop_flag {
+        reset_stop_flag(&mut conn2, "dmlddl").await?;
+    }
+    conn2.execute("set @@tidb_general_log=1").await?; // ensure partition is supported
+    let weights = opt.ddl_weights.unwrap_or_default();
+    let (tx, rx2) = channel(1);
+    let mut dml_handles = Vec::new();
+    for i in 0..opt.dml_workers {
+        let mut conn = pool.acquire().await?;
+        set_session_alias(&mut conn, "dmlddl-dml", i).await?;
+        set_session_vars(&mut conn, DML_SESSION_VARS).await?; // ensure assertion is supported
+        let dml_recorder = match &recorder {
+            Some(r) => {
+                let r = r
+                    .connection(&mut conn, &format!("dmlddl-dml-{}", i))
+                    .await?;
+                for (name, value) in DML_SESSION_VARS {
+                    r.record(&set_session_var_sql(name, value));
+                }
+                Some(r)
+            }
+            None => None,
+        };
+        let rx = tx.subscribe();
+        let schema = schema.clone();
+        dml_handles.push(tokio::spawn(async move {
+            dml_worker(&mut conn, rx, &schema, dml_recorder).await
+        }));
+    }
+    let h2 = {
+        let schema = schema.clone();
+        tokio::spawn(async move {
+            ddl_worker(&mut conn2, rx2, seed, weights, &schema, ddl_recorder).await
+        })
+    };
+    let bar = ProgressBar::new(run_duration.as_secs()).with_style(
+        ProgressStyle::with_template("[{elapsed_precise}] {wide_bar} remaining {eta}").unwrap(),
+    );
+    let ticker = {
+        let bar = bar.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                bar.inc(1);
+            }
+        })
+    };
+    {
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = tokio::time::sleep(run_duration) => {}
+                _ = wait_for_stop_flag(&pool, "dmlddl"), if opt.stop_flag => {}
+                _ = tokio::signal::ctrl_c() => info!("interrupted"),
+            }
+            tx.send(()).unwrap();
+        });
+    }
+
+    let mut outcome = DmlOutcome::default();
+    for h in dml_handles {
+        outcome.merge(h.await.unwrap()?);
+    }
+    h2.await.unwrap()?;
+    if let Some(r) = &recorder {
+        r.flush()?;
+    }
+    ticker.abort();
+    bar.abandon();
+    info!("{}", outcome.warnings);
+    println!("{}", outcome.warnings);
+    if outcome.conflicts.total() > 0 {
+        info!("skipped conflicting DML {}", outcome.conflicts);
+        println!("skipped conflicting DML {}", outcome.conflicts);
+    }
+    let mut conn = pool.acquire().await?;
+    schema.verify(&mut conn, !outcome.left_rows).await?;
+    info!("table verified");
+    println!("table verified");
+    if let Some(pd) = &pd {
+        pd.report_table_regions(&mut conn, &schema.quoted_table())
+            .await;
+    }
+    Ok(())
+}
+
+fn parse_level(s: &str) -> std::result::Result<LevelFilter, String> {
+    s.parse().map_err(|_| format!("unknown log level {}", s))
+}
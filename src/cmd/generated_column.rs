@@ -0,0 +1,63 @@
+//! Updates of the base columns of a table while a DDL worker adds and drops
+//! virtual generated columns and indexes on generated columns, then ADMIN
+//! CHECK and a check that every generated column equals its expression.
+//! `--concurrency` is the number of DML workers.
+use crate::cmd::Common;
+use crate::invariant::InvariantOpts;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, Scenario};
+use crate::workload::generated::{GenDdl, GenDml, GenTable};
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WORKERS: usize = 8;
+const RUN_DURATION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Args)]
+pub struct Opt {
+    /// DDL statements per second
+    #[clap(long, default_value = "0.2")]
+    ddl_rate: f64,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("generated_column.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let workers = common.concurrency(WORKERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(workers as u32 + 3)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let table = Arc::new(GenTable::default());
+    let scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .invariants(opt.invariants)
+        .phase(
+            Phase::new("generated-column", common.duration(RUN_DURATION))
+                .with(Arc::new(GenDml(table.clone())), workers)
+                .with_rate(Arc::new(GenDdl(table.clone())), 1, opt.ddl_rate),
+        );
+    // DML fails with "schema changed" when it overlaps a DDL
+    let retry = RetryPolicy {
+        max_retries: 3,
+        base_backoff: Duration::from_millis(10),
+    };
+    for report in run_scenario(&pool, &scenario, seed, retry).await? {
+        println!("{}", report);
+    }
+    let mut conn = pool.acquire().await?;
+    table.verify(&mut conn).await?;
+    println!("gen_t verified");
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! Single-row inserts with the primary key from AUTO_INCREMENT, from
+//! client-side scattering, from AUTO_RANDOM or a random string, one phase
+//! per `--pk`, to compare them. `--concurrency` is the number of
+//! workers, `--duration` the duration of each phase.
+use crate::cmd::Common;
+use crate::invariant::InvariantOpts;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, Scenario};
+use crate::workload::insert::{Insert, PkKind};
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WORKERS: usize = 32;
+const PHASE_DURATION: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Args)]
+pub struct Opt {
+    /// One of auto-increment, scattered, auto-random, common-handle; can be
+    /// repeated to run them one after another
+    #[clap(long = "pk", default_value = "auto-random")]
+    pks: Vec<PkKind>,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("insert.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let workers = common.concurrency(WORKERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(workers as u32 + 2)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let inserts: Vec<Arc<Insert>> = opt
+        .pks
+        .iter()
+        .map(|&pk| Arc::new(Insert::new(pk)))
+        .collect();
+    let mut scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .invariants(opt.invariants);
+    for insert in &inserts {
+        scenario = scenario.phase(
+            Phase::new(insert.table(), common.duration(PHASE_DURATION))
+                .with(insert.clone(), workers),
+        );
+    }
+    let retry = RetryPolicy {
+        max_retries: 0,
+        base_backoff: Duration::ZERO,
+    };
+    for report in run_scenario(&pool, &scenario, seed, retry).await? {
+        println!("{}", report);
+    }
+    let mut conn = pool.acquire().await?;
+    for insert in &inserts {
+        insert.verify(&mut conn).await?;
+        println!("{} verified", insert.table());
+    }
+    Ok(())
+}
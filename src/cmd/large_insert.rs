@@ -1,20 +1,22 @@
-/// load SQLs from a file. Execute them in a large SQL.
-use dmlddl::Result;
+//! load SQLs from a file. Execute them in a large SQL.
+use crate::cmd::Common;
+use crate::session::set_session_alias;
+use crate::Result;
 use sqlx::mysql::MySqlPoolOptions;
 use sqlx::Executor;
 use std::fs::File;
 use std::io::BufRead;
 use std::sync::Arc;
 
-#[tokio::main]
-async fn main() -> Result<()> {
+pub async fn run(common: &Common) -> Result<()> {
     let pool = MySqlPoolOptions::new()
         .max_connections(500)
-        .connect("mysql://root@172.16.5.181:4000/test")
+        .connect_with(common.connection.connect_options()?)
         .await?;
     let pool = Arc::new(pool);
 
     let mut conn = pool.acquire().await?;
+    set_session_alias(&mut conn, "large-insert", 0).await?;
     conn.execute("use credit_card").await?;
     conn.execute("drop table if exists T_CUSTOMER").await?;
 
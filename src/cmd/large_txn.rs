@@ -0,0 +1,64 @@
+//! Transactions of `--rows` rows each with point reads running alongside,
+//! reporting the commit durations, size and memory limit errors and the
+//! read latency. `--concurrency` is the number of writers.
+use crate::cmd::Common;
+use crate::invariant::InvariantOpts;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, Scenario};
+use crate::workload::large_txn::{LargeTxn, LargeTxnRead};
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WRITERS: usize = 1;
+const RUN_DURATION: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Args)]
+pub struct Opt {
+    /// Rows written by each transaction
+    #[clap(long, default_value = "100000")]
+    rows: u64,
+    /// Number of point read workers
+    #[clap(long, default_value = "4")]
+    readers: usize,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("large_txn.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let writers = common.concurrency(WRITERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections((writers + opt.readers) as u32 + 2)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let txn = Arc::new(LargeTxn::new(opt.rows));
+    let scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .invariants(opt.invariants)
+        .phase(
+            Phase::new("large-txn", common.duration(RUN_DURATION))
+                .with(txn.clone(), writers)
+                .with(Arc::new(LargeTxnRead(txn.clone())), opt.readers),
+        );
+    let retry = RetryPolicy {
+        max_retries: 0,
+        base_backoff: Duration::ZERO,
+    };
+    for report in run_scenario(&pool, &scenario, seed, retry).await? {
+        println!("{}", report);
+    }
+    let msg = txn.commit_summary();
+    info!("{}", msg);
+    println!("{}", msg);
+    Ok(())
+}
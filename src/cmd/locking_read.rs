@@ -0,0 +1,60 @@
+//! Queue-style `SELECT ... FOR UPDATE NOWAIT` or `SKIP LOCKED` under
+//! contention, reporting how many rows were acquired and how many skipped or
+//! failed on a lock. `--concurrency` is the number of workers.
+use crate::cmd::Common;
+use crate::invariant::InvariantOpts;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, Scenario};
+use crate::workload::locking::{LockMode, LockingRead};
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WORKERS: usize = 16;
+const RUN_DURATION: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Args)]
+pub struct Opt {
+    /// nowait or skip-locked
+    #[clap(long, default_value = "nowait")]
+    mode: LockMode,
+    /// Lock the next rows from a random id instead of a single row
+    #[clap(long)]
+    range: bool,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("locking_read.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let workers = common.concurrency(WORKERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(workers as u32 + 2)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let read = Arc::new(LockingRead::new(opt.mode, opt.range));
+    let scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .invariants(opt.invariants)
+        .phase(
+            Phase::new("locking-read", common.duration(RUN_DURATION)).with(read.clone(), workers),
+        );
+    let retry = RetryPolicy {
+        max_retries: 0,
+        base_backoff: Duration::ZERO,
+    };
+    for report in run_scenario(&pool, &scenario, seed, retry).await? {
+        println!("{}", report);
+    }
+    println!("{}", read.outcomes);
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Writes of values around the limits of TINYINT and VARCHAR(8) while a DDL
+//! worker narrows an INT column to TINYINT and a VARCHAR(64) to VARCHAR(8)
+//! and back. Afterwards ADMIN CHECK, and every row must hold what was last
+//! written to it. Writes and changes rejected because a value doesn't fit are
+//! counted. `--concurrency` is the number of DML workers.
+use crate::cmd::Common;
+use crate::invariant::InvariantOpts;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, Scenario};
+use crate::workload::lossy::{LossyDdl, LossyDml, LossyTable};
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WORKERS: usize = 8;
+const RUN_DURATION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Args)]
+pub struct Opt {
+    /// Column type changes per second
+    #[clap(long, default_value = "0.2")]
+    ddl_rate: f64,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("lossy_change.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let workers = common.concurrency(WORKERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(workers as u32 + 3)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let table = Arc::new(LossyTable::default());
+    let scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .invariants(opt.invariants)
+        .phase(
+            Phase::new("lossy-change", common.duration(RUN_DURATION))
+                .with(Arc::new(LossyDml(table.clone())), workers)
+                .with_rate(Arc::new(LossyDdl(table.clone())), 1, opt.ddl_rate),
+        );
+    // DML fails with "schema changed" when it overlaps a DDL
+    let retry = RetryPolicy {
+        max_retries: 3,
+        base_backoff: Duration::from_millis(10),
+    };
+    for report in run_scenario(&pool, &scenario, seed, retry).await? {
+        println!("{}", report);
+    }
+    let mut conn = pool.acquire().await?;
+    table.verify(&mut conn).await?;
+    println!("lossy_t verified");
+    let msg = format!(
+        "type changes applied: {}, rejected: {}; writes rejected: {}",
+        table.applied_changes.load(Ordering::SeqCst),
+        table.rejected_changes.load(Ordering::SeqCst),
+        table.rejected_writes.load(Ordering::SeqCst)
+    );
+    info!("{}", msg);
+    println!("{}", msg);
+    Ok(())
+}
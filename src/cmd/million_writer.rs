@@ -0,0 +1,287 @@
+// write a million rows.
+// the i-th row: <i 2*i>
+// worker w of n writes batches w, w + n, w + 2 * n, ..., n is --concurrency
+// The number of batches each worker has finished is saved to STATE_FILE, in
+// the run's directory if it has one, so a load that fails partway can be
+// continued with --resume, the same n and the same --run-name.
+// --verify-only checks a previously loaded table instead of loading it: the
+// schema, the row count, and the sums of VERIFY_SAMPLES random batches.
+
+use crate::cmd::Common;
+use crate::error::{ErrorClass, MyError};
+use crate::metrics::RunStats;
+use crate::output;
+use crate::session::set_session_alias;
+use crate::Result;
+use clap::Args;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use log::{error, info, LevelFilter};
+use rand::Rng;
+use sqlx::mysql::{MySqlConnection, MySqlPoolOptions};
+use sqlx::{Executor, Row};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const NUM_WORKERS: usize = 32;
+const BATCH_SIZE: u32 = 100;
+const NUM_BATCHES: u32 = 10_000_000 / BATCH_SIZE;
+const STATE_FILE: &str = "million_writer.state";
+const VERIFY_SAMPLES: usize = 100;
+
+#[derive(Args)]
+pub struct Opt {
+    /// Continue from the progress in million_writer.state instead of recreating the table
+    #[clap(long)]
+    resume: bool,
+    /// Only check that the table is fully loaded, failing if it is not
+    #[clap(long, conflicts_with = "resume")]
+    verify_only: bool,
+}
+
+type Progress = Arc<Vec<AtomicU32>>;
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("million_writer.log"), LevelFilter::Info)?;
+    let workers = common.concurrency(NUM_WORKERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(500)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+    let pool = Arc::new(pool);
+    if opt.verify_only {
+        let mut conn = pool.acquire().await?;
+        return verify(&mut conn).await;
+    }
+
+    let progress: Progress = if opt.resume {
+        Arc::new(load_progress(workers)?)
+    } else {
+        let mut conn = pool.acquire().await?;
+        conn.execute("drop table if exists t").await?;
+        conn.execute("create table t(a int primary key, b int)")
+            .await?;
+        Arc::new((0..workers).map(|_| AtomicU32::new(0)).collect())
+    };
+    // the last batch of a worker may have been committed after the last checkpoint
+    let insert = if opt.resume {
+        "insert ignore"
+    } else {
+        "insert"
+    };
+
+    let checkpoint = {
+        let progress = progress.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                if let Err(e) = save_progress(&progress) {
+                    error!("failed to save progress: {:?}", e);
+                }
+            }
+        })
+    };
+
+    let bars = MultiProgress::new();
+    let style = ProgressStyle::with_template(
+        "{prefix:>9} [{elapsed_precise}] {wide_bar} {pos}/{len} ETA {eta}",
+    )
+    .unwrap();
+    let done: u32 = progress.iter().map(|n| n.load(Ordering::SeqCst)).sum();
+    let total = bars.add(
+        ProgressBar::new((NUM_BATCHES * BATCH_SIZE) as u64)
+            .with_style(style.clone())
+            .with_prefix("rows")
+            .with_position((done * BATCH_SIZE) as u64),
+    );
+
+    let start = Instant::now();
+    let mut handles = Vec::new();
+    for i in 0..workers {
+        let acquire_begin = Instant::now();
+        let mut conn = pool.acquire().await?;
+        let acquired = acquire_begin.elapsed();
+        set_session_alias(&mut conn, "million-writer", i).await?;
+        let progress = progress.clone();
+        let total = total.clone();
+        let batches = (NUM_BATCHES - i as u32).div_ceil(workers as u32);
+        let bar = bars.add(
+            ProgressBar::new(batches as u64)
+                .with_style(style.clone())
+                .with_prefix(format!("worker {}", i))
+                .with_position(progress[i].load(Ordering::SeqCst) as u64),
+        );
+        handles.push(tokio::spawn(async move {
+            let start = Instant::now();
+            let mut stats = RunStats::default();
+            stats.acquire.record(acquired);
+            let res = loop {
+                let x = i as u32 + progress[i].load(Ordering::SeqCst) * workers as u32;
+                if x >= NUM_BATCHES {
+                    bar.finish();
+                    break Ok(());
+                }
+                let begin = Instant::now();
+                let res = conn
+                    .execute(
+                        format!(
+                            "{} into t values {}",
+                            insert,
+                            (0..BATCH_SIZE)
+                                .map(|y| format!(
+                                    "({}, {})",
+                                    x * BATCH_SIZE + y,
+                                    (x * BATCH_SIZE + y) * 2
+                                ))
+                                .collect::<Vec<String>>()
+                                .join(","),
+                        )
+                        .as_str(),
+                    )
+                    .await;
+                if let Err(e) = res {
+                    stats.errors.add(ErrorClass::of(&e));
+                    break Err(e);
+                }
+                stats.succeeded += 1;
+                stats.latencies.record(begin.elapsed());
+                progress[i].fetch_add(1, Ordering::SeqCst);
+                bar.inc(1);
+                total.inc(BATCH_SIZE as u64);
+            };
+            stats.elapsed = start.elapsed();
+            stats.latencies.sort();
+            (stats, res)
+        }));
+    }
+    let mut res: Result<()> = Ok(());
+    let mut all = RunStats::default();
+    for (i, handle) in handles.into_iter().enumerate() {
+        let (stats, worker_res) = handle.await.expect("spawn failed");
+        info!("worker {} batches: {}", i, stats);
+        if let Err(e) = worker_res {
+            error!("insert failed: {:?}", e);
+            res = Err(e.into());
+        }
+        all.merge(stats);
+    }
+    all.elapsed = start.elapsed();
+    all.latencies.sort();
+    all.acquire.sort();
+    total.finish();
+    let msg = format!(
+        "batches: {}\nconnection acquire: {}\nrows/s: {:.0}\n{}",
+        all,
+        all.acquire,
+        all.succeeded as f64 * BATCH_SIZE as f64 / all.elapsed.as_secs_f64(),
+        all.errors
+    );
+    info!("{}", msg);
+    println!("{}", msg);
+    checkpoint.abort();
+    save_progress(&progress)?;
+    res?;
+    let mut conn = pool.acquire().await?;
+    verify(&mut conn).await
+}
+
+async fn verify(conn: &mut MySqlConnection) -> Result<()> {
+    let columns: Vec<(String, String, String)> = conn
+        .fetch_all(
+            "select column_name, data_type, column_key from information_schema.columns \
+            where table_schema = database() and table_name = 't' order by ordinal_position",
+        )
+        .await?
+        .iter()
+        .map(|row| {
+            Ok((
+                row.try_get_unchecked(0)?,
+                row.try_get_unchecked(1)?,
+                row.try_get_unchecked(2)?,
+            ))
+        })
+        .collect::<std::result::Result<_, sqlx::Error>>()?;
+    let expected = [("a", "int", "PRI"), ("b", "int", "")];
+    if columns.len() != expected.len()
+        || columns
+            .iter()
+            .zip(expected)
+            .any(|((n, t, k), (en, et, ek))| n != en || t != et || k != ek)
+    {
+        return fail(format!("unexpected schema of t: {:?}", columns));
+    }
+
+    let rows = number(conn, "select count(*) from t").await?;
+    if rows != (NUM_BATCHES * BATCH_SIZE) as u64 {
+        return fail(format!(
+            "t has {} rows, expected {}",
+            rows,
+            NUM_BATCHES * BATCH_SIZE
+        ));
+    }
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..VERIFY_SAMPLES {
+        let x = rng.gen_range(0..NUM_BATCHES) as u64;
+        let (lo, hi) = (x * BATCH_SIZE as u64, (x + 1) * BATCH_SIZE as u64 - 1);
+        let where_ = format!("from t where a between {} and {}", lo, hi);
+        let sum_a = number(conn, &format!("select sum(a) {}", where_)).await?;
+        let sum_b = number(conn, &format!("select sum(b) {}", where_)).await?;
+        let expected = (lo + hi) * BATCH_SIZE as u64 / 2;
+        if sum_a != expected || sum_b != 2 * expected {
+            return fail(format!(
+                "batch {}: sum(a) = {}, sum(b) = {}, expected {} and {}",
+                x,
+                sum_a,
+                sum_b,
+                expected,
+                2 * expected
+            ));
+        }
+    }
+    info!("t verified");
+    println!("t verified");
+    Ok(())
+}
+
+async fn number(conn: &mut MySqlConnection, sql: &str) -> Result<u64> {
+    let value: Option<String> = conn.fetch_one(sql).await?.try_get_unchecked(0)?;
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(0))
+}
+
+fn fail(msg: String) -> Result<()> {
+    error!("{}", msg);
+    Err(MyError::StringError(msg))
+}
+
+fn load_progress(workers: usize) -> Result<Vec<AtomicU32>> {
+    let path = output::path(STATE_FILE);
+    let state = std::fs::read_to_string(&path)?;
+    let progress = state
+        .split_whitespace()
+        .map(|n| n.parse().map(AtomicU32::new))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| MyError::StringError(format!("invalid {}: {}", path, e)))?;
+    if progress.len() != workers {
+        return Err(MyError::StringError(format!(
+            "{} has {} workers, expected {}",
+            path,
+            progress.len(),
+            workers
+        )));
+    }
+    Ok(progress)
+}
+
+fn save_progress(progress: &[AtomicU32]) -> Result<()> {
+    let state = progress
+        .iter()
+        .map(|n| n.load(Ordering::SeqCst).to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let path = output::path(STATE_FILE);
+    let tmp = format!("{}.tmp", path);
+    std::fs::write(&tmp, state)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
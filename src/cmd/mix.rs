@@ -0,0 +1,62 @@
+//! Point inserts, updates, deletes and selects blended in every worker by
+//! `--mix`. `--concurrency` is the number of workers.
+use crate::cmd::Common;
+use crate::invariant::InvariantOpts;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, Scenario};
+use crate::workload::mix::{Mix, OpWeights};
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WORKERS: usize = 16;
+const RUN_DURATION: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Args)]
+pub struct Opt {
+    /// Relative weights of the operations each worker picks from. Operations:
+    /// insert, update, delete, select
+    #[clap(long, default_value = "insert=30,update=50,delete=10,select=10")]
+    mix: OpWeights,
+    /// Retry an operation up to this many times after a retryable error
+    #[clap(long, default_value = "0")]
+    retries: u32,
+    /// Backoff before the first retry in milliseconds, doubled on each retry
+    #[clap(long, default_value = "10")]
+    backoff_ms: u64,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("mix.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let workers = common.concurrency(WORKERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(workers as u32 + 2)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .invariants(opt.invariants)
+        .phase(
+            Phase::new("mix", common.duration(RUN_DURATION))
+                .with(Arc::new(Mix::new(&opt.mix)), workers),
+        );
+    let retry = RetryPolicy {
+        max_retries: opt.retries,
+        base_backoff: Duration::from_millis(opt.backoff_ms),
+    };
+    for report in run_scenario(&pool, &scenario, seed, retry).await? {
+        println!("{}", report);
+    }
+    Ok(())
+}
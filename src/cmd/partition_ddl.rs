@@ -0,0 +1,63 @@
+//! DML on a range-partitioned table while a DDL worker adds, drops, truncates
+//! and exchanges its partitions, then ADMIN CHECK and a check that every
+//! partition only holds rows of its range. `--concurrency` is the number of
+//! DML workers.
+use crate::cmd::Common;
+use crate::invariant::InvariantOpts;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, Scenario};
+use crate::workload::partition::{PartitionDdl, PartitionDml, PartitionTable};
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WORKERS: usize = 8;
+const RUN_DURATION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Args)]
+pub struct Opt {
+    /// Partition DDL statements per second
+    #[clap(long, default_value = "0.2")]
+    ddl_rate: f64,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("partition_ddl.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let workers = common.concurrency(WORKERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(workers as u32 + 3)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let table = Arc::new(PartitionTable::default());
+    let scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .invariants(opt.invariants)
+        .phase(
+            Phase::new("partition-ddl", common.duration(RUN_DURATION))
+                .with(Arc::new(PartitionDml(table.clone())), workers)
+                .with_rate(Arc::new(PartitionDdl(table.clone())), 1, opt.ddl_rate),
+        );
+    // DML fails with "schema changed" when it overlaps a DDL
+    let retry = RetryPolicy {
+        max_retries: 3,
+        base_backoff: Duration::from_millis(10),
+    };
+    for report in run_scenario(&pool, &scenario, seed, retry).await? {
+        println!("{}", report);
+    }
+    let mut conn = pool.acquire().await?;
+    table.verify(&mut conn).await?;
+    println!("part_t verified");
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! Inserts and updates on a range-partitioned table while a DDL worker keeps
+//! splitting and merging its partitions with REORGANIZE PARTITION, then
+//! ADMIN CHECK and a comparison of the rows of every partition with the ids
+//! inserted into its range. `--concurrency` is the number of DML workers.
+use crate::cmd::Common;
+use crate::invariant::InvariantOpts;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, Scenario};
+use crate::workload::reorganize::{ReorgDdl, ReorgDml, ReorgTable};
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WORKERS: usize = 8;
+const RUN_DURATION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Args)]
+pub struct Opt {
+    /// REORGANIZE PARTITION statements per second; each copies the rows of
+    /// one or two partitions
+    #[clap(long, default_value = "0.1")]
+    ddl_rate: f64,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("reorganize_partition.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let workers = common.concurrency(WORKERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(workers as u32 + 3)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let table = Arc::new(ReorgTable::default());
+    let scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .invariants(opt.invariants)
+        .phase(
+            Phase::new("reorganize-partition", common.duration(RUN_DURATION))
+                .with(Arc::new(ReorgDml(table.clone())), workers)
+                .with_rate(Arc::new(ReorgDdl(table.clone())), 1, opt.ddl_rate),
+        );
+    // DML fails with "schema changed" when it overlaps a DDL
+    let retry = RetryPolicy {
+        max_retries: 3,
+        base_backoff: Duration::from_millis(10),
+    };
+    for report in run_scenario(&pool, &scenario, seed, retry).await? {
+        println!("{}", report);
+    }
+    let mut conn = pool.acquire().await?;
+    table.verify(&mut conn).await?;
+    println!("reorg_t verified");
+    Ok(())
+}
@@ -0,0 +1,75 @@
+//! Re-execute a recording made with `--record`, see [`crate::record`]. Every
+//! recorded connection gets a connection of its own that issues its
+//! statements in order, each no earlier than it was recorded relative to the
+//! start. Timing is best effort: a statement waiting for a lock delays the
+//! rest of its connection's statements.
+use crate::cmd::Common;
+use crate::error::{ErrorClass, ErrorCounts};
+use crate::output;
+use crate::record::read;
+use crate::Result;
+use clap::Args;
+use log::{error, info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use sqlx::Executor;
+use std::collections::BTreeMap;
+use tokio::time::Instant;
+
+#[derive(Args)]
+pub struct Opt {
+    /// The recording
+    file: String,
+    /// Replay this many times faster than recorded
+    #[clap(long, default_value = "1")]
+    speed: f64,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("replay.log"), LevelFilter::Info)?;
+    let mut connections: BTreeMap<String, Vec<_>> = BTreeMap::new();
+    for statement in read(&opt.file)? {
+        connections
+            .entry(statement.connection.clone())
+            .or_default()
+            .push(statement);
+    }
+    info!("replaying {} connections", connections.len());
+    let pool = MySqlPoolOptions::new()
+        .max_connections(connections.len() as u32)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let start = Instant::now();
+    let mut handles = Vec::new();
+    for (connection, statements) in connections {
+        let mut conn = pool.acquire().await?;
+        let speed = opt.speed;
+        handles.push(tokio::spawn(async move {
+            let mut errors = ErrorCounts::default();
+            for statement in &statements {
+                tokio::time::sleep_until(start + statement.at.div_f64(speed)).await;
+                if let Err(e) = conn.execute(statement.sql.as_str()).await {
+                    let class = ErrorClass::of(&e);
+                    if class == ErrorClass::Assertion {
+                        error!("{}: `{}`: {:?}", connection, statement.sql, e);
+                    } else {
+                        info!("{}: `{}`: {:?}", connection, statement.sql, e);
+                    }
+                    errors.add(class);
+                }
+            }
+            (statements.len(), errors)
+        }));
+    }
+    let mut statements = 0;
+    let mut errors = ErrorCounts::default();
+    for h in handles {
+        let (n, e) = h.await.unwrap();
+        statements += n;
+        errors.merge(&e);
+    }
+    let msg = format!("replayed {} statements, {}", statements, errors);
+    info!("{}", msg);
+    println!("{}", msg);
+    Ok(())
+}
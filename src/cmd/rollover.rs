@@ -0,0 +1,167 @@
+//! Steady-state soak: keep inserting new rows while deleting rows older than a
+//! sliding window, so the table size stays roughly constant for days.
+//!
+//! Every report interval we log the insert p99 of that interval and its drift
+//! from the first interval, and optionally the SELECT 1 round trip of an idle
+//! connection so network jitter can be told apart from the server.
+//! `--concurrency` is the number of inserters.
+use crate::cmd::Common;
+use crate::metrics::Latencies;
+use crate::output;
+use crate::probe::ping;
+use crate::rng::worker_rng;
+use crate::session::set_session_alias;
+use crate::Result;
+use clap::Args;
+use log::{info, warn, LevelFilter};
+use rand::distributions::Alphanumeric;
+use rand::rngs::SmallRng;
+use rand::Rng;
+use sqlx::mysql::MySqlPoolOptions;
+use sqlx::{query, Executor};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+const DELETE_BATCH: u64 = 10_000;
+const INSERTERS: usize = 16;
+const RUN_DURATION: Duration = Duration::from_secs(60 * 60 * 24 * 3);
+
+#[derive(Args)]
+pub struct Opt {
+    /// Rows older than this many seconds are deleted
+    #[clap(long, default_value = "3600")]
+    window_secs: u64,
+    /// Seconds between p99 reports
+    #[clap(long, default_value = "60")]
+    report_secs: u64,
+    /// Time SELECT 1 on an idle connection every this many milliseconds and
+    /// report it next to the insert latency
+    #[clap(long)]
+    ping_ms: Option<u64>,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("rollover.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    let inserters = common.concurrency(INSERTERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(inserters as u32 + 3)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let mut conn = pool.acquire().await?;
+    conn.execute("drop table if exists rollover").await?;
+    conn.execute(
+        "create table rollover ( \
+        id bigint not null auto_increment primary key, \
+        ts timestamp(3) not null default current_timestamp(3), \
+        v varchar(255), \
+        key rollover_ts(ts) \
+        )",
+    )
+    .await?;
+    drop(conn);
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    for i in 0..inserters {
+        let mut conn = pool.acquire().await?;
+        set_session_alias(&mut conn, "rollover-insert", i).await?;
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let mut rng: SmallRng = worker_rng(seed, i);
+            loop {
+                let v: String = (&mut rng)
+                    .sample_iter(&Alphanumeric)
+                    .take(64)
+                    .map(char::from)
+                    .collect();
+                let start = Instant::now();
+                match conn
+                    .execute(query("insert into rollover (v) values (?)").bind(v))
+                    .await
+                {
+                    Ok(_) => {
+                        if tx.send(start.elapsed()).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => warn!("insert failed: {:?}", e),
+                }
+            }
+        });
+    }
+    drop(tx);
+
+    let mut conn = pool.acquire().await?;
+    set_session_alias(&mut conn, "rollover-delete", 0).await?;
+    let window = opt.window_secs;
+    tokio::spawn(async move {
+        loop {
+            let res = conn
+                .execute(
+                    query("delete from rollover where ts < now(3) - interval ? second limit ?")
+                        .bind(window)
+                        .bind(DELETE_BATCH),
+                )
+                .await;
+            match res {
+                // keep deleting while there is a backlog
+                Ok(r) if r.rows_affected() == DELETE_BATCH => continue,
+                Ok(_) => {}
+                Err(e) => warn!("delete failed: {:?}", e),
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+
+    let (ping_tx, mut ping_rx) = mpsc::unbounded_channel();
+    if let Some(ms) = opt.ping_ms {
+        let mut conn = pool.acquire().await?;
+        set_session_alias(&mut conn, "rollover-ping", 0).await?;
+        tokio::spawn(async move { ping(&mut conn, Duration::from_millis(ms), ping_tx).await });
+    }
+
+    let end = Instant::now() + common.duration(RUN_DURATION);
+    let mut interval = tokio::time::interval(Duration::from_secs(opt.report_secs));
+    interval.tick().await;
+    let mut latencies = Latencies::default();
+    let mut pings = Latencies::default();
+    let mut baseline = None;
+    while Instant::now() < end {
+        tokio::select! {
+            Some(latency) = rx.recv() => latencies.record(latency),
+            Some(latency) = ping_rx.recv() => pings.record(latency),
+            _ = interval.tick() => {
+                if latencies.is_empty() {
+                    warn!("no inserts in the last interval");
+                    continue;
+                }
+                latencies.sort();
+                let p99 = latencies.percentile(99.0).unwrap();
+                let baseline = *baseline.get_or_insert(p99);
+                let mut msg = format!(
+                    "inserts: {}, p99: {:?}, drift: {:+.1}%",
+                    latencies.len(),
+                    p99,
+                    (p99.as_secs_f64() / baseline.as_secs_f64() - 1.0) * 100.0
+                );
+                if !pings.is_empty() {
+                    pings.sort();
+                    let rtt = pings.percentile(50.0).unwrap();
+                    msg += &format!(
+                        ", ping p50: {:?}, ping p99: {:?}, p99 minus ping: {:?}",
+                        rtt,
+                        pings.percentile(99.0).unwrap(),
+                        p99.saturating_sub(rtt)
+                    );
+                    pings.clear();
+                }
+                info!("{}", msg);
+                println!("{}", msg);
+                latencies.clear();
+            }
+        }
+    }
+    Ok(())
+}
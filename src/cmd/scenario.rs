@@ -0,0 +1,48 @@
+//! Run a scenario file, see [`crate::scenario::file`]. `--duration` is the
+//! duration of phases that don't give one.
+use crate::cmd::Common;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::scenario::file::load;
+use crate::scenario::run as run_scenario;
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::time::Duration;
+
+const PHASE_DURATION: Duration = Duration::from_secs(60);
+
+#[derive(Args)]
+pub struct Opt {
+    /// The scenario file
+    file: String,
+    /// Retry a transaction up to this many times after a retryable error
+    #[clap(long, default_value = "0")]
+    retries: u32,
+    /// Backoff before the first retry in milliseconds, doubled on each retry
+    #[clap(long, default_value = "10")]
+    backoff_ms: u64,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("scenario.log"), LevelFilter::Info)?;
+    let scenario = load(&opt.file, common.duration(PHASE_DURATION))?
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode);
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(500)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+    let retry = RetryPolicy {
+        max_retries: opt.retries,
+        base_backoff: Duration::from_millis(opt.backoff_ms),
+    };
+    for report in run_scenario(&pool, &scenario, seed, retry).await? {
+        println!("{}", report);
+    }
+    Ok(())
+}
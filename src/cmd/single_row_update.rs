@@ -0,0 +1,74 @@
+//! to reproduce https://github.com/pingcap/tidb/issues/25659, https://github.com/pingcap/tidb/issues/33393
+//!
+//! Stops at the first error. Runs one updater and until an error or Ctrl-C
+//! unless `--concurrency` or `--duration` say otherwise.
+use crate::cmd::Common;
+use crate::error::MyError;
+use crate::invariant::InvariantOpts;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::runner::{run as run_workload, RunOptions};
+use crate::session::{with_isolation, Isolation};
+use crate::workload::single_row::SingleRowUpdate;
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Args)]
+pub struct Opt {
+    /// Updates per second over all updaters; as fast as possible if not given
+    #[clap(long)]
+    rate: Option<f64>,
+    /// Transaction isolation level of the workers, repeatable-read or
+    /// read-committed; the server's default if not given
+    #[clap(long)]
+    isolation: Option<Isolation>,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    if let Some(rate) = opt.rate.filter(|rate| !rate.is_finite() || *rate <= 0.0) {
+        return Err(MyError::StringError(format!(
+            "--rate must be positive, got {}",
+            rate
+        )));
+    }
+    simple_logging::log_to_file(output::path("single_row_update.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let pool = with_isolation(MySqlPoolOptions::new(), opt.isolation)
+        .max_connections(common.concurrency(1) as u32 + 1)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let stats = run_workload(
+        &pool,
+        Arc::new(SingleRowUpdate::default()),
+        &RunOptions {
+            concurrency: common.concurrency(1),
+            iterations: None,
+            duration: common.duration.map(Duration::from_secs),
+            rate: opt.rate,
+            seed,
+            retry: RetryPolicy {
+                max_retries: 0,
+                base_backoff: Duration::ZERO,
+            },
+            fail_fast: true,
+            stop: None,
+            invariants: opt.invariants,
+            live: None,
+            measure: common.measure_window()?,
+            connection_mode: common.connection.connection_mode,
+        },
+    )
+    .await?;
+    println!("{}", stats);
+    println!("{}", stats.statements);
+    Ok(())
+}
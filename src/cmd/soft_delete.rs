@@ -0,0 +1,70 @@
+//! Soft deletes with filtered reads and a periodic hard-delete cleanup, to
+//! watch read latency while tombstones accumulate and get collected.
+//! `--concurrency` is the number of writers.
+use crate::cmd::Common;
+use crate::invariant::InvariantOpts;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, Scenario};
+use crate::workload::soft_delete::{SoftDeleteCleanup, SoftDeleteRead, SoftDeleteWrite};
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WRITERS: usize = 8;
+const RUN_DURATION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Args)]
+pub struct Opt {
+    /// Number of readers
+    #[clap(long, default_value = "4")]
+    readers: usize,
+    /// Hard-delete batches per second
+    #[clap(long, default_value = "1")]
+    cleanup_rate: f64,
+    /// Tombstones are hard-deleted once they are this many seconds old
+    #[clap(long, default_value = "600")]
+    retention_secs: u64,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("soft_delete.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let writers = common.concurrency(WRITERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections((writers + opt.readers) as u32 + 2)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .invariants(opt.invariants)
+        .phase(
+            Phase::new("soft-delete", common.duration(RUN_DURATION))
+                .with(Arc::new(SoftDeleteWrite), writers)
+                .with(Arc::new(SoftDeleteRead), opt.readers)
+                .with_rate(
+                    Arc::new(SoftDeleteCleanup {
+                        retention_secs: opt.retention_secs,
+                    }),
+                    1,
+                    opt.cleanup_rate,
+                ),
+        );
+    let retry = RetryPolicy {
+        max_retries: 0,
+        base_backoff: Duration::ZERO,
+    };
+    for report in run_scenario(&pool, &scenario, seed, retry).await? {
+        println!("{}", report);
+    }
+    Ok(())
+}
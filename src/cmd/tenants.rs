@@ -0,0 +1,144 @@
+//! Tenants sharing one table, each writing and reading only the rows of its
+//! own `tenant_id`, optionally each in its own resource group.
+//!
+//! Tenant 0 is the victim, paced to `--victim-rate`; the others run as fast as
+//! they can. Phase "alone" runs the victim by itself, phase "shared" all
+//! tenants. The victim's latency in "shared" relative to "alone" is the
+//! interference of the other tenants. `--concurrency` is the number of
+//! workers per tenant, `--duration` the duration of each phase.
+use crate::cmd::Common;
+use crate::error::MyError;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, PhaseReport, Scenario};
+use crate::workload::composite::{CompositeWrite, TenantMix};
+use crate::workload::keyspace::{KeyDist, KeySpace};
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WORKERS: usize = 4;
+const PHASE_DURATION: Duration = Duration::from_secs(300);
+
+#[derive(Args)]
+pub struct Opt {
+    /// Number of tenants, including the victim
+    #[clap(long, default_value = "4", value_parser = clap::value_parser!(u64).range(1..))]
+    tenants: u64,
+    /// Rows per tenant
+    #[clap(long, default_value = "10000", value_parser = clap::value_parser!(u64).range(1..))]
+    rows: u64,
+    /// Statements per second of the victim
+    #[clap(long, default_value = "100")]
+    victim_rate: f64,
+    /// Put every tenant in its own resource group with this many request
+    /// units per second
+    #[clap(long)]
+    ru_per_sec: Option<u64>,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    if !opt.victim_rate.is_finite() || opt.victim_rate <= 0.0 {
+        return Err(MyError::StringError(format!(
+            "--victim-rate must be positive, got {}",
+            opt.victim_rate
+        )));
+    }
+    simple_logging::log_to_file(output::path("tenants.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let workers = common.concurrency(WORKERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections((workers as u64 * opt.tenants) as u32 + 2)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let mut keys = KeySpace {
+        table: "tenants".to_owned(),
+        ..KeySpace::default()
+    };
+    keys.columns[0].dist = KeyDist::Uniform(opt.tenants);
+    keys.columns[1].dist = KeyDist::Uniform(opt.rows);
+    keys.columns[2].dist = KeyDist::Uniform(opt.rows);
+    let keys = Arc::new(keys);
+
+    let mut scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .setup(Arc::new(CompositeWrite(keys.clone())));
+    let groups: Vec<Option<String>> = (0..opt.tenants)
+        .map(|i| opt.ru_per_sec.map(|_| format!("tenant_{}", i)))
+        .collect();
+    if let Some(ru) = opt.ru_per_sec {
+        for group in groups.iter().flatten() {
+            scenario = scenario
+                .setup_sql(&format!(
+                    "create resource group if not exists {} ru_per_sec = {}",
+                    group, ru
+                ))
+                .teardown_sql(&format!("drop resource group if exists {}", group));
+        }
+    }
+    let victim = Arc::new(TenantMix::new(keys.clone(), 0, groups[0].clone()));
+    let duration = common.duration(PHASE_DURATION);
+    let mut shared =
+        Phase::new("shared", duration).with_rate(victim.clone(), workers, opt.victim_rate);
+    for (tenant, group) in groups.iter().enumerate().skip(1) {
+        shared = shared.with(
+            Arc::new(TenantMix::new(keys.clone(), tenant as u64, group.clone())),
+            workers,
+        );
+    }
+    let scenario = scenario
+        .phase(Phase::new("alone", duration).with_rate(victim, workers, opt.victim_rate))
+        .phase(shared);
+
+    let retry = RetryPolicy {
+        max_retries: 0,
+        base_backoff: Duration::ZERO,
+    };
+    let reports = run_scenario(&pool, &scenario, seed, retry).await?;
+    for report in &reports {
+        println!("{}", report);
+    }
+    // the victim is the first workload of both phases
+    for alone in reports.iter().filter(|r| r.name == "alone") {
+        let Some(shared) = reports
+            .iter()
+            .find(|r| r.name == "shared" && r.repetition == alone.repetition)
+        else {
+            continue;
+        };
+        let msg = interference(alone, shared);
+        info!("{}", msg);
+        println!("{}", msg);
+    }
+    Ok(())
+}
+
+/// The victim's latency in `shared` relative to `alone`, phases of the same
+/// repetition.
+fn interference(alone: &PhaseReport, shared: &PhaseReport) -> String {
+    let mut lines = vec![format!(
+        "victim latency {} / {}:",
+        shared.label(),
+        alone.label()
+    )];
+    let (alone, shared) = (&alone.workloads[0].1, &shared.workloads[0].1);
+    for p in [50.0, 99.0] {
+        if let (Some(a), Some(s)) = (alone.percentile(p), shared.percentile(p)) {
+            lines.push(format!(
+                "  p{}: {:.1?} / {:.1?} = {:.2}x",
+                p,
+                s,
+                a,
+                s.as_secs_f64() / a.as_secs_f64()
+            ));
+        }
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,128 @@
+//! Inserts and updates on a table with a TTL attribute while TiDB's TTL job
+//! deletes expired rows. The number of expired rows not deleted yet is logged
+//! during the run. Afterwards the rows that have not expired must all still
+//! exist, and the job gets `--settle-secs` to delete all expired rows.
+//! `--concurrency` is the number of writers.
+use crate::cmd::Common;
+use crate::error::MyError;
+use crate::invariant::InvariantOpts;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, Scenario};
+use crate::workload::ttl::{TtlBacklog, TtlTable, TtlWrite};
+use crate::Result;
+use clap::Args;
+use log::{info, warn, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use sqlx::{Executor, Row};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const WRITERS: usize = 8;
+const RUN_DURATION: Duration = Duration::from_secs(60 * 60);
+const SETTLE_POLL: Duration = Duration::from_secs(10);
+
+#[derive(Args)]
+pub struct Opt {
+    /// Seconds after `created_at` a row expires
+    #[clap(long, default_value = "300")]
+    ttl_secs: u64,
+    /// TTL_JOB_INTERVAL of the table
+    #[clap(long, default_value = "1m")]
+    job_interval: String,
+    /// Log the number of expired rows every this many seconds
+    #[clap(long, default_value = "10")]
+    backlog_secs: u64,
+    /// Seconds to wait after the run for the TTL job to delete all expired rows
+    #[clap(long, default_value = "1800")]
+    settle_secs: u64,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("ttl.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let writers = common.concurrency(WRITERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(writers as u32 + 3)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let table = Arc::new(TtlTable::new(
+        Duration::from_secs(opt.ttl_secs),
+        &format!("'{}'", opt.job_interval),
+    ));
+    let scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .invariants(opt.invariants)
+        .phase(
+            Phase::new("ttl", common.duration(RUN_DURATION))
+                .with(Arc::new(TtlWrite(table.clone())), writers)
+                .with_rate(
+                    Arc::new(TtlBacklog(table.clone())),
+                    1,
+                    1.0 / opt.backlog_secs as f64,
+                ),
+        );
+    let retry = RetryPolicy {
+        max_retries: 0,
+        base_backoff: Duration::ZERO,
+    };
+    for report in run_scenario(&pool, &scenario, seed, retry).await? {
+        println!("{}", report);
+    }
+
+    // before the remaining rows expire while waiting for the job
+    let mut conn = pool.acquire().await?;
+    table.verify(&mut conn).await?;
+    println!("ttl_t verified");
+    let start = Instant::now();
+    loop {
+        let backlog = table.backlog(&mut conn).await?;
+        let msg = format!("ttl backlog after the run: {} expired rows", backlog);
+        info!("{}", msg);
+        println!("{}", msg);
+        if backlog == 0 {
+            break;
+        }
+        if start.elapsed() >= Duration::from_secs(opt.settle_secs) {
+            return Err(MyError::StringError(format!(
+                "{} expired rows left after {}s",
+                backlog, opt.settle_secs
+            )));
+        }
+        tokio::time::sleep(SETTLE_POLL).await;
+    }
+    let msg = format!("ttl job caught up in {:.0?}", start.elapsed());
+    info!("{}", msg);
+    println!("{}", msg);
+
+    // only in TiDB versions that keep a job history
+    match conn
+        .fetch_one(
+            "select count(*), sum(deleted_rows), sum(error_delete_rows) \
+            from mysql.tidb_ttl_job_history where table_name = 'ttl_t'",
+        )
+        .await
+    {
+        Ok(row) => {
+            let summary = (0..3)
+                .map(|i| row.try_get_unchecked::<Option<String>, _>(i))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let msg = format!(
+                "ttl jobs: {}, deleted rows: {}, failed deletes: {}",
+                summary[0].as_deref().unwrap_or("0"),
+                summary[1].as_deref().unwrap_or("0"),
+                summary[2].as_deref().unwrap_or("0")
+            );
+            info!("{}", msg);
+            println!("{}", msg);
+        }
+        Err(e) => warn!("no ttl job history: {:?}", e),
+    }
+    Ok(())
+}
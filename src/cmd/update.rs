@@ -0,0 +1,307 @@
+use crate::cmd::Common;
+use crate::control::{reset_stop_flag, wait_for_stop_flag};
+use crate::error::ErrorClass;
+use crate::metrics::StatementLatencies;
+use crate::output;
+use crate::pd::PdClient;
+use crate::record::Recorder;
+use crate::session::{restore_session_vars, set_session_alias, with_isolation, Isolation};
+use crate::txn_ts;
+use crate::Result;
+use clap::Args;
+use futures::future::join_all;
+use log::{error, info, LevelFilter};
+use sqlx::mysql::{MySqlPool, MySqlPoolOptions};
+use sqlx::{query, query_scalar, Executor, Row};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::select;
+
+const NUM_WORKERS: usize = 20;
+const RUN_DURATION: Duration = Duration::from_secs(60 * 60 * 24);
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+// check session variables after every N-th transaction
+const SESSION_VARS_CHECK_INTERVAL: u64 = 1000;
+const SESSION_VARS: &[(&str, &str)] = &[("tidb_txn_assertion_level", "strict")];
+
+/// Client-side model of `val`: under serializability every transaction that
+/// commits its update adds exactly 1 to it.
+#[derive(Default)]
+struct Shadow {
+    workers: u64,
+    committed: AtomicU64,
+    // commits whose outcome is unknown, e.g. the connection broke
+    in_doubt: AtomicU64,
+}
+
+impl Shadow {
+    /// Compare `val` with the model. Commits that are applied but not counted
+    /// yet are bounded by the number of workers.
+    async fn check(&self, pool: &MySqlPool) -> Result<bool> {
+        let low = 1 + self.committed.load(Ordering::SeqCst);
+        let val: i32 = query_scalar("select val from cycle where sk = 1")
+            .fetch_one(pool)
+            .await?;
+        let high = 1
+            + self.committed.load(Ordering::SeqCst)
+            + self.in_doubt.load(Ordering::SeqCst)
+            + self.workers;
+        let val = val as u64;
+        if val < low || val > high {
+            error!("val is {}, expected in [{}, {}]", val, low, high);
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+#[derive(Args)]
+pub struct Opt {
+    /// Also stop when `bench_control.stop_flag` is set for "update"
+    #[clap(long)]
+    stop_flag: bool,
+    /// PD address. Print the stores at start and log when one changes state
+    #[clap(long)]
+    pd: Option<String>,
+    /// Record the statements of the workers to this file, for the replay
+    /// subcommand
+    #[clap(long)]
+    record: Option<String>,
+    /// Transaction isolation level of the workers, repeatable-read or
+    /// read-committed; the server's default if not given
+    #[clap(long)]
+    isolation: Option<Isolation>,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    simple_logging::log_to_file(output::path("update.log"), LevelFilter::Info)?;
+    let workers = common.concurrency(NUM_WORKERS);
+    let pool = with_isolation(MySqlPoolOptions::new(), opt.isolation)
+        .max_connections(500)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+    let pool = Arc::new(pool);
+    let pd = opt.pd.as_deref().map(PdClient::new);
+    if let Some(pd) = &pd {
+        for store in pd.stores().await? {
+            info!("{}", store);
+            println!("{}", store);
+        }
+        let pd = pd.clone();
+        tokio::spawn(async move { pd.watch_stores().await });
+    }
+
+    let recorder = opt
+        .record
+        .as_deref()
+        .map(|r| Recorder::create(&output::path(r)))
+        .transpose()?;
+    let mut conn = pool.acquire().await?;
+    let setup_recorder = match &recorder {
+        Some(r) => Some(r.connection(&mut conn, "update-setup").await?),
+        None => None,
+    };
+    for sql in [
+        "set @@global.tidb_txn_assertion_level=strict",
+        "set @@tidb_general_log=1",
+        "drop table if exists cycle",
+        "create table cycle ( \
+        pk  int not null primary key, \
+        sk  int not null, \
+        val int, \
+        key cycle_sk_val(sk, val) \
+        );",
+        "insert into cycle values (1, 1, 1)",
+    ] {
+        if let Some(r) = &setup_recorder {
+            r.record(sql);
+        }
+        conn.execute(sql).await?;
+    }
+    if let Some(pd) = &pd {
+        pd.report_table_regions(&mut conn, "cycle").await;
+    }
+    if opt.stop_flag {
+        reset_stop_flag(&mut conn, "update").await?;
+    }
+    drop(conn);
+
+    let mut handles = Vec::new();
+
+    // channel to report assertion failure, calling a early termination
+    let (error_tx, mut error_rx) = tokio::sync::mpsc::channel(workers);
+
+    // channel to nofitify workers to stop
+    let (end_tx, _) = tokio::sync::broadcast::channel(1);
+
+    let shadow = Arc::new(Shadow {
+        workers: workers as u64,
+        ..Default::default()
+    });
+
+    for i in 0..workers {
+        let mut conn = pool.acquire().await?;
+        set_session_alias(&mut conn, "update", i).await?;
+        let conn_recorder = match &recorder {
+            Some(r) => Some(r.connection(&mut conn, &format!("update-{}", i)).await?),
+            None => None,
+        };
+        let record = move |sql: &str| {
+            if let Some(r) = &conn_recorder {
+                r.record(sql);
+            }
+        };
+        let error_tx = error_tx.clone();
+        let mut end_rx = end_tx.subscribe();
+        let shadow = shadow.clone();
+        let handle = tokio::spawn(async move {
+            // to see whether time goes to the locking read, the update or the
+            // commit; sketched, as the run lasts a day
+            let mut statements = StatementLatencies::sketched();
+            let mut time =
+                |statement, begin: Instant| statements.record(statement, begin.elapsed());
+            for i in 0u64.. {
+                if end_rx.try_recv().is_ok() {
+                    break;
+                }
+                if i % SESSION_VARS_CHECK_INTERVAL == 0 {
+                    if let Err(e) = restore_session_vars(&mut conn, SESSION_VARS).await {
+                        info!("failed to check session variables: {:?}", e);
+                    }
+                }
+                record("begin");
+                let begin = Instant::now();
+                let txn_begin = begin;
+                let res = conn.execute("begin").await;
+                time("begin", begin);
+                if res.is_err() {
+                    continue;
+                }
+                // for update or not??
+                let select = "select val from cycle where sk = 1 for update";
+                record(select);
+                let begin = Instant::now();
+                let res = query(select).fetch_one(&mut conn).await;
+                time("select for update", begin);
+                if res.is_err() {
+                    continue;
+                }
+                let val: i32 = res.unwrap().get("val");
+                let update = format!("update cycle set val = {} where sk = 1;", val + 1);
+                record(&update);
+                let begin = Instant::now();
+                let res = conn.execute(update.as_str()).await;
+                time("update", begin);
+                let updated = res.is_ok();
+                check_res(res, &error_tx);
+                record("commit");
+                let begin = Instant::now();
+                let res = conn.execute("commit").await;
+                time("commit", begin);
+                if res.is_ok() && rand::random::<f64>() < txn_ts::ratio() {
+                    txn_ts::record(&mut conn, "update", txn_begin.elapsed()).await;
+                }
+                if updated {
+                    match &res {
+                        Ok(_) => {
+                            shadow.committed.fetch_add(1, Ordering::SeqCst);
+                        }
+                        Err(e)
+                            if matches!(
+                                ErrorClass::of(e),
+                                ErrorClass::Connection | ErrorClass::Other
+                            ) =>
+                        {
+                            shadow.in_doubt.fetch_add(1, Ordering::SeqCst);
+                        }
+                        Err(_) => {}
+                    }
+                }
+                check_res(res, &error_tx);
+            }
+            statements
+        });
+        handles.push(handle);
+    }
+
+    let mut workers = join_all(handles);
+    let finished = select! {
+        _ = error_rx.recv() => {
+            info!("assertion failed");
+            println!("assertion failed");
+            None
+        },
+        workers = &mut workers => {
+            error!("unexpected update finished");
+            Some(workers)
+        },
+        _ = tokio::time::sleep(common.duration(RUN_DURATION)) => {
+            info!("time up");
+            println!("time up");
+            None
+        }
+        _ = wait_for_stop_flag(&pool, "update"), if opt.stop_flag => {
+            println!("stopped by flag");
+            None
+        }
+        _ = tokio::signal::ctrl_c() => {
+            info!("interrupted");
+            println!("interrupted");
+            None
+        }
+        _ = verify_loop(&shadow, &pool) => {
+            println!("val diverged from the shadow model");
+            None
+        }
+    };
+    let _ = end_tx.send(());
+    let workers = match finished {
+        Some(workers) => workers,
+        None => workers.await,
+    };
+    if let Some(r) = &recorder {
+        r.flush()?;
+    }
+    let mut statements = StatementLatencies::sketched();
+    for worker in workers.into_iter().flatten() {
+        statements.merge(worker);
+    }
+    statements.sort();
+    info!("statement latencies:\n{}", statements);
+    println!("{}", statements);
+    if !shadow.check(&pool).await? {
+        println!("val diverged from the shadow model");
+    }
+    if let Some(pd) = &pd {
+        pd.report_table_regions(&mut *pool.acquire().await?, "cycle")
+            .await;
+    }
+    Ok(())
+}
+
+async fn verify_loop(shadow: &Shadow, pool: &MySqlPool) {
+    loop {
+        tokio::time::sleep(CHECK_INTERVAL).await;
+        match shadow.check(pool).await {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(e) => info!("failed to check val: {:?}", e),
+        }
+    }
+}
+
+fn check_res(
+    res: std::result::Result<sqlx::mysql::MySqlQueryResult, sqlx::Error>,
+    error_tx: &tokio::sync::mpsc::Sender<()>,
+) {
+    if let Err(e) = res {
+        info!("{:?}", e);
+        if e.to_string().to_lowercase().contains("assertion") {
+            error!("{:?}", e);
+            // never blocks: once the run is ending nobody receives, and one
+            // report is enough to end it
+            let _ = error_tx.try_send(());
+        }
+    }
+}
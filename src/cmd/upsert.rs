@@ -0,0 +1,71 @@
+//! Upserts with `INSERT ... ON DUPLICATE KEY UPDATE` or `REPLACE`, a
+//! `--conflict` fraction of them of existing keys, reporting the latency of
+//! the insert and the update path separately. `--concurrency` is the number
+//! of workers.
+use crate::cmd::Common;
+use crate::error::MyError;
+use crate::invariant::InvariantOpts;
+use crate::output;
+use crate::retry::RetryPolicy;
+use crate::scenario::{run as run_scenario, Phase, Scenario};
+use crate::workload::upsert::{Upsert, UpsertKind};
+use crate::Result;
+use clap::Args;
+use log::{info, LevelFilter};
+use sqlx::mysql::MySqlPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WORKERS: usize = 16;
+const RUN_DURATION: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Args)]
+pub struct Opt {
+    /// on-duplicate or replace
+    #[clap(long, default_value = "on-duplicate")]
+    kind: UpsertKind,
+    /// Probability that an upsert is of an existing key
+    #[clap(long, default_value = "0.5")]
+    conflict: f64,
+    /// Retry an upsert up to this many times after a retryable error
+    #[clap(long, default_value = "0")]
+    retries: u32,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+}
+
+pub async fn run(common: &Common, opt: Opt) -> Result<()> {
+    if !(0.0..=1.0).contains(&opt.conflict) {
+        return Err(MyError::StringError(format!(
+            "--conflict must be between 0 and 1, got {}",
+            opt.conflict
+        )));
+    }
+    simple_logging::log_to_file(output::path("upsert.log"), LevelFilter::Info)?;
+    let seed = common.seed();
+    info!("seed: {}", seed);
+    println!("seed: {}", seed);
+    let workers = common.concurrency(WORKERS);
+    let pool = MySqlPoolOptions::new()
+        .max_connections(workers as u32 + 2)
+        .connect_with(common.connection.connect_options()?)
+        .await?;
+
+    let upsert = Arc::new(Upsert::new(opt.kind, opt.conflict));
+    let scenario = Scenario::new()
+        .measure(common.measure_window()?)
+        .connection_mode(common.connection.connection_mode)
+        .invariants(opt.invariants)
+        .phase(Phase::new("upsert", common.duration(RUN_DURATION)).with(upsert.clone(), workers));
+    let retry = RetryPolicy {
+        max_retries: opt.retries,
+        base_backoff: Duration::from_millis(10),
+    };
+    for report in run_scenario(&pool, &scenario, seed, retry).await? {
+        println!("{}", report);
+    }
+    let msg = upsert.summary();
+    info!("{}", msg);
+    println!("{}", msg);
+    Ok(())
+}
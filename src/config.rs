@@ -0,0 +1,109 @@
+//! Options from a TOML file.
+//!
+//! `--config <file>` is accepted by every binary that parses its options with
+//! [`parse`]. Keys of the file are option names, e.g. `stop_flag = true` or
+//! `seed = 42`. They are turned into arguments placed before the command line
+//! ones, right after the subcommand if there is one, so flags given on the
+//! command line win. The file is kept for the metadata of the run, see
+//! [`file`].
+use crate::connection::redact_url;
+use crate::error::MyError;
+use crate::Result;
+use clap::{Arg, Parser};
+use std::ffi::OsString;
+use std::sync::OnceLock;
+
+// the path and the options of the file given with --config, if any
+static FILE: OnceLock<(String, toml::value::Table)> = OnceLock::new();
+
+/// The path and the contents of the file given with `--config`, if any,
+/// with its password and the user and password of its URL hidden.
+pub fn file() -> Option<(&'static str, String)> {
+    let (path, table) = FILE.get()?;
+    let mut table = table.clone();
+    if let Some(password) = table.get_mut("password") {
+        *password = "<hidden>".into();
+    }
+    if let Some(toml::Value::String(url)) = table.get_mut("url") {
+        *url = redact_url(url);
+    }
+    Some((path, toml::to_string(&table).unwrap_or_default()))
+}
+
+pub fn parse<T: Parser>() -> Result<T> {
+    let args: Vec<OsString> = std::env::args_os().collect();
+    let mut command = T::command();
+    // the file args go right after the subcommand, if any, as most of them
+    // are options of the subcommand
+    let names: Vec<String> = command
+        .get_subcommands()
+        .map(|c| c.get_name().to_owned())
+        .collect();
+    let split = args[1..]
+        .iter()
+        .position(|a| names.iter().any(|n| a == n.as_str()))
+        .map_or(1, |i| i + 2);
+    for name in &names {
+        command = command.mut_subcommand(name.as_str(), |c| c.args_override_self(true));
+    }
+    let mut full = args[..split].to_vec();
+    if let Some(path) = config_path(&args[1..]) {
+        full.extend(file_args(&path)?);
+    }
+    full.extend_from_slice(&args[split..]);
+    let matches = command
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .takes_value(true)
+                .global(true)
+                .help("Read options from a TOML file; command line flags override it"),
+        )
+        .args_override_self(true)
+        .get_matches_from(full);
+    Ok(T::from_arg_matches(&matches).unwrap_or_else(|e| e.exit()))
+}
+
+fn config_path(args: &[OsString]) -> Option<String> {
+    let mut args = args.iter().map(|a| a.to_string_lossy());
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(|a| a.into_owned());
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(path.to_owned());
+        }
+    }
+    None
+}
+
+fn file_args(path: &str) -> Result<Vec<OsString>> {
+    let table: toml::value::Table = toml::from_str(&std::fs::read_to_string(path)?)
+        .map_err(|e| MyError::StringError(format!("invalid config {}: {}", path, e)))?;
+    let _ = FILE.set((path.to_owned(), table.clone()));
+    let mut args = Vec::new();
+    for (key, value) in table {
+        let flag = format!("--{}", key.replace('_', "-"));
+        let values = match value {
+            toml::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                toml::Value::Boolean(true) => args.push(flag.clone().into()),
+                toml::Value::Boolean(false) => {}
+                toml::Value::String(s) => args.extend([flag.clone().into(), s.into()]),
+                toml::Value::Integer(_) | toml::Value::Float(_) => {
+                    args.extend([flag.clone().into(), value.to_string().into()])
+                }
+                _ => {
+                    return Err(MyError::StringError(format!(
+                        "unsupported value of {} in {}",
+                        key, path
+                    )))
+                }
+            }
+        }
+    }
+    Ok(args)
+}
@@ -0,0 +1,131 @@
+//! Options of the connections to the cluster, the same for every subcommand.
+//!
+//! The URL comes from `--url`, else `DATABASE_URL`, else [`DEFAULT_URL`].
+//! `--host`, `--port`, `--user`, `--password` and `--database` override its
+//! parts, e.g. to keep the password in `MYSQL_PWD` rather than in the URL,
+//! and the TLS flags apply on top. `--socket` connects through a Unix socket
+//! instead of TCP, to leave the loopback overhead out of microbenchmarks.
+//!
+//! `--connection-mode` chooses whether the runner's workers hold a
+//! connection for the whole run or take one from the pool per iteration.
+use crate::error::MyError;
+use crate::Result;
+use clap::Args;
+use sqlx::mysql::{MySqlConnectOptions, MySqlSslMode};
+use std::str::FromStr;
+
+pub const DEFAULT_URL: &str = "mysql://root@127.0.0.1:4000/test";
+
+/// `s` with the user and password of a URL in it hidden, e.g. to record a
+/// command line; as is if it has none.
+pub fn redact_url(s: &str) -> String {
+    let Some(scheme_end) = s.find("://").map(|i| i + 3) else {
+        return s.to_owned();
+    };
+    let authority_end = s[scheme_end..]
+        .find(['/', '?', '#'])
+        .map_or(s.len(), |i| scheme_end + i);
+    match s[scheme_end..authority_end].rfind('@') {
+        Some(at) => format!("{}<hidden>{}", &s[..scheme_end], &s[scheme_end + at..]),
+        None => s.to_owned(),
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ConnectionOpts {
+    /// MySQL URL to connect to, mysql://root@127.0.0.1:4000/test if neither
+    /// given nor in DATABASE_URL
+    #[clap(long, global = true, env = "DATABASE_URL")]
+    pub url: Option<String>,
+    /// Host to connect to, overriding the URL's
+    #[clap(long, global = true)]
+    pub host: Option<String>,
+    /// Port to connect to, overriding the URL's
+    #[clap(long, global = true)]
+    pub port: Option<u16>,
+    /// Unix socket of a tidb-server on this host to connect through instead
+    /// of TCP, e.g. /tmp/tidb-4000.sock
+    #[clap(long, global = true)]
+    pub socket: Option<String>,
+    /// User to connect as, overriding the URL's
+    #[clap(long, global = true)]
+    pub user: Option<String>,
+    /// Password of the user, overriding the URL's
+    #[clap(long, global = true, env = "MYSQL_PWD", hide_env_values = true)]
+    pub password: Option<String>,
+    /// Database to use, overriding the URL's
+    #[clap(long, global = true)]
+    pub database: Option<String>,
+    /// TLS mode of the connections: disabled, preferred, required, verify_ca
+    /// or verify_identity; as in the URL if not given, else preferred
+    #[clap(long, global = true)]
+    pub ssl_mode: Option<MySqlSslMode>,
+    /// CA certificate to verify the server with in the verify_ca and
+    /// verify_identity modes, a PEM file
+    #[clap(long, global = true)]
+    pub ssl_ca: Option<String>,
+    /// dedicated: each worker holds a connection for the whole run; pooled:
+    /// it takes one from the pool for each iteration and puts it back
+    /// after, timed apart from the iteration as the connection acquire
+    #[clap(long, global = true, default_value = "dedicated")]
+    pub connection_mode: ConnectionMode,
+}
+
+/// How the runner's workers use connections, see [`ConnectionOpts`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectionMode {
+    #[default]
+    Dedicated,
+    Pooled,
+}
+
+impl FromStr for ConnectionMode {
+    type Err = MyError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "dedicated" => Ok(ConnectionMode::Dedicated),
+            "pooled" => Ok(ConnectionMode::Pooled),
+            _ => Err(MyError::StringError(format!(
+                "unknown connection mode {}, expected dedicated or pooled",
+                s
+            ))),
+        }
+    }
+}
+
+impl ConnectionOpts {
+    pub fn url(&self) -> &str {
+        self.url.as_deref().unwrap_or(DEFAULT_URL)
+    }
+
+    /// Options to connect with: the URL with the other flags applied.
+    pub fn connect_options(&self) -> Result<MySqlConnectOptions> {
+        let mut options = MySqlConnectOptions::from_str(self.url())?;
+        if let Some(host) = &self.host {
+            options = options.host(host);
+        }
+        if let Some(port) = self.port {
+            options = options.port(port);
+        }
+        if let Some(socket) = &self.socket {
+            options = options.socket(socket);
+        }
+        if let Some(user) = &self.user {
+            options = options.username(user);
+        }
+        if let Some(password) = &self.password {
+            options = options.password(password);
+        }
+        if let Some(database) = &self.database {
+            options = options.database(database);
+        }
+        if let Some(mode) = self.ssl_mode {
+            options = options.ssl_mode(mode);
+        }
+        if let Some(ca) = &self.ssl_ca {
+            options = options.ssl_ca(ca);
+        }
+        Ok(options)
+    }
+}
@@ -0,0 +1,93 @@
+//! Remote stop switch for long runs.
+//!
+//! Each workload owns a row in `bench_control`; an operator stops the run from
+//! any SQL client with `UPDATE bench_control SET stop_flag = 1 WHERE name = '<workload>'`.
+//!
+//! Workers can also be paused, e.g. to let GC or a DDL drain during a manual
+//! cluster operation, with `kill -USR1 <pid>` and resumed with
+//! `kill -USR2 <pid>`. A paused worker finishes its current iteration and
+//! waits before the next; durations keep running while paused.
+use crate::Result;
+use log::{info, warn};
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use sqlx::{query, query_scalar, Executor};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Create the control table if needed and clear the flag of `name`.
+pub async fn reset_stop_flag(conn: &mut MySqlConnection, name: &str) -> Result<()> {
+    conn.execute(
+        "create table if not exists bench_control ( \
+        name varchar(64) not null primary key, \
+        stop_flag tinyint not null default 0 \
+        )",
+    )
+    .await?;
+    conn.execute(
+        query("insert into bench_control values (?, 0) on duplicate key update stop_flag = 0")
+            .bind(name),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Poll the flag of `name` until it is set. Poll errors are logged and retried.
+pub async fn wait_for_stop_flag(pool: &MySqlPool, name: &str) {
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let res = query_scalar::<_, i8>("select stop_flag from bench_control where name = ?")
+            .bind(name)
+            .fetch_optional(pool)
+            .await;
+        match res {
+            Ok(Some(flag)) if flag != 0 => {
+                info!("stop flag of {} is set", name);
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => warn!("failed to poll stop flag: {:?}", e),
+        }
+    }
+}
+
+static PAUSED: OnceLock<watch::Receiver<bool>> = OnceLock::new();
+
+/// Whether workers are paused, switched by SIGUSR1 and SIGUSR2. The first
+/// call starts listening for them, so it must be made inside the runtime.
+pub fn pause_switch() -> watch::Receiver<bool> {
+    PAUSED
+        .get_or_init(|| {
+            let (tx, rx) = watch::channel(false);
+            match (
+                signal(SignalKind::user_defined1()),
+                signal(SignalKind::user_defined2()),
+            ) {
+                (Ok(mut pause), Ok(mut resume)) => {
+                    tokio::spawn(async move {
+                        loop {
+                            let paused = tokio::select! {
+                                _ = pause.recv() => true,
+                                _ = resume.recv() => false,
+                            };
+                            info!("{}", if paused { "paused" } else { "resumed" });
+                            println!("{}", if paused { "paused" } else { "resumed" });
+                            let _ = tx.send(paused);
+                        }
+                    });
+                }
+                (Err(e), _) | (_, Err(e)) => warn!("can't pause on signals: {:?}", e),
+            }
+            rx
+        })
+        .clone()
+}
+
+/// Return once `paused` is off.
+pub async fn wait_resumed(paused: &mut watch::Receiver<bool>) {
+    // the sender lives as long as the listener, i.e. forever
+    let _ = paused.wait_for(|p| !p).await;
+}
@@ -1,3 +1,6 @@
+use sqlx::mysql::MySqlDatabaseError;
+use std::collections::BTreeMap;
+use std::fmt;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -13,4 +16,129 @@ pub enum MyError {
     Io(#[from] std::io::Error),
     #[error("{0}")]
     Rng(#[from] rand::Error),
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+}
+
+impl MyError {
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            MyError::SqlxError { sqlx } => ErrorClass::of(sqlx),
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+/// Coarse classes of statement failures, used for counting and retrying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorClass {
+    WriteConflict,
+    LockWaitTimeout,
+    /// A NOWAIT locking read found a row locked.
+    LockNowait,
+    Deadlock,
+    DuplicateKey,
+    SchemaChanged,
+    Assertion,
+    /// A value doesn't fit its column, e.g. after a lossy type change.
+    Truncated,
+    /// A transaction or statement exceeded a size or memory limit.
+    TooLarge,
+    Connection,
+    Other,
+}
+
+impl ErrorClass {
+    pub fn of(e: &sqlx::Error) -> ErrorClass {
+        match e {
+            sqlx::Error::Database(e) => {
+                match e
+                    .try_downcast_ref::<MySqlDatabaseError>()
+                    .map(|e| e.number())
+                {
+                    Some(9007) => ErrorClass::WriteConflict,
+                    Some(1205) => ErrorClass::LockWaitTimeout,
+                    Some(3572) => ErrorClass::LockNowait,
+                    Some(1213) => ErrorClass::Deadlock,
+                    Some(1062) => ErrorClass::DuplicateKey,
+                    Some(8028) => ErrorClass::SchemaChanged,
+                    Some(8141) => ErrorClass::Assertion,
+                    Some(1264 | 1265 | 1406 | 1690) => ErrorClass::Truncated,
+                    Some(8004 | 8175 | 8176) => ErrorClass::TooLarge,
+                    _ => ErrorClass::Other,
+                }
+            }
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => ErrorClass::Connection,
+            _ => ErrorClass::Other,
+        }
+    }
+
+    /// Whether re-running the failed transaction can be expected to succeed.
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorClass::WriteConflict
+                | ErrorClass::LockWaitTimeout
+                | ErrorClass::Deadlock
+                | ErrorClass::SchemaChanged
+        )
+    }
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorClass::WriteConflict => "write conflict",
+            ErrorClass::LockWaitTimeout => "lock wait timeout",
+            ErrorClass::LockNowait => "lock nowait",
+            ErrorClass::Deadlock => "deadlock",
+            ErrorClass::DuplicateKey => "duplicate key",
+            ErrorClass::SchemaChanged => "schema changed",
+            ErrorClass::Assertion => "assertion",
+            ErrorClass::Truncated => "truncated",
+            ErrorClass::TooLarge => "too large",
+            ErrorClass::Connection => "connection",
+            ErrorClass::Other => "other",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Number of errors seen per class.
+#[derive(Debug, Default, Clone)]
+pub struct ErrorCounts(BTreeMap<ErrorClass, u64>);
+
+impl ErrorCounts {
+    pub fn add(&mut self, class: ErrorClass) {
+        *self.0.entry(class).or_default() += 1;
+    }
+
+    pub fn merge(&mut self, other: &ErrorCounts) {
+        for (class, count) in &other.0 {
+            *self.0.entry(*class).or_default() += count;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.0.values().sum()
+    }
+
+    /// Classes that occurred and their counts.
+    pub fn iter(&self) -> impl Iterator<Item = (ErrorClass, u64)> + '_ {
+        self.0.iter().map(|(class, count)| (*class, *count))
+    }
+}
+
+impl fmt::Display for ErrorCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "errors: {}", self.total())?;
+        for (class, count) in &self.0 {
+            write!(f, "\n  {}: {}", class, count)?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,113 @@
+//! A JSON Lines log of every iteration of the runner's workers, with
+//! `--event-log <file>`, for analyses the reports don't cover. A file that
+//! exists, e.g. a named pipe, is written to as is.
+//!
+//! A line has the wall time in microseconds, the workload, the worker, the
+//! attempt of the iteration from 1, its latency and its result, e.g.
+//! `{"ts_us":1700000000000000,"workload":"mix","worker":3,"attempt":1,
+//! "latency_us":1234,"result":"error","error_class":"write conflict"}`.
+//! `error_class` is null for successes.
+use crate::error::ErrorClass;
+use crate::output;
+use crate::Result;
+use log::warn;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, oneshot};
+
+// the workers only queue their events, a single writer writes them out
+static LOG: OnceLock<mpsc::UnboundedSender<Message>> = OnceLock::new();
+
+enum Message {
+    Event(Event),
+    /// Write out what is buffered, then signal.
+    Flush(oneshot::Sender<()>),
+}
+
+#[derive(Serialize)]
+struct Event {
+    ts_us: u64,
+    workload: String,
+    worker: usize,
+    attempt: u32,
+    latency_us: u64,
+    result: &'static str,
+    error_class: Option<String>,
+}
+
+/// Log the iterations from now on to `path`, in the run's directory if it
+/// has one and `path` is relative.
+pub fn log_to(path: &str) -> Result<()> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(output::path(path))?;
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::task::spawn_blocking(move || write_loop(file, rx));
+    let _ = LOG.set(tx);
+    Ok(())
+}
+
+pub fn enabled() -> bool {
+    LOG.get().is_some()
+}
+
+/// Log an attempt of an iteration of `workload` by `worker` that took
+/// `latency` and failed with `error`, if any.
+pub fn iteration(
+    workload: &str,
+    worker: usize,
+    attempt: u32,
+    latency: Duration,
+    error: Option<ErrorClass>,
+) {
+    let Some(log) = LOG.get() else {
+        return;
+    };
+    let ts_us = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_micros() as u64);
+    let _ = log.send(Message::Event(Event {
+        ts_us,
+        workload: workload.to_owned(),
+        worker,
+        attempt,
+        latency_us: latency.as_micros() as u64,
+        result: if error.is_some() { "error" } else { "ok" },
+        error_class: error.map(|class| class.to_string()),
+    }));
+}
+
+/// Write out the queued events, e.g. when a run ends.
+pub async fn flush() {
+    let Some(log) = LOG.get() else {
+        return;
+    };
+    let (done, wait) = oneshot::channel();
+    if log.send(Message::Flush(done)).is_ok() {
+        let _ = wait.await;
+    }
+}
+
+fn write_loop(file: File, mut rx: mpsc::UnboundedReceiver<Message>) {
+    let mut out = BufWriter::new(file);
+    while let Some(message) = rx.blocking_recv() {
+        let res = match message {
+            Message::Event(event) => serde_json::to_writer(&mut out, &event)
+                .map_err(io::Error::from)
+                .and_then(|()| out.write_all(b"\n")),
+            Message::Flush(done) => {
+                let res = out.flush();
+                let _ = done.send(());
+                res
+            }
+        };
+        if let Err(e) = res {
+            warn!("failed to write the event log: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Invariant queries checked periodically while a workload runs.
+//!
+//! Each query must return a single 0, e.g.
+//! `select count(*) from t where v < 0`. In a config file they are given as
+//! `invariant = ["...", "..."]`. Tables can also be checked with ADMIN CHECK
+//! TABLE, usually less often since it reads the whole table; the first
+//! failure always stops the run.
+use crate::error::ErrorClass;
+use crate::Result;
+use clap::Args;
+use log::{error, info, warn};
+use sqlx::mysql::MySqlPool;
+use sqlx::{Executor, Row};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct InvariantOpts {
+    /// Query that must return 0 while the workload runs; can be repeated
+    #[clap(long = "invariant")]
+    pub queries: Vec<String>,
+    /// Seconds between two checks of the invariants
+    #[clap(long = "invariant-secs", default_value = "10")]
+    pub interval_secs: u64,
+    /// Stop the run at the first violated invariant instead of only logging it
+    #[clap(long = "abort-on-violation")]
+    pub abort: bool,
+    /// Table to run ADMIN CHECK TABLE on while the workload runs; can be
+    /// repeated
+    #[clap(long = "admin-check")]
+    pub admin_check: Vec<String>,
+    /// Seconds between two ADMIN CHECKs of the tables
+    #[clap(long = "admin-check-secs", default_value = "300")]
+    pub admin_check_secs: u64,
+}
+
+impl InvariantOpts {
+    /// Whether there is anything to check.
+    pub fn enabled(&self) -> bool {
+        !self.queries.is_empty() || !self.admin_check.is_empty()
+    }
+}
+
+/// Check `opts.queries` every `opts.interval_secs` and `opts.admin_check`
+/// every `opts.admin_check_secs`, adding violations to `violations`. Returns
+/// a description of the first violation if `opts.abort` is set or ADMIN
+/// CHECK failed, otherwise runs forever. At least one of them must be given.
+pub async fn check_loop(
+    pool: &MySqlPool,
+    opts: &InvariantOpts,
+    violations: Arc<AtomicU64>,
+) -> String {
+    // intervals must not be 0, which only a default InvariantOpts has
+    let mut queries = tokio::time::interval(Duration::from_secs(opts.interval_secs.max(1)));
+    let mut admin = tokio::time::interval(Duration::from_secs(opts.admin_check_secs.max(1)));
+    // a slow ADMIN CHECK delays the queries rather than causing a burst
+    queries.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    admin.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // the first tick completes immediately
+    queries.tick().await;
+    admin.tick().await;
+    loop {
+        tokio::select! {
+            _ = queries.tick(), if !opts.queries.is_empty() => {
+                for sql in &opts.queries {
+                    match check(pool, sql).await {
+                        Ok(None) => {}
+                        Ok(Some(value)) => {
+                            let msg = format!("invariant violated: `{}` returned {}", sql, value);
+                            error!("{}", msg);
+                            violations.fetch_add(1, Ordering::SeqCst);
+                            if opts.abort {
+                                return msg;
+                            }
+                        }
+                        Err(e) => warn!("failed to check `{}`: {:?}", sql, e),
+                    }
+                }
+            }
+            _ = admin.tick(), if !opts.admin_check.is_empty() => {
+                for table in &opts.admin_check {
+                    if let Some(msg) = admin_check(pool, table).await {
+                        violations.fetch_add(1, Ordering::SeqCst);
+                        return msg;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// ADMIN CHECK TABLE `table`, returning what failed. Errors that say nothing
+/// about the data, like a lost connection or a concurrent DDL, are only
+/// logged. The table's latest DDL jobs are logged along with a failure.
+async fn admin_check(pool: &MySqlPool, table: &str) -> Option<String> {
+    let e = match pool
+        .execute(format!("admin check table {}", table).as_str())
+        .await
+    {
+        Ok(_) => {
+            info!("admin check table {} passed", table);
+            return None;
+        }
+        Err(e) => e,
+    };
+    if matches!(
+        ErrorClass::of(&e),
+        ErrorClass::Connection | ErrorClass::SchemaChanged
+    ) {
+        warn!("failed to admin check table {}: {:?}", table, e);
+        return None;
+    }
+    let msg = format!("admin check table {} failed: {}", table, e);
+    error!("{}", msg);
+    match pool
+        .fetch_all(format!("admin show ddl jobs 10 where table_name = '{}'", table).as_str())
+        .await
+    {
+        Ok(jobs) => {
+            for job in jobs {
+                let columns: Vec<String> = (0..job.len())
+                    .map(|i| {
+                        job.try_get_unchecked::<Option<String>, _>(i)
+                            .ok()
+                            .flatten()
+                            .unwrap_or_default()
+                    })
+                    .collect();
+                error!("ddl job of {}: {}", table, columns.join(" | "));
+            }
+        }
+        Err(e) => warn!("failed to show the ddl jobs of {}: {:?}", table, e),
+    }
+    Some(msg)
+}
+
+/// Run `sql` and return what it returned unless that is 0.
+async fn check(pool: &MySqlPool, sql: &str) -> Result<Option<String>> {
+    let row = pool.fetch_one(sql).await?;
+    let value: Option<String> = row.try_get_unchecked(0)?;
+    match value {
+        Some(v) if v.parse::<f64>() == Ok(0.0) => Ok(None),
+        Some(v) => Ok(Some(v)),
+        None => Ok(Some("NULL".to_owned())),
+    }
+}
@@ -1,4 +1,33 @@
+pub mod assertions;
+pub mod charts;
+pub mod cmd;
+pub mod config;
+pub mod connection;
+pub mod control;
 pub mod error;
+pub mod events;
+pub mod invariant;
+pub mod metadata;
+pub mod metrics;
+pub mod otlp;
+pub mod output;
+pub mod pd;
+pub mod placement;
+pub mod probe;
+pub mod pushgateway;
+pub mod record;
+pub mod regions;
+pub mod results;
+pub mod retry;
+pub mod rng;
+pub mod runner;
+pub mod scenario;
+pub mod server_metrics;
+pub mod session;
+pub mod slow_query;
+pub mod tui;
+pub mod txn_ts;
+pub mod warnings;
 pub mod workload;
 
 pub type Result<T> = std::result::Result<T, error::MyError>;
@@ -0,0 +1,158 @@
+//! What a run was made with, written at the top of its results files so they
+//! can still be told apart weeks later: the TiDB version, variables that
+//! change transaction behavior, the command line and the `--config` file
+//! it read, the commit of this tool and the user's `--label`.
+use crate::config;
+use crate::connection::redact_url;
+use crate::Result;
+use log::warn;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{Executor, Row};
+use std::io::Write;
+use std::sync::OnceLock;
+
+/// Variables whose session and global values are recorded.
+const VARIABLES: &[&str] = &[
+    "tidb_txn_mode",
+    "transaction_isolation",
+    "tidb_txn_assertion_level",
+    "tidb_enable_async_commit",
+    "tidb_enable_1pc",
+    "tidb_pessimistic_txn_fair_locking",
+    "tidb_dml_type",
+    "tidb_mem_quota_query",
+    "tidb_enable_metadata_lock",
+];
+
+static LABEL: OnceLock<String> = OnceLock::new();
+
+/// Label the results of the run with `label`.
+pub fn set_label(label: &str) {
+    let _ = LABEL.set(label.to_owned());
+}
+
+/// Keys and values, in order.
+#[derive(Debug, Default, Clone)]
+pub struct Metadata(Vec<(String, String)>);
+
+impl Metadata {
+    /// Collect the metadata, the server's through `conn`. What can't be read
+    /// from the server is left out with a warning.
+    pub async fn collect(conn: &mut MySqlConnection) -> Metadata {
+        let mut entries = Vec::new();
+        if let Some(label) = LABEL.get() {
+            entries.push(("label".to_owned(), label.clone()));
+        }
+        entries.push((
+            "command".to_owned(),
+            redact_args(std::env::args()).join(" "),
+        ));
+        if let Some((path, contents)) = config::file() {
+            entries.push(("config".to_owned(), path.to_owned()));
+            entries.push(("config_file".to_owned(), contents));
+        }
+        entries.push((
+            "tool_commit".to_owned(),
+            env!("DMLDDL_GIT_COMMIT").to_owned(),
+        ));
+        match server(conn).await {
+            Ok(server) => entries.extend(server),
+            Err(e) => warn!("failed to read the server's metadata: {:?}", e),
+        }
+        Metadata(entries)
+    }
+
+    /// Write a `# key: value` line per entry; multi-line values take a line
+    /// each.
+    pub fn write_header(&self, out: &mut impl Write) -> Result<()> {
+        for (key, value) in &self.0 {
+            for line in value.lines() {
+                writeln!(out, "# {}: {}", key, line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `args` with the value of `--password` and the user and password of URLs
+/// hidden.
+fn redact_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut redacted = Vec::new();
+    let mut password = false;
+    for arg in args {
+        redacted.push(if password {
+            "<hidden>".to_owned()
+        } else if arg.starts_with("--password=") {
+            "--password=<hidden>".to_owned()
+        } else {
+            redact_url(&arg)
+        });
+        password = arg == "--password";
+    }
+    redacted
+}
+
+async fn server(conn: &mut MySqlConnection) -> Result<Vec<(String, String)>> {
+    let text = |row: &sqlx::mysql::MySqlRow, i| -> Result<String> {
+        let v: Option<String> = row.try_get_unchecked(i)?;
+        Ok(v.unwrap_or_default())
+    };
+    let mut entries = Vec::new();
+    let row = conn.fetch_one("select tidb_version()").await?;
+    entries.push(("tidb_version".to_owned(), text(&row, 0)?));
+    let names = VARIABLES
+        .iter()
+        .map(|v| format!("'{}'", v))
+        .collect::<Vec<_>>()
+        .join(",");
+    for scope in ["session", "global"] {
+        let sql = format!(
+            "show {} variables where variable_name in ({})",
+            scope, names
+        );
+        for row in conn.fetch_all(sql.as_str()).await? {
+            entries.push((format!("{} {}", scope, text(&row, 0)?), text(&row, 1)?));
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn args_redacted() {
+        let args = [
+            "scripts",
+            "--url",
+            "mysql://root:pw@127.0.0.1:4000/test",
+            "--password",
+            "pw",
+            "--password=pw",
+            "--url=mysql://u@h:4000/db?ssl-mode=disabled",
+            "update",
+            "--seed",
+            "42",
+        ];
+        assert_eq!(
+            redact_args(args.iter().map(|a| a.to_string())),
+            [
+                "scripts",
+                "--url",
+                "mysql://<hidden>@127.0.0.1:4000/test",
+                "--password",
+                "<hidden>",
+                "--password=<hidden>",
+                "--url=mysql://<hidden>@h:4000/db?ssl-mode=disabled",
+                "update",
+                "--seed",
+                "42",
+            ]
+        );
+        assert_eq!(
+            redact_url("mysql://127.0.0.1:4000/a@b"),
+            "mysql://127.0.0.1:4000/a@b"
+        );
+    }
+}
@@ -0,0 +1,831 @@
+//! Counts, errors and latencies of operations: [`RunStats`] of the runner's
+//! workers, and [`Latencies`] for whatever else a subcommand or workload
+//! times, e.g. commits or one path of a statement.
+//!
+//! Latencies are kept exactly by default. Runs of hundreds of millions of
+//! operations can keep them in a [`Sketch`] instead with `--latency-sketch`,
+//! see [`use_sketch`].
+use crate::error::{ErrorClass, ErrorCounts};
+use crate::otlp;
+use crate::runner::MeasureWindow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Width of the intervals of an [`ErrorSeries`].
+pub const ERROR_SERIES_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Width of the intervals of a [`LatencySeries`].
+pub const LATENCY_SERIES_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Relative error of the percentiles of a [`Sketch`].
+const SKETCH_ACCURACY: f64 = 0.01;
+
+static SKETCH: AtomicBool = AtomicBool::new(false);
+
+/// Keep the latencies created from now on in a [`Sketch`] rather than
+/// exactly. Set once at startup, as exact latencies and sketches don't merge
+/// without loss.
+pub fn use_sketch(on: bool) {
+    SKETCH.store(on, Ordering::Relaxed);
+}
+
+/// Latencies in buckets whose bounds grow by a constant factor, as in
+/// DDSketch: every percentile is within [`SKETCH_ACCURACY`] of the exact one,
+/// memory only grows with the logarithm of the range of the latencies, and
+/// merging adds up the buckets.
+#[derive(Debug, Default, Clone)]
+pub struct Sketch {
+    // bucket i counts latencies in (gamma^(i-1), gamma^i] nanoseconds
+    buckets: BTreeMap<i32, u64>,
+    // latencies of 0ns, which have no bucket
+    zeros: u64,
+    count: u64,
+    min: Duration,
+    max: Duration,
+    // of the latencies in nanoseconds, for the standard deviation
+    sum: f64,
+    sum_of_squares: f64,
+}
+
+impl Sketch {
+    fn gamma() -> f64 {
+        (1.0 + SKETCH_ACCURACY) / (1.0 - SKETCH_ACCURACY)
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        self.min = if self.count == 0 {
+            latency
+        } else {
+            self.min.min(latency)
+        };
+        self.count += 1;
+        self.max = self.max.max(latency);
+        let nanos = latency.as_nanos() as f64;
+        self.sum += nanos;
+        self.sum_of_squares += nanos * nanos;
+        if nanos < 1.0 {
+            self.zeros += 1;
+            return;
+        }
+        let i = (nanos.ln() / Self::gamma().ln()).ceil() as i32;
+        *self.buckets.entry(i).or_default() += 1;
+    }
+
+    pub fn merge(&mut self, other: &Sketch) {
+        for (i, n) in &other.buckets {
+            *self.buckets.entry(*i).or_default() += n;
+        }
+        if other.count > 0 {
+            self.min = if self.count == 0 {
+                other.min
+            } else {
+                self.min.min(other.min)
+            };
+        }
+        self.zeros += other.zeros;
+        self.count += other.count;
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+        self.sum_of_squares += other.sum_of_squares;
+    }
+
+    /// The standard deviation, exact unlike the percentiles; `None` if
+    /// nothing was recorded.
+    pub fn stddev(&self) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let mean = self.sum / self.count as f64;
+        let variance = (self.sum_of_squares / self.count as f64 - mean * mean).max(0.0);
+        Some(Duration::from_nanos(variance.sqrt() as u64))
+    }
+
+    /// The `p`-th percentile, `None` if nothing was recorded.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((self.count - 1) as f64 * p / 100.0).round() as u64;
+        if rank < self.zeros {
+            return Some(Duration::ZERO);
+        }
+        let mut seen = self.zeros;
+        for (i, n) in &self.buckets {
+            seen += n;
+            if seen > rank {
+                // the middle of the bucket in relative terms
+                let gamma = Self::gamma();
+                let nanos = 2.0 * gamma.powi(*i) / (gamma + 1.0);
+                return Some(Duration::from_nanos(nanos as u64).min(self.max));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+/// Latencies of one kind of operation, exactly or in a [`Sketch`] as chosen
+/// by [`use_sketch`] when created. Exact percentiles are only right after
+/// [`Latencies::sort`]; [`Latencies::clear`] starts a new window.
+#[derive(Debug, Clone)]
+pub enum Latencies {
+    Exact(Vec<Duration>),
+    Sketch(Sketch),
+}
+
+impl Default for Latencies {
+    fn default() -> Self {
+        if SKETCH.load(Ordering::Relaxed) {
+            Latencies::Sketch(Sketch::default())
+        } else {
+            Latencies::Exact(Vec::new())
+        }
+    }
+}
+
+impl Latencies {
+    pub fn record(&mut self, latency: Duration) {
+        match self {
+            Latencies::Exact(l) => l.push(latency),
+            Latencies::Sketch(s) => s.record(latency),
+        }
+    }
+
+    pub fn merge(&mut self, other: Latencies) {
+        match (self, other) {
+            (Latencies::Exact(l), Latencies::Exact(o)) => l.extend(o),
+            (Latencies::Sketch(s), Latencies::Sketch(o)) => s.merge(&o),
+            (Latencies::Sketch(s), Latencies::Exact(o)) => o.into_iter().for_each(|l| s.record(l)),
+            (this @ Latencies::Exact(_), Latencies::Sketch(mut o)) => {
+                if let Latencies::Exact(l) = this {
+                    l.iter().for_each(|l| o.record(*l));
+                }
+                *this = Latencies::Sketch(o);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Latencies::Exact(l) => l.len(),
+            Latencies::Sketch(s) => s.count as usize,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn sort(&mut self) {
+        if let Latencies::Exact(l) = self {
+            l.sort();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        match self {
+            Latencies::Exact(l) => l.clear(),
+            Latencies::Sketch(s) => *s = Sketch::default(),
+        }
+    }
+
+    /// The `p`-th percentile, `None` if nothing was recorded.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        match self {
+            Latencies::Exact(l) if l.is_empty() => None,
+            Latencies::Exact(l) => {
+                let i = ((l.len() - 1) as f64 * p / 100.0).round() as usize;
+                Some(l[i])
+            }
+            Latencies::Sketch(s) => s.percentile(p),
+        }
+    }
+
+    /// The smallest latency, `None` if nothing was recorded.
+    pub fn min(&self) -> Option<Duration> {
+        match self {
+            Latencies::Exact(l) => l.iter().min().copied(),
+            Latencies::Sketch(s) => (s.count > 0).then_some(s.min),
+        }
+    }
+
+    /// The largest latency, `None` if nothing was recorded.
+    pub fn max(&self) -> Option<Duration> {
+        match self {
+            Latencies::Exact(l) => l.iter().max().copied(),
+            Latencies::Sketch(s) => (s.count > 0).then_some(s.max),
+        }
+    }
+
+    /// The standard deviation, `None` if nothing was recorded.
+    pub fn stddev(&self) -> Option<Duration> {
+        match self {
+            Latencies::Exact(l) if l.is_empty() => None,
+            Latencies::Exact(l) => {
+                let nanos = || l.iter().map(|l| l.as_nanos() as f64);
+                let mean = nanos().sum::<f64>() / l.len() as f64;
+                let variance = nanos().map(|n| (n - mean).powi(2)).sum::<f64>() / l.len() as f64;
+                Some(Duration::from_nanos(variance.sqrt() as u64))
+            }
+            Latencies::Sketch(s) => s.stddev(),
+        }
+    }
+}
+
+/// Count, minimum, median, p99, p99.9, maximum and standard deviation, e.g.
+/// `12, min: 0.9ms, p50: 1.2ms, p99: 3.4ms, p99.9: 5.1ms, max: 5.6ms,
+/// stddev: 0.4ms`.
+impl fmt::Display for Latencies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (
+            self.min(),
+            self.percentile(50.0),
+            self.percentile(99.0),
+            self.percentile(99.9),
+            self.max(),
+            self.stddev(),
+        ) {
+            (Some(min), Some(p50), Some(p99), Some(p999), Some(max), Some(stddev)) => write!(
+                f,
+                "{}, min: {:.1?}, p50: {:.1?}, p99: {:.1?}, p99.9: {:.1?}, max: {:.1?}, \
+                stddev: {:.1?}",
+                self.len(),
+                min,
+                p50,
+                p99,
+                p999,
+                max,
+                stddev
+            ),
+            _ => write!(f, "0"),
+        }
+    }
+}
+
+/// Latencies of each statement of a transaction, e.g. to tell the time spent
+/// waiting for locks in an UPDATE from that of 2PC in the COMMIT.
+#[derive(Debug, Default, Clone)]
+pub struct StatementLatencies {
+    latencies: BTreeMap<&'static str, Latencies>,
+    // keep every statement's latencies in a sketch whatever `use_sketch` says
+    sketch: bool,
+    // statements timed since the last `take_timed`, while tracing
+    timed: Vec<TimedStatement>,
+}
+
+/// A statement timed with [`StatementLatencies::timed`], for a child span of
+/// its iteration's, see [`crate::otlp`].
+#[derive(Debug, Clone)]
+pub struct TimedStatement {
+    pub statement: &'static str,
+    pub start: SystemTime,
+    pub latency: Duration,
+}
+
+impl StatementLatencies {
+    /// Latencies kept in a [`Sketch`] even without `--latency-sketch`, for
+    /// runs too long to keep every latency of.
+    pub fn sketched() -> Self {
+        StatementLatencies {
+            sketch: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn record(&mut self, statement: &'static str, latency: Duration) {
+        let sketch = self.sketch;
+        self.latencies
+            .entry(statement)
+            .or_insert_with(|| {
+                if sketch {
+                    Latencies::Sketch(Sketch::default())
+                } else {
+                    Latencies::default()
+                }
+            })
+            .record(latency);
+    }
+
+    /// Await `f`, recording how long it took as a latency of `statement`
+    /// whether or not it failed.
+    pub async fn timed<F: Future>(&mut self, statement: &'static str, f: F) -> F::Output {
+        let start = SystemTime::now();
+        let begin = std::time::Instant::now();
+        let output = f.await;
+        let latency = begin.elapsed();
+        self.record(statement, latency);
+        if otlp::enabled() {
+            self.timed.push(TimedStatement {
+                statement,
+                start,
+                latency,
+            });
+        }
+        output
+    }
+
+    /// The statements timed since the last call, only kept while tracing.
+    pub fn take_timed(&mut self) -> Vec<TimedStatement> {
+        std::mem::take(&mut self.timed)
+    }
+
+    pub fn merge(&mut self, other: StatementLatencies) {
+        for (statement, latencies) in other.latencies {
+            self.latencies
+                .entry(statement)
+                .or_default()
+                .merge(latencies);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latencies.is_empty()
+    }
+
+    pub fn sort(&mut self) {
+        self.latencies.values_mut().for_each(Latencies::sort);
+    }
+}
+
+/// A line per statement, e.g. `commit: 12, p50: ...`.
+impl fmt::Display for StatementLatencies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (statement, latencies)) in self.latencies.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}: {}", statement, latencies)?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors per [`ERROR_SERIES_INTERVAL`] since the start, to see when the
+/// failure mode of a run shifts, e.g. from write conflicts to lock wait
+/// timeouts.
+#[derive(Debug, Default, Clone)]
+pub struct ErrorSeries(Vec<ErrorCounts>);
+
+impl ErrorSeries {
+    /// Count an error of `class` that happened `at` after the start.
+    pub fn record(&mut self, at: Duration, class: ErrorClass) {
+        let i = (at.as_secs_f64() / ERROR_SERIES_INTERVAL.as_secs_f64()) as usize;
+        if self.0.len() <= i {
+            self.0.resize_with(i + 1, ErrorCounts::default);
+        }
+        self.0[i].add(class);
+    }
+
+    pub fn merge(&mut self, other: &ErrorSeries) {
+        if self.0.len() < other.0.len() {
+            self.0.resize_with(other.0.len(), ErrorCounts::default);
+        }
+        for (counts, other) in self.0.iter_mut().zip(&other.0) {
+            counts.merge(other);
+        }
+    }
+}
+
+/// A line per interval with errors, e.g. `10s-20s: 3.1/s (write conflict:
+/// 2.0/s, lock wait timeout: 1.1/s)`.
+impl fmt::Display for ErrorSeries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = ERROR_SERIES_INTERVAL.as_secs_f64();
+        let mut first = true;
+        for (i, counts) in self.0.iter().enumerate() {
+            if counts.total() == 0 {
+                continue;
+            }
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            let classes: Vec<_> = counts
+                .iter()
+                .map(|(class, n)| format!("{}: {:.1}/s", class, n as f64 / secs))
+                .collect();
+            write!(
+                f,
+                "{:.0}s-{:.0}s: {:.1}/s ({})",
+                i as f64 * secs,
+                (i + 1) as f64 * secs,
+                counts.total() as f64 / secs,
+                classes.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Buckets of [`LiveStats`]: bucket i counts latencies below
+/// `LIVE_GROWTH^i` microseconds, the last one everything longer.
+const LIVE_BUCKETS: usize = 256;
+const LIVE_GROWTH: f64 = 1.1;
+
+/// Running totals of the workers of a workload, readable while they run.
+/// Latencies go to coarse buckets of atomics, so workers never wait for each
+/// other and percentiles of an interval are within 10%.
+#[derive(Debug)]
+pub struct LiveStats {
+    succeeded: AtomicU64,
+    errors: AtomicU64,
+    latency_nanos: AtomicU64,
+    buckets: Vec<AtomicU64>,
+}
+
+/// [`LiveStats`] at some point.
+#[derive(Debug, Clone)]
+pub struct LiveSnapshot {
+    pub succeeded: u64,
+    pub errors: u64,
+    /// Sum of the latencies of the succeeded iterations.
+    pub latency_nanos: u64,
+    buckets: Vec<u64>,
+}
+
+impl Default for LiveStats {
+    fn default() -> Self {
+        LiveStats {
+            succeeded: AtomicU64::default(),
+            errors: AtomicU64::default(),
+            latency_nanos: AtomicU64::default(),
+            buckets: (0..LIVE_BUCKETS).map(|_| AtomicU64::default()).collect(),
+        }
+    }
+}
+
+impl LiveStats {
+    pub fn succeeded(&self, latency: Duration) {
+        self.succeeded.fetch_add(1, Ordering::Relaxed);
+        self.latency_nanos
+            .fetch_add(latency.as_nanos() as u64, Ordering::Relaxed);
+        let micros = latency.as_micros().max(1) as f64;
+        let i = ((micros.ln() / LIVE_GROWTH.ln()).floor() as usize + 1).min(LIVE_BUCKETS - 1);
+        self.buckets[i].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn failed(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> LiveSnapshot {
+        LiveSnapshot {
+            succeeded: self.succeeded.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            latency_nanos: self.latency_nanos.load(Ordering::Relaxed),
+            buckets: self
+                .buckets
+                .iter()
+                .map(|b| b.load(Ordering::Relaxed))
+                .collect(),
+        }
+    }
+}
+
+impl LiveSnapshot {
+    /// The `p`-th percentile of the latencies since `earlier`, the upper
+    /// bound of its bucket. `None` if nothing succeeded since.
+    pub fn percentile_since(&self, earlier: &LiveSnapshot, p: f64) -> Option<Duration> {
+        let succeeded = self.succeeded - earlier.succeeded;
+        if succeeded == 0 {
+            return None;
+        }
+        let rank = ((succeeded - 1) as f64 * p / 100.0).round() as u64;
+        let mut seen = 0;
+        for (i, (now, then)) in self.buckets.iter().zip(&earlier.buckets).enumerate() {
+            seen += now - then;
+            if seen > rank {
+                let micros = LIVE_GROWTH.powi(i as i32);
+                return Some(Duration::from_micros(micros as u64));
+            }
+        }
+        None
+    }
+}
+
+/// Latencies per [`LATENCY_SERIES_INTERVAL`] since the start, in sketches
+/// so a long run takes little memory, for charts of percentiles over time.
+#[derive(Debug, Default, Clone)]
+pub struct LatencySeries(Vec<Sketch>);
+
+impl LatencySeries {
+    /// Record `latency` of an operation that finished `at` after the start.
+    pub fn record(&mut self, at: Duration, latency: Duration) {
+        let i = (at.as_secs_f64() / LATENCY_SERIES_INTERVAL.as_secs_f64()) as usize;
+        if self.0.len() <= i {
+            self.0.resize_with(i + 1, Sketch::default);
+        }
+        self.0[i].record(latency);
+    }
+
+    pub fn merge(&mut self, other: &LatencySeries) {
+        if self.0.len() < other.0.len() {
+            self.0.resize_with(other.0.len(), Sketch::default);
+        }
+        for (sketch, other) in self.0.iter_mut().zip(&other.0) {
+            sketch.merge(other);
+        }
+    }
+
+    /// The `p`-th percentile of each interval, `None` of those without
+    /// latencies.
+    pub fn percentiles(&self, p: f64) -> Vec<Option<Duration>> {
+        self.0.iter().map(|s| s.percentile(p)).collect()
+    }
+}
+
+/// Successes in each second since the start, samples of the throughput for
+/// telling a real difference between two runs from noise, see
+/// [`mann_whitney`].
+#[derive(Debug, Default, Clone)]
+pub struct PerSecond(Vec<u64>);
+
+impl PerSecond {
+    /// Count a success `at` after the start.
+    pub fn record(&mut self, at: Duration) {
+        let i = at.as_secs() as usize;
+        if self.0.len() <= i {
+            self.0.resize(i + 1, 0);
+        }
+        self.0[i] += 1;
+    }
+
+    pub fn merge(&mut self, other: &PerSecond) {
+        if self.0.len() < other.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+        for (n, other) in self.0.iter_mut().zip(&other.0) {
+            *n += other;
+        }
+    }
+
+    /// Counts of each second, the last one usually partial.
+    pub fn all(&self) -> &[u64] {
+        &self.0
+    }
+
+    /// Counts of the whole seconds in `window`, i.e. without the last one,
+    /// which the run usually ends in the middle of.
+    pub fn samples(&self, window: &MeasureWindow) -> &[u64] {
+        &self.0[window.whole_seconds(self.0.len().saturating_sub(1))]
+    }
+}
+
+/// Mean, standard deviation and range of a measure over repeated runs.
+#[derive(Debug, Clone, Copy)]
+pub struct Spread {
+    pub mean: f64,
+    /// The sample standard deviation, 0 of a single value.
+    pub stddev: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Spread {
+    /// `None` if `values` is empty.
+    pub fn of(values: &[f64]) -> Option<Spread> {
+        if values.is_empty() {
+            return None;
+        }
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = if values.len() > 1 {
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)
+        } else {
+            0.0
+        };
+        Some(Spread {
+            mean,
+            stddev: variance.sqrt(),
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+}
+
+/// E.g. `mean 1012.3, stddev 21.0, min 990.1, max 1040.2`.
+impl fmt::Display for Spread {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "mean {:.2}, stddev {:.2}, min {:.2}, max {:.2}",
+            self.mean, self.stddev, self.min, self.max
+        )
+    }
+}
+
+/// Two-sided p-value of the Mann-Whitney U test of `a` and `b` having the
+/// same distribution, by the normal approximation with a correction for
+/// ties. Meaningful from about 10 samples each; `None` if either is empty or
+/// all samples are equal.
+pub fn mann_whitney(a: &[f64], b: &[f64]) -> Option<f64> {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let mut all: Vec<(f64, bool)> = a
+        .iter()
+        .map(|&v| (v, true))
+        .chain(b.iter().map(|&v| (v, false)))
+        .collect();
+    all.sort_by(|x, y| x.0.total_cmp(&y.0));
+    // sum of the ranks of `a`, tied samples sharing their mean rank
+    let mut rank_sum = 0.0;
+    let mut ties = 0.0;
+    let mut i = 0;
+    while i < all.len() {
+        let j = i + all[i..].iter().take_while(|x| x.0 == all[i].0).count();
+        let rank = (i + j + 1) as f64 / 2.0;
+        rank_sum += rank * all[i..j].iter().filter(|x| x.1).count() as f64;
+        let t = (j - i) as f64;
+        ties += t * t * t - t;
+        i = j;
+    }
+    let n = n1 + n2;
+    let u = rank_sum - n1 * (n1 + 1.0) / 2.0;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        return None;
+    }
+    let z = (u - n1 * n2 / 2.0).abs() / variance.sqrt();
+    Some(erfc(z / std::f64::consts::SQRT_2).min(1.0))
+}
+
+/// The complementary error function of `x >= 0`, within 1.2e-7
+/// (Numerical Recipes' erfcc).
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.5 * x);
+    let poly = -x * x - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398
+                                + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    t * poly.exp()
+}
+
+#[derive(Debug, Default)]
+pub struct RunStats {
+    /// Iterations that eventually succeeded, retries not counted.
+    pub succeeded: u64,
+    pub errors: ErrorCounts,
+    /// `errors` over time.
+    pub error_series: ErrorSeries,
+    /// `succeeded` over time, the whole run.
+    pub per_second: PerSecond,
+    /// The iterations counted in `succeeded`, `errors` and `latencies`.
+    pub measure: MeasureWindow,
+    /// Length of the measurement window, the whole run unless narrowed with
+    /// [`MeasureWindow`].
+    pub elapsed: Duration,
+    /// Latency of each succeeded iteration, including its retries. It never
+    /// includes getting a connection from the pool, which is in `acquire`.
+    pub latencies: Latencies,
+    /// Time taken to get each connection from the pool, kept apart from
+    /// `latencies` so pool overhead and contention show as such.
+    pub acquire: Latencies,
+    /// `latencies` over time.
+    pub latency_series: LatencySeries,
+    /// Invariant checks that failed.
+    pub violations: u64,
+    /// Times a worker replaced its connection after it broke.
+    pub reconnects: u64,
+    /// Latencies of the statements workloads time, see [`Worker::statements`].
+    ///
+    /// [`Worker::statements`]: crate::workload::Worker::statements
+    pub statements: StatementLatencies,
+}
+
+impl RunStats {
+    pub fn throughput(&self) -> f64 {
+        self.succeeded as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// The `p`-th percentile of `latencies`, which must be sorted.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        self.latencies.percentile(p)
+    }
+
+    /// Add the counts and latencies of `other`, leaving `elapsed` alone.
+    pub fn merge(&mut self, other: RunStats) {
+        self.succeeded += other.succeeded;
+        self.reconnects += other.reconnects;
+        self.errors.merge(&other.errors);
+        self.error_series.merge(&other.error_series);
+        self.per_second.merge(&other.per_second);
+        self.latency_series.merge(&other.latency_series);
+        self.latencies.merge(other.latencies);
+        self.acquire.merge(other.acquire);
+        self.statements.merge(other.statements);
+    }
+}
+
+impl fmt::Display for RunStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ok, {:.1}/s, {} errors",
+            self.succeeded,
+            self.throughput(),
+            self.errors.total()
+        )?;
+        if let (Some(min), Some(p50), Some(p99), Some(p999), Some(max), Some(stddev)) = (
+            self.latencies.min(),
+            self.percentile(50.0),
+            self.percentile(99.0),
+            self.percentile(99.9),
+            self.latencies.max(),
+            self.latencies.stddev(),
+        ) {
+            write!(
+                f,
+                ", min: {:.1?}, p50: {:.1?}, p99: {:.1?}, p99.9: {:.1?}, max: {:.1?}, \
+                stddev: {:.1?}",
+                min, p50, p99, p999, max, stddev
+            )?;
+        }
+        if self.violations > 0 {
+            write!(f, ", {} invariant violations", self.violations)?;
+        }
+        if self.reconnects > 0 {
+            write!(f, ", {} reconnects", self.reconnects)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sketch_percentiles_within_accuracy() {
+        // 1us to 100ms, uniform: the exact p-th percentile is the latency of
+        // the rank the sketch rounds to
+        let n = 100_000u64;
+        let mut sketch = Sketch::default();
+        for us in 1..=n {
+            sketch.record(Duration::from_micros(us));
+        }
+        for p in [0.0, 1.0, 10.0, 50.0, 90.0, 99.0, 99.9, 100.0] {
+            let rank = ((n - 1) as f64 * p / 100.0).round() as u64;
+            let exact = Duration::from_micros(rank + 1).as_secs_f64();
+            let estimate = sketch.percentile(p).unwrap().as_secs_f64();
+            let error = (estimate - exact).abs() / exact;
+            assert!(
+                error <= SKETCH_ACCURACY,
+                "p{}: {} vs {}",
+                p,
+                estimate,
+                exact
+            );
+        }
+    }
+
+    #[test]
+    fn mann_whitney_with_ties() {
+        // ranks of a: 1.5 1.5 3 4 5.5 7.5, so R1 = 23 and U = 2; ties of 2
+        // at 2, 5 and 6 make the variance 36 * (13 - 18 / 132) / 12 = 38.59,
+        // z = 16 / 6.212 = 2.576 and the two-sided p-value 0.0100
+        let a = [3.0, 4.0, 2.0, 6.0, 2.0, 5.0];
+        let b = [9.0, 7.0, 5.0, 10.0, 6.0, 8.0];
+        let p = mann_whitney(&a, &b).unwrap();
+        assert!((p - 0.010007).abs() < 1e-5, "{}", p);
+        assert_eq!(mann_whitney(&b, &a), Some(p));
+    }
+
+    #[test]
+    fn mann_whitney_degenerate() {
+        assert_eq!(mann_whitney(&[], &[1.0]), None);
+        assert_eq!(mann_whitney(&[1.0, 1.0], &[1.0, 1.0]), None);
+    }
+
+    #[test]
+    fn per_second_samples_in_window() {
+        // i + 1 successes in second i up to second 9, taken as partial
+        let mut per_second = PerSecond::default();
+        for i in 0..10u64 {
+            for _ in 0..=i {
+                per_second.record(Duration::from_millis(i * 1000 + 500));
+            }
+        }
+        let window = |start, end: Option<u64>| {
+            MeasureWindow::new(Duration::from_millis(start), end.map(Duration::from_secs)).unwrap()
+        };
+        assert_eq!(
+            per_second.samples(&window(0, None)),
+            &[1, 2, 3, 4, 5, 6, 7, 8, 9]
+        );
+        assert_eq!(per_second.samples(&window(2000, Some(5))), &[3, 4, 5]);
+        // second 2 is only partly in the window
+        assert_eq!(
+            per_second.samples(&window(1500, Some(20))),
+            &[3, 4, 5, 6, 7, 8, 9]
+        );
+        assert!(per_second.samples(&window(12000, None)).is_empty());
+    }
+}
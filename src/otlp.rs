@@ -0,0 +1,292 @@
+//! Tracing of the runner's workers to an OpenTelemetry collector, with
+//! `--otlp-endpoint`, e.g. to line up the slow transactions of a run with
+//! TiDB's own traces.
+//!
+//! Every attempt of every iteration is a span named after its workload, with
+//! the worker, the attempt from 1 and the error class if it failed. The
+//! statements a workload times with [`StatementLatencies::timed`] are child
+//! spans of it. Spans are sent in batches as OTLP/HTTP JSON to
+//! `<endpoint>/v1/traces`, under the service `dmlddl` and the name of the
+//! run. If the collector can't keep up, spans are dropped rather than
+//! slowing the workers down.
+//!
+//! [`StatementLatencies::timed`]: crate::metrics::StatementLatencies::timed
+use crate::error::ErrorClass;
+use crate::metrics::TimedStatement;
+use crate::output;
+use log::{info, warn};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::select;
+use tokio::sync::{mpsc, oneshot};
+
+const EXPORT_INTERVAL: Duration = Duration::from_secs(1);
+const BATCH_SIZE: usize = 512;
+// spans waiting to be exported; more are dropped
+const QUEUE_SIZE: usize = 65536;
+
+static EXPORTER: OnceLock<Exporter> = OnceLock::new();
+
+struct Exporter {
+    tx: mpsc::Sender<Message>,
+    dropped: AtomicU64,
+}
+
+enum Message {
+    Span(Span),
+    /// Export what is queued, then signal.
+    Flush(oneshot::Sender<()>),
+}
+
+struct Span {
+    trace_id: u128,
+    span_id: u64,
+    parent_id: Option<u64>,
+    name: String,
+    start: SystemTime,
+    latency: Duration,
+    attributes: Vec<(&'static str, String)>,
+    error: Option<String>,
+}
+
+/// Trace to the collector at `endpoint`, e.g. `http://127.0.0.1:4318`, from
+/// now on.
+pub fn init(endpoint: &str) {
+    let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+    info!("exporting spans to {}", url);
+    let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+    tokio::spawn(export_loop(url, rx));
+    let _ = EXPORTER.set(Exporter {
+        tx,
+        dropped: AtomicU64::new(0),
+    });
+}
+
+pub fn enabled() -> bool {
+    EXPORTER.get().is_some()
+}
+
+/// Trace an attempt of an iteration of `workload` by `worker` that started
+/// at `start`, took `latency` and failed with `error`, if any, with a child
+/// span per statement in `statements`.
+pub fn iteration(
+    workload: &str,
+    worker: usize,
+    attempt: u32,
+    start: SystemTime,
+    latency: Duration,
+    error: Option<ErrorClass>,
+    statements: Vec<TimedStatement>,
+) {
+    let Some(exporter) = EXPORTER.get() else {
+        return;
+    };
+    let trace_id = rand::random();
+    let span_id = rand::random();
+    let mut spans = vec![Span {
+        trace_id,
+        span_id,
+        parent_id: None,
+        name: workload.to_owned(),
+        start,
+        latency,
+        attributes: vec![
+            ("worker", worker.to_string()),
+            ("attempt", attempt.to_string()),
+        ],
+        error: error.map(|class| class.to_string()),
+    }];
+    spans.extend(statements.into_iter().map(|s| Span {
+        trace_id,
+        span_id: rand::random(),
+        parent_id: Some(span_id),
+        name: s.statement.to_owned(),
+        start: s.start,
+        latency: s.latency,
+        attributes: Vec::new(),
+        error: None,
+    }));
+    for span in spans {
+        if exporter.tx.try_send(Message::Span(span)).is_err() {
+            exporter.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Export the queued spans, e.g. when a run ends.
+pub async fn flush() {
+    let Some(exporter) = EXPORTER.get() else {
+        return;
+    };
+    let (done, wait) = oneshot::channel();
+    if exporter.tx.send(Message::Flush(done)).await.is_ok() {
+        let _ = wait.await;
+    }
+    let dropped = exporter.dropped.swap(0, Ordering::Relaxed);
+    if dropped > 0 {
+        warn!("dropped {} spans, the collector is too slow", dropped);
+    }
+}
+
+async fn export_loop(url: String, mut rx: mpsc::Receiver<Message>) {
+    let client = reqwest::Client::new();
+    let resource = resource();
+    let mut batch = Vec::new();
+    let mut tick = tokio::time::interval(EXPORT_INTERVAL);
+    loop {
+        select! {
+            message = rx.recv() => match message {
+                Some(Message::Span(span)) => {
+                    batch.push(span);
+                    if batch.len() >= BATCH_SIZE {
+                        export(&client, &url, &resource, &mut batch).await;
+                    }
+                }
+                Some(Message::Flush(done)) => {
+                    export(&client, &url, &resource, &mut batch).await;
+                    let _ = done.send(());
+                }
+                None => return,
+            },
+            _ = tick.tick() => export(&client, &url, &resource, &mut batch).await,
+        }
+    }
+}
+
+/// The resource of every span.
+fn resource() -> Resource {
+    Resource {
+        attributes: vec![
+            KeyValue::new("service.name", "dmlddl"),
+            KeyValue::new("run_id", output::run_name()),
+        ],
+    }
+}
+
+/// Send `batch` and empty it. Failures only log a warning, so the collector
+/// never stops a run.
+async fn export(client: &reqwest::Client, url: &str, resource: &Resource, batch: &mut Vec<Span>) {
+    if batch.is_empty() {
+        return;
+    }
+    let request = ExportRequest {
+        resource_spans: [ResourceSpans {
+            resource,
+            scope_spans: [ScopeSpans {
+                scope: Scope { name: "dmlddl" },
+                spans: batch.drain(..).map(Span::into_otlp).collect(),
+            }],
+        }],
+    };
+    let res = client
+        .post(url)
+        .json(&request)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+    if let Err(e) = res {
+        warn!("failed to export spans to {}: {}", url, e);
+    }
+}
+
+impl Span {
+    fn into_otlp(self) -> OtlpSpan {
+        let nanos = |t: SystemTime| t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+        let start = nanos(self.start);
+        OtlpSpan {
+            trace_id: format!("{:032x}", self.trace_id),
+            span_id: format!("{:016x}", self.span_id),
+            parent_span_id: self.parent_id.map(|id| format!("{:016x}", id)),
+            name: self.name,
+            // internal for iterations, client for the statements they send
+            kind: if self.parent_id.is_some() { 3 } else { 1 },
+            start_time_unix_nano: start.to_string(),
+            end_time_unix_nano: (start + self.latency.as_nanos()).to_string(),
+            attributes: self
+                .attributes
+                .into_iter()
+                .map(|(key, value)| KeyValue::new(key, value))
+                .collect(),
+            status: self.error.map(|message| Status { code: 2, message }),
+        }
+    }
+}
+
+// The OTLP/HTTP JSON encoding of ExportTraceServiceRequest, as far as used.
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportRequest<'a> {
+    resource_spans: [ResourceSpans<'a>; 1],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourceSpans<'a> {
+    resource: &'a Resource,
+    scope_spans: [ScopeSpans; 1],
+}
+
+#[derive(Serialize)]
+struct Resource {
+    attributes: Vec<KeyValue>,
+}
+
+#[derive(Serialize)]
+struct ScopeSpans {
+    scope: Scope,
+    spans: Vec<OtlpSpan>,
+}
+
+#[derive(Serialize)]
+struct Scope {
+    name: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OtlpSpan {
+    trace_id: String,
+    span_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_span_id: Option<String>,
+    name: String,
+    kind: u8,
+    // 64-bit integers are strings in OTLP JSON
+    start_time_unix_nano: String,
+    end_time_unix_nano: String,
+    attributes: Vec<KeyValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<Status>,
+}
+
+#[derive(Serialize)]
+struct Status {
+    code: u8,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct KeyValue {
+    key: String,
+    value: AnyValue,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnyValue {
+    string_value: String,
+}
+
+impl KeyValue {
+    fn new(key: &str, value: impl Into<String>) -> KeyValue {
+        KeyValue {
+            key: key.to_owned(),
+            value: AnyValue {
+                string_value: value.into(),
+            },
+        }
+    }
+}
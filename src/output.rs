@@ -0,0 +1,48 @@
+//! Where a run writes its files. With `--output-dir` or `--run-name`, logs,
+//! results, charts and recordings of a run go to a directory of its own,
+//! `<output dir>/<run name>`, and results are written even without
+//! `--results`. Without either, files go to the working directory as before.
+use crate::Result;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static RUN_NAME: OnceLock<String> = OnceLock::new();
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Name the run `run_name`, by default its start time in seconds since the
+/// epoch, and create its directory if `output_dir` or `run_name` is given.
+pub fn init(output_dir: Option<&str>, run_name: Option<&str>) -> Result<()> {
+    let name = run_name.map(str::to_owned).unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+            .to_string()
+    });
+    if output_dir.is_some() || run_name.is_some() {
+        let dir = Path::new(output_dir.unwrap_or(".")).join(&name);
+        std::fs::create_dir_all(&dir)?;
+        let _ = DIR.set(dir);
+    }
+    let _ = RUN_NAME.set(name);
+    Ok(())
+}
+
+/// The name of the run, e.g. to label its metrics.
+pub fn run_name() -> &'static str {
+    RUN_NAME.get().map_or("", String::as_str)
+}
+
+/// The directory of the run, if it has one.
+pub fn dir() -> Option<&'static Path> {
+    DIR.get().map(PathBuf::as_path)
+}
+
+/// Where to write the file `name`: in the run's directory if it has one.
+/// Absolute paths are kept.
+pub fn path(name: &str) -> String {
+    match dir() {
+        Some(dir) => dir.join(name).display().to_string(),
+        None => name.to_owned(),
+    }
+}
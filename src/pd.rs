@@ -0,0 +1,213 @@
+//! Minimal client of the PD HTTP API, used to report cluster topology and to
+//! notice stores going down during a run.
+//!
+//! The regions of a benchmark table come from `SHOW TABLE ... REGIONS`, and
+//! the stores of their peers from PD, so their spread over the stores can be
+//! reported.
+use crate::Result;
+use log::{info, warn};
+use serde::Deserialize;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{Executor, Row};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::time::Duration;
+
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+pub struct PdClient {
+    client: reqwest::Client,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct StoresResponse {
+    stores: Vec<StoreInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StoreInfo {
+    store: StoreMeta,
+    status: StoreStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct StoreMeta {
+    id: u64,
+    address: String,
+    state_name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StoreStatus {
+    #[serde(default)]
+    leader_count: u64,
+    #[serde(default)]
+    region_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegionInfo {
+    #[serde(default)]
+    peers: Vec<Peer>,
+    leader: Option<Peer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Peer {
+    store_id: u64,
+}
+
+/// The regions of a table and how they are spread over the stores.
+#[derive(Debug)]
+pub struct TableRegions {
+    pub table: String,
+    pub regions: u64,
+    /// Regions with a peer on the store and regions led by it, by store id.
+    pub stores: BTreeMap<u64, (u64, u64)>,
+}
+
+#[derive(Debug)]
+pub struct Store {
+    pub id: u64,
+    pub address: String,
+    pub state: String,
+    pub leader_count: u64,
+    pub region_count: u64,
+}
+
+impl PdClient {
+    /// `addr` is the PD client address, e.g. `127.0.0.1:2379`.
+    pub fn new(addr: &str) -> PdClient {
+        PdClient {
+            client: reqwest::Client::new(),
+            url: format!("http://{}/pd/api/v1", addr),
+        }
+    }
+
+    pub async fn stores(&self) -> Result<Vec<Store>> {
+        let resp: StoresResponse = self
+            .client
+            .get(format!("{}/stores", self.url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(resp
+            .stores
+            .into_iter()
+            .map(|s| Store {
+                id: s.store.id,
+                address: s.store.address,
+                state: s.store.state_name,
+                leader_count: s.status.leader_count,
+                region_count: s.status.region_count,
+            })
+            .collect())
+    }
+
+    /// The regions of `table`, quoted, in the connection's database.
+    pub async fn table_regions(
+        &self,
+        conn: &mut MySqlConnection,
+        table: &str,
+    ) -> Result<TableRegions> {
+        let rows = conn
+            .fetch_all(format!("show table {} regions", table).as_str())
+            .await?;
+        let mut regions = TableRegions {
+            table: table.to_owned(),
+            regions: rows.len() as u64,
+            stores: BTreeMap::new(),
+        };
+        for row in rows {
+            let id: Option<String> = row.try_get_unchecked(0)?;
+            let region: RegionInfo = self
+                .client
+                .get(format!("{}/region/id/{}", self.url, id.unwrap_or_default()))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            for peer in &region.peers {
+                regions.stores.entry(peer.store_id).or_default().0 += 1;
+            }
+            if let Some(leader) = &region.leader {
+                regions.stores.entry(leader.store_id).or_default().1 += 1;
+            }
+        }
+        Ok(regions)
+    }
+
+    /// Log and print the regions of `table`, quoted, only warning if they
+    /// can't be read.
+    pub async fn report_table_regions(&self, conn: &mut MySqlConnection, table: &str) {
+        match self.table_regions(conn, table).await {
+            Ok(regions) => {
+                info!("{}", regions);
+                println!("{}", regions);
+            }
+            Err(e) => warn!("failed to read the regions of {}: {:?}", table, e),
+        }
+    }
+
+    /// Poll the stores forever, logging a warning whenever one changes state
+    /// or disappears.
+    pub async fn watch_stores(&self) {
+        let mut states: HashMap<u64, String> = HashMap::new();
+        loop {
+            match self.stores().await {
+                Ok(stores) => {
+                    for store in &stores {
+                        let prev = states.insert(store.id, store.state.clone());
+                        if prev.as_deref() == Some(store.state.as_str()) {
+                            continue;
+                        }
+                        if store.state == "Up" {
+                            info!("store {} ({}) is Up", store.id, store.address);
+                        } else {
+                            warn!("store {} ({}) is {}", store.id, store.address, store.state);
+                        }
+                    }
+                    states.retain(|id, _| {
+                        let present = stores.iter().any(|s| s.id == *id);
+                        if !present {
+                            warn!("store {} is gone", id);
+                        }
+                        present
+                    });
+                }
+                Err(e) => warn!("failed to query PD: {:?}", e),
+            }
+            tokio::time::sleep(WATCH_INTERVAL).await;
+        }
+    }
+}
+
+/// e.g. `table t: 3 regions; store 1: 3 regions, 2 leaders; store 4: ...`.
+impl fmt::Display for TableRegions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "table {}: {} regions", self.table, self.regions)?;
+        for (store, (regions, leaders)) in &self.stores {
+            write!(
+                f,
+                "; store {}: {} regions, {} leaders",
+                store, regions, leaders
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Store {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "store {} ({}) {}: {} regions, {} leaders",
+            self.id, self.address, self.state, self.region_count, self.leader_count
+        )
+    }
+}
@@ -0,0 +1,74 @@
+//! Placement policies for benchmark tables, e.g. leaders constrained to one
+//! zone, applied before measuring and waited on until PD has scheduled them.
+use crate::error::MyError;
+use crate::Result;
+use log::info;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{Executor, Row};
+use std::time::{Duration, Instant};
+
+const POLICY: &str = "bench_placement";
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+pub struct Placement {
+    /// Options of the policy, e.g. `LEADER_CONSTRAINTS="[+zone=z1]"`.
+    pub options: String,
+    pub tables: Vec<String>,
+    /// How long to wait for the placement to be scheduled.
+    pub timeout: Duration,
+}
+
+impl Placement {
+    /// Create or update the policy, attach it to the tables, and wait until
+    /// all of them are scheduled.
+    pub async fn apply(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute(
+            format!(
+                "create placement policy if not exists {} {}",
+                POLICY, self.options
+            )
+            .as_str(),
+        )
+        .await?;
+        conn.execute(format!("alter placement policy {} {}", POLICY, self.options).as_str())
+            .await?;
+        for table in &self.tables {
+            conn.execute(format!("alter table {} placement policy = {}", table, POLICY).as_str())
+                .await?;
+        }
+        self.wait_scheduled(conn).await
+    }
+
+    async fn wait_scheduled(&self, conn: &mut MySqlConnection) -> Result<()> {
+        let start = Instant::now();
+        loop {
+            let mut pending = Vec::new();
+            for table in &self.tables {
+                let rows = conn
+                    .fetch_all(format!("show placement for table {}", table).as_str())
+                    .await?;
+                for row in rows {
+                    let state: String = row.try_get_unchecked("Scheduling_State")?;
+                    if state != "SCHEDULED" {
+                        let target: String = row.try_get_unchecked("Target")?;
+                        pending.push(format!("{} {}", target, state));
+                    }
+                }
+            }
+            if pending.is_empty() {
+                info!("placement scheduled after {:?}", start.elapsed());
+                return Ok(());
+            }
+            if start.elapsed() > self.timeout {
+                return Err(MyError::StringError(format!(
+                    "placement not scheduled after {:?}: {}",
+                    self.timeout,
+                    pending.join(", ")
+                )));
+            }
+            info!("waiting for placement: {}", pending.join(", "));
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
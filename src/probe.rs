@@ -0,0 +1,64 @@
+//! Periodic latency probes run next to a workload, for operations the
+//! workload itself does not measure.
+use log::{info, warn};
+use sqlx::mysql::{MySqlConnectOptions, MySqlConnection, MySqlPool};
+use sqlx::{Connection, Executor};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+
+const ADMIN_PROBES: &[(&str, &str)] = &[
+    ("show tables", "show tables"),
+    (
+        "information_schema",
+        "select count(*) from information_schema.tables where table_schema = database()",
+    ),
+];
+
+/// Every `interval`, time the admin statements on a pooled connection, and
+/// connecting with `options` plus a BEGIN on that fresh connection, then log them.
+pub async fn admin_probes(pool: &MySqlPool, options: &MySqlConnectOptions, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let mut line = String::from("admin probes:");
+        for (name, sql) in ADMIN_PROBES {
+            let start = Instant::now();
+            match pool.execute(*sql).await {
+                Ok(_) => write!(line, " {}={:?}", name, start.elapsed()).unwrap(),
+                Err(e) => warn!("probe {} failed: {:?}", name, e),
+            }
+        }
+        let start = Instant::now();
+        match MySqlConnection::connect_with(options).await {
+            Ok(mut conn) => {
+                write!(line, " connect={:?}", start.elapsed()).unwrap();
+                let start = Instant::now();
+                match conn.execute("begin").await {
+                    Ok(_) => write!(line, " begin={:?}", start.elapsed()).unwrap(),
+                    Err(e) => warn!("probe begin failed: {:?}", e),
+                }
+                let _ = conn.close().await;
+            }
+            Err(e) => warn!("probe connect failed: {:?}", e),
+        }
+        info!("{}", line);
+    }
+}
+
+/// Time `SELECT 1` on `conn`, which should be otherwise idle, every
+/// `interval` and send each round trip to `tx`. This is the network plus
+/// minimal server cost that every statement pays.
+pub async fn ping(conn: &mut MySqlConnection, interval: Duration, tx: UnboundedSender<Duration>) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let start = Instant::now();
+        match conn.execute("select 1").await {
+            Ok(_) => {
+                if tx.send(start.elapsed()).is_err() {
+                    return;
+                }
+            }
+            Err(e) => warn!("ping failed: {:?}", e),
+        }
+    }
+}
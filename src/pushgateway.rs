@@ -0,0 +1,141 @@
+//! Pushing the stats of the workloads to a Prometheus Pushgateway, so runs
+//! show up in the monitoring of the cluster they stress.
+//!
+//! With `--pushgateway-url`, every workload of every scenario phase pushes
+//! to its own group, `job="dmlddl"` labelled with the `run_id`, the phase as
+//! `mode` and the workload as `operation`. While the phase runs it pushes
+//! the throughput, mean latency and error rate of each
+//! [`PUSH_INTERVAL`], and when it ends those of the whole phase, with
+//! percentiles, the error rate of each class and the reconnects. The label
+//! values are base64-encoded in the URL, so phase and workload names can
+//! contain `/` or be empty.
+use crate::metrics::{LiveStats, RunStats};
+use crate::output;
+use crate::Result;
+use log::{info, warn};
+use std::fmt::Write;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+pub const PUSH_INTERVAL: Duration = Duration::from_secs(15);
+
+static PUSHGATEWAY: OnceLock<Pushgateway> = OnceLock::new();
+
+pub struct Pushgateway {
+    client: reqwest::Client,
+    url: String,
+    run_id: String,
+}
+
+/// Push to the Pushgateway at `url`, e.g. `http://127.0.0.1:9091`, from now
+/// on. The run id is the name of the run, see [`crate::output`].
+pub fn init(url: &str) {
+    let run_id = output::run_name().to_owned();
+    info!("pushing to {} as run {}", url, run_id);
+    println!("pushgateway run id: {}", run_id);
+    let _ = PUSHGATEWAY.set(Pushgateway {
+        client: reqwest::Client::new(),
+        url: url.trim_end_matches('/').to_owned(),
+        run_id,
+    });
+}
+
+/// The Pushgateway set with [`init`], if any.
+pub fn get() -> Option<&'static Pushgateway> {
+    PUSHGATEWAY.get()
+}
+
+impl Pushgateway {
+    /// Push the stats of `live` every [`PUSH_INTERVAL`] until cancelled.
+    pub async fn push_loop(&self, mode: &str, operation: &str, live: &LiveStats) {
+        let mut last = live.snapshot();
+        let mut last_at = Instant::now();
+        loop {
+            tokio::time::sleep(PUSH_INTERVAL).await;
+            let now = live.snapshot();
+            let secs = last_at.elapsed().as_secs_f64();
+            last_at = Instant::now();
+            let succeeded = now.succeeded - last.succeeded;
+            let mut body = String::new();
+            gauge(&mut body, "dmlddl_throughput", "", succeeded as f64 / secs);
+            if succeeded > 0 {
+                let mean = (now.latency_nanos - last.latency_nanos) as f64 / succeeded as f64;
+                gauge(&mut body, "dmlddl_latency_mean_seconds", "", mean / 1e9);
+            }
+            let errors = (now.errors - last.errors) as f64 / secs;
+            gauge(&mut body, "dmlddl_errors_per_second", "", errors);
+            last = now;
+            self.push(mode, operation, body).await;
+        }
+    }
+
+    /// Push the stats of a whole phase, replacing those of the intervals.
+    pub async fn push_final(&self, mode: &str, operation: &str, stats: &RunStats) {
+        let mut body = String::new();
+        gauge(&mut body, "dmlddl_throughput", "", stats.throughput());
+        for (quantile, p) in [("0.5", 50.0), ("0.99", 99.0)] {
+            if let Some(latency) = stats.percentile(p) {
+                let labels = format!("quantile=\"{}\"", quantile);
+                gauge(
+                    &mut body,
+                    "dmlddl_latency_seconds",
+                    &labels,
+                    latency.as_secs_f64(),
+                );
+            }
+        }
+        let secs = stats.elapsed.as_secs_f64();
+        let errors = stats.errors.total() as f64 / secs;
+        gauge(&mut body, "dmlddl_errors_per_second", "", errors);
+        for (class, count) in stats.errors.iter() {
+            let labels = format!("class=\"{}\"", class);
+            let rate = count as f64 / secs;
+            gauge(&mut body, "dmlddl_errors_per_second", &labels, rate);
+        }
+        gauge(&mut body, "dmlddl_reconnects", "", stats.reconnects as f64);
+        self.push(mode, operation, body).await;
+    }
+
+    /// Replace the group of `mode` and `operation` with `body`. Failures only
+    /// log a warning, so the Pushgateway never stops a run.
+    async fn push(&self, mode: &str, operation: &str, body: String) {
+        let url = format!(
+            "{}/metrics/job/dmlddl{}{}{}",
+            self.url,
+            segment("run_id", &self.run_id),
+            segment("mode", mode),
+            segment("operation", operation)
+        );
+        if let Err(e) = self.try_push(&url, body).await {
+            warn!("failed to push to {}: {:?}", url, e);
+        }
+    }
+
+    async fn try_push(&self, url: &str, body: String) -> Result<()> {
+        self.client
+            .put(url)
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// The path segment of a grouping label, `/<name>@base64/<value>`, where an
+/// empty value is `=` as the Pushgateway expects.
+fn segment(name: &str, value: &str) -> String {
+    let encoded = match base64::encode_config(value, base64::URL_SAFE) {
+        e if e.is_empty() => "=".to_owned(),
+        e => e,
+    };
+    format!("/{}@base64/{}", name, encoded)
+}
+
+/// Append a sample of gauge `name` in the text exposition format.
+fn gauge(body: &mut String, name: &str, labels: &str, value: f64) {
+    if !body.contains(&format!("# TYPE {} ", name)) {
+        let _ = writeln!(body, "# TYPE {} gauge", name);
+    }
+    let _ = writeln!(body, "{}{{{}}} {}", name, labels, value);
+}
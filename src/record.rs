@@ -0,0 +1,128 @@
+//! Recording the statements workers execute, to replay the interleaving that
+//! led to a failure with the `replay` subcommand.
+//!
+//! A recording has a line per statement:
+//! `<microseconds since the start>\t<connection>\t<sql>`, where
+//! `<connection>` is the worker's session alias and TiDB connection id, e.g.
+//! `dmlddl-dml-0#2199023255559`. Backslashes, tabs and newlines in the SQL
+//! are escaped.
+use crate::error::MyError;
+use crate::Result;
+use log::warn;
+use sqlx::mysql::MySqlConnection;
+use sqlx::query_scalar;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The file all connections of a run record to.
+pub struct Recorder {
+    start: Instant,
+    file: Mutex<BufWriter<File>>,
+}
+
+/// Records the statements of one connection.
+#[derive(Clone)]
+pub struct ConnRecorder {
+    recorder: Arc<Recorder>,
+    connection: String,
+}
+
+/// A statement read from a recording.
+pub struct Recorded {
+    pub at: Duration,
+    pub connection: String,
+    pub sql: String,
+}
+
+impl Recorder {
+    pub fn create(path: &str) -> Result<Arc<Recorder>> {
+        Ok(Arc::new(Recorder {
+            start: Instant::now(),
+            file: Mutex::new(BufWriter::new(File::create(path)?)),
+        }))
+    }
+
+    /// A recorder for `conn`, labelled `alias` and its connection id.
+    pub async fn connection(
+        self: &Arc<Self>,
+        conn: &mut MySqlConnection,
+        alias: &str,
+    ) -> Result<ConnRecorder> {
+        let id: u64 = query_scalar("select connection_id()")
+            .fetch_one(&mut *conn)
+            .await?;
+        Ok(ConnRecorder {
+            recorder: self.clone(),
+            connection: format!("{}#{}", alias, id),
+        })
+    }
+
+    /// Write out what is buffered, e.g. before checking the table after a
+    /// run.
+    pub fn flush(&self) -> Result<()> {
+        self.file.lock().unwrap().flush()?;
+        Ok(())
+    }
+}
+
+impl ConnRecorder {
+    /// Record `sql` as issued now. Failing to write only logs a warning, so
+    /// the recording never stops a run.
+    pub fn record(&self, sql: &str) {
+        let at = self.recorder.start.elapsed().as_micros();
+        let mut file = self.recorder.file.lock().unwrap();
+        if let Err(e) = writeln!(file, "{}\t{}\t{}", at, self.connection, escape(sql)) {
+            warn!("failed to record `{}`: {:?}", sql, e);
+        }
+    }
+}
+
+/// Read the recording in `path`, in the order the statements were issued.
+pub fn read(path: &str) -> Result<Vec<Recorded>> {
+    let mut statements = Vec::new();
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        let invalid = || MyError::StringError(format!("{}:{}: invalid line", path, i + 1));
+        let mut fields = line.splitn(3, '\t');
+        let at: u64 = fields
+            .next()
+            .and_then(|at| at.parse().ok())
+            .ok_or_else(invalid)?;
+        let connection = fields.next().ok_or_else(invalid)?.to_owned();
+        let sql = unescape(fields.next().ok_or_else(invalid)?);
+        statements.push(Recorded {
+            at: Duration::from_micros(at),
+            connection,
+            sql,
+        });
+    }
+    // lines of different connections may be written slightly out of order
+    statements.sort_by_key(|s| s.at);
+    Ok(statements)
+}
+
+fn escape(sql: &str) -> String {
+    sql.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
@@ -0,0 +1,87 @@
+//! Snapshots of the regions of the tables of the current database and the
+//! stores of their leaders, taken at the start, the middle and the end of
+//! each scenario phase with `--region-snapshots`, to see splits and leader
+//! balancing during the measurement.
+use crate::Result;
+use log::{info, warn};
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use sqlx::{Executor, Row};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const REGIONS: &str = "select count(distinct region_id) \
+    from information_schema.tikv_region_status where db_name = database()";
+const LEADERS: &str = "select p.store_id, count(distinct p.region_id) \
+    from information_schema.tikv_region_status s \
+    join information_schema.tikv_region_peers p on s.region_id = p.region_id \
+    where s.db_name = database() and p.is_leader = 1 \
+    group by p.store_id";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Take snapshots during the phases run from now on.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone)]
+pub struct RegionSnapshot {
+    /// When in the phase, e.g. `start`.
+    pub at: &'static str,
+    pub regions: u64,
+    /// Leaders by store id.
+    pub leaders: BTreeMap<u64, u64>,
+}
+
+/// Take a snapshot, labelled `at`. Failures only log a warning, as the
+/// tables may be dropped and recreated by a workload meanwhile.
+pub async fn snapshot(pool: &MySqlPool, at: &'static str) -> Option<RegionSnapshot> {
+    let res = async {
+        let mut conn = pool.acquire().await?;
+        take(&mut conn, at).await
+    }
+    .await;
+    match res {
+        Ok(snapshot) => {
+            info!("regions: {}", snapshot);
+            Some(snapshot)
+        }
+        Err(e) => {
+            warn!("failed to take a region snapshot at {}: {:?}", at, e);
+            None
+        }
+    }
+}
+
+async fn take(conn: &mut MySqlConnection, at: &'static str) -> Result<RegionSnapshot> {
+    let count = |row: &sqlx::mysql::MySqlRow, i| -> Result<u64> {
+        let v: Option<String> = row.try_get_unchecked(i)?;
+        Ok(v.and_then(|v| v.parse().ok()).unwrap_or(0))
+    };
+    let regions = count(&conn.fetch_one(REGIONS).await?, 0)?;
+    let mut leaders = BTreeMap::new();
+    for row in conn.fetch_all(LEADERS).await? {
+        leaders.insert(count(&row, 0)?, count(&row, 1)?);
+    }
+    Ok(RegionSnapshot {
+        at,
+        regions,
+        leaders,
+    })
+}
+
+/// E.g. `start: 12 regions, leaders by store 1: 4, 4: 4, 5: 4`.
+impl fmt::Display for RegionSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} regions, leaders by store", self.at, self.regions)?;
+        for (i, (store, leaders)) in self.leaders.iter().enumerate() {
+            write!(f, "{} {}: {}", if i > 0 { "," } else { "" }, store, leaders)?;
+        }
+        Ok(())
+    }
+}
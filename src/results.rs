@@ -0,0 +1,265 @@
+//! Results of scenario phases as CSV, written with `--results` and compared
+//! with the `compare` subcommand, e.g. to tell two TiDB versions apart.
+//!
+//! A file starts with the [`Metadata`] of the run in `# key: value` lines,
+//! then has a header and a line per workload of each phase:
+//! `phase,workload,ok,throughput,p50_ms,p99_ms,p999_ms,min_ms,max_ms,stddev_ms,errors,per_second`.
+//! Latencies are empty if nothing succeeded; `per_second` has the
+//! successes of each whole second of the measurement window separated by
+//! spaces, for the significance test of `compare`. A phase or workload name
+//! with a `,` or `"` is quoted, with its `"` doubled.
+//!
+//! With `--slow-queries`, the slowest plans of each phase go next to it, e.g.
+//! to `results.slow.csv` for `results.csv`.
+use crate::error::MyError;
+use crate::metadata::Metadata;
+use crate::scenario::PhaseReport;
+use crate::Result;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+
+const HEADER: &str =
+    "phase,workload,ok,throughput,p50_ms,p99_ms,p999_ms,min_ms,max_ms,stddev_ms,errors,per_second";
+
+static PATH: OnceLock<String> = OnceLock::new();
+
+/// Write the results of every scenario run from now on to `path`.
+pub fn write_to(path: &str) {
+    let _ = PATH.set(path.to_owned());
+}
+
+/// The file set with [`write_to`], if any.
+pub fn path() -> Option<&'static str> {
+    PATH.get().map(String::as_str)
+}
+
+/// The stats of one workload in one phase.
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    pub phase: String,
+    pub workload: String,
+    pub succeeded: u64,
+    pub throughput: f64,
+    pub p50_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub p999_ms: Option<f64>,
+    pub min_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub stddev_ms: Option<f64>,
+    pub errors: u64,
+    pub per_second: Vec<u64>,
+}
+
+impl Measurement {
+    /// The phase and the workload, which identify a measurement in a file.
+    pub fn key(&self) -> String {
+        format!("{}/{}", self.phase, self.workload)
+    }
+}
+
+pub fn measurements(reports: &[PhaseReport]) -> Vec<Measurement> {
+    let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+    reports
+        .iter()
+        .flat_map(|report| {
+            report.workloads.iter().map(|(name, stats)| Measurement {
+                phase: report.label(),
+                workload: name.clone(),
+                succeeded: stats.succeeded,
+                throughput: stats.throughput(),
+                p50_ms: stats.percentile(50.0).map(ms),
+                p99_ms: stats.percentile(99.0).map(ms),
+                p999_ms: stats.percentile(99.9).map(ms),
+                min_ms: stats.latencies.min().map(ms),
+                max_ms: stats.latencies.max().map(ms),
+                stddev_ms: stats.latencies.stddev().map(ms),
+                errors: stats.errors.total(),
+                per_second: stats.per_second.samples(&stats.measure).to_vec(),
+            })
+        })
+        .collect()
+}
+
+pub fn write(path: &str, metadata: &Metadata, measurements: &[Measurement]) -> Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    metadata.write_header(&mut file)?;
+    writeln!(file, "{}", HEADER)?;
+    let opt = |v: Option<f64>| v.map_or(String::new(), |v| format!("{:.3}", v));
+    for m in measurements {
+        writeln!(
+            file,
+            "{},{},{},{:.3},{},{},{},{},{},{},{},{}",
+            field(&m.phase),
+            field(&m.workload),
+            m.succeeded,
+            m.throughput,
+            opt(m.p50_ms),
+            opt(m.p99_ms),
+            opt(m.p999_ms),
+            opt(m.min_ms),
+            opt(m.max_ms),
+            opt(m.stddev_ms),
+            m.errors,
+            m.per_second
+                .iter()
+                .map(u64::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        )?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Write the slow plans of `reports` next to the results in `path`.
+pub fn write_slow_queries(path: &str, metadata: &Metadata, reports: &[PhaseReport]) -> Result<()> {
+    let path = Path::new(path).with_extension("slow.csv");
+    let mut file = BufWriter::new(File::create(&path)?);
+    metadata.write_header(&mut file)?;
+    writeln!(file, "phase,plan_digest,count,total_s,max_s,query")?;
+    for report in reports {
+        for q in &report.slow_queries {
+            writeln!(
+                file,
+                "{},{},{},{:.3},{:.3},\"{}\"",
+                field(&report.label()),
+                q.plan_digest,
+                q.count,
+                q.total_secs,
+                q.max_secs,
+                q.query.replace('"', "\"\"")
+            )?;
+        }
+    }
+    file.flush()?;
+    Ok(())
+}
+
+pub fn read(path: &str) -> Result<Vec<Measurement>> {
+    let mut measurements = Vec::new();
+    let mut header = false;
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.starts_with('#') {
+            continue;
+        }
+        if !header {
+            if line != HEADER {
+                return Err(MyError::StringError(format!(
+                    "{}: expected header {}",
+                    path, HEADER
+                )));
+            }
+            header = true;
+            continue;
+        }
+        let invalid = || MyError::StringError(format!("{}:{}: invalid line", path, i + 1));
+        let fields = split(&line).ok_or_else(invalid)?;
+        if fields.len() != 12 {
+            return Err(invalid());
+        }
+        let opt = |s: &str| -> Result<Option<f64>> {
+            if s.is_empty() {
+                Ok(None)
+            } else {
+                s.parse().map(Some).map_err(|_| invalid())
+            }
+        };
+        measurements.push(Measurement {
+            phase: fields[0].clone(),
+            workload: fields[1].clone(),
+            succeeded: fields[2].parse().map_err(|_| invalid())?,
+            throughput: fields[3].parse().map_err(|_| invalid())?,
+            p50_ms: opt(&fields[4])?,
+            p99_ms: opt(&fields[5])?,
+            p999_ms: opt(&fields[6])?,
+            min_ms: opt(&fields[7])?,
+            max_ms: opt(&fields[8])?,
+            stddev_ms: opt(&fields[9])?,
+            errors: fields[10].parse().map_err(|_| invalid())?,
+            per_second: fields[11]
+                .split_whitespace()
+                .map(|n| n.parse().map_err(|_| invalid()))
+                .collect::<Result<_>>()?,
+        });
+    }
+    Ok(measurements)
+}
+
+/// `s` as a CSV field, quoted if it has a `,` or `"`.
+fn field(s: &str) -> String {
+    if s.contains([',', '"']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+/// The fields of a CSV line written with [`field`]; `None` if a quote isn't
+/// closed.
+fn split(line: &str) -> Option<Vec<String>> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().unwrap();
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    (!quoted).then_some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        let names = [
+            ("load", "insert"),
+            ("load, then scan", "point read"),
+            ("\"quoted\" phase", "a,\"b\",c"),
+            ("", "\""),
+        ];
+        let written: Vec<Measurement> = names
+            .iter()
+            .map(|(phase, workload)| Measurement {
+                phase: phase.to_string(),
+                workload: workload.to_string(),
+                succeeded: 10,
+                throughput: 1.5,
+                p50_ms: Some(1.0),
+                p99_ms: None,
+                p999_ms: None,
+                min_ms: None,
+                max_ms: None,
+                stddev_ms: None,
+                errors: 2,
+                per_second: vec![3, 4],
+            })
+            .collect();
+        let path = std::env::temp_dir().join(format!("results-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        write(path, &Metadata::default(), &written).unwrap();
+        let read = read(path);
+        std::fs::remove_file(path).unwrap();
+        let read = read.unwrap();
+        assert_eq!(read.len(), names.len());
+        for (m, (phase, workload)) in read.iter().zip(names) {
+            assert_eq!((m.phase.as_str(), m.workload.as_str()), (phase, workload));
+            assert_eq!((m.succeeded, m.errors), (10, 2));
+            assert_eq!(m.p50_ms, Some(1.0));
+            assert_eq!(m.per_second, [3, 4]);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! Retry with exponential backoff for retryable error classes.
+use crate::error::ErrorClass;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// How long to wait before retry number `attempt` (starting from 1) after
+    /// an error of `class`, or `None` if it should not be retried.
+    pub fn backoff(&self, attempt: u32, class: ErrorClass) -> Option<Duration> {
+        if attempt > self.max_retries || !class.is_retryable() {
+            return None;
+        }
+        Some(self.base_backoff * 2u32.saturating_pow(attempt - 1))
+    }
+}
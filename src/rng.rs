@@ -0,0 +1,18 @@
+//! Per-worker RNGs derived from a single run seed, so a run can be repeated
+//! with the same key choices.
+use rand::SeedableRng;
+
+/// RNG of worker `worker` in a run seeded with `seed`.
+pub fn worker_rng<R: SeedableRng>(seed: u64, worker: usize) -> R {
+    R::seed_from_u64(seed ^ (worker as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
+
+/// Seed of the `stream`-th independent part of a run seeded with `seed`, e.g.
+/// one workload of a scenario phase. Mixed with splitmix64 so the workers
+/// seeded from it don't overlap with those seeded from `seed`.
+pub fn derive_seed(seed: u64, stream: usize) -> u64 {
+    let mut z = seed ^ (stream as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
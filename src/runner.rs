@@ -0,0 +1,421 @@
+//! Drives a [`Workload`] with a set of concurrent workers.
+use crate::connection::ConnectionMode;
+use crate::control::{pause_switch, wait_resumed};
+use crate::error::{ErrorClass, MyError};
+use crate::events;
+use crate::invariant::{check_loop, InvariantOpts};
+use crate::metrics::{LiveStats, RunStats, StatementLatencies};
+use crate::otlp;
+use crate::retry::RetryPolicy;
+use crate::rng::worker_rng;
+use crate::session::set_session_alias;
+use crate::workload::{Effects, Worker, Workload};
+use crate::Result;
+use futures::future::join_all;
+use log::{error, info, warn};
+use sqlx::mysql::{MySql, MySqlPool};
+use sqlx::pool::PoolConnection;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::time::MissedTickBehavior;
+
+// after this long, a worker fails if a statement it recorded in
+// `Worker::effects` never touched a row
+const NO_OP_CHECK_AFTER: Duration = Duration::from_secs(10);
+const NO_OP_MIN_EXECUTIONS: u64 = 20;
+// between attempts to replace a broken connection, doubled up to the max
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(100);
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// The iterations that count in the totals, throughput and percentiles of a
+/// run: those that finish between `start` and `end` after the workers
+/// started, e.g. to leave out the warm-up. The series over time still cover
+/// the whole run.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MeasureWindow {
+    start: Duration,
+    /// The end of the run if `None`.
+    end: Option<Duration>,
+}
+
+impl MeasureWindow {
+    pub fn new(start: Duration, end: Option<Duration>) -> Result<MeasureWindow> {
+        if end.is_some_and(|end| end <= start) {
+            return Err(MyError::StringError(
+                "the measurement window ends before it starts".to_string(),
+            ));
+        }
+        Ok(MeasureWindow { start, end })
+    }
+
+    fn contains(&self, at: Duration) -> bool {
+        at >= self.start && self.end.is_none_or(|end| at < end)
+    }
+
+    /// The whole seconds since the start of a run, of the first `seconds`,
+    /// that lie in the window.
+    pub fn whole_seconds(&self, seconds: usize) -> Range<usize> {
+        let start = self.start.as_secs() as usize + (self.start.subsec_nanos() > 0) as usize;
+        let end = self
+            .end
+            .map_or(seconds, |end| (end.as_secs() as usize).min(seconds));
+        start.min(end)..end
+    }
+
+    /// The part of a run of length `elapsed` in the window.
+    fn length(&self, elapsed: Duration) -> Duration {
+        self.end
+            .map_or(elapsed, |end| elapsed.min(end))
+            .saturating_sub(self.start)
+    }
+}
+
+pub struct RunOptions {
+    pub concurrency: usize,
+    /// Iterations per worker, unlimited if `None`.
+    pub iterations: Option<u64>,
+    /// No iteration is started after this long, unlimited if `None`.
+    pub duration: Option<Duration>,
+    /// Iterations per second over all workers, as fast as possible if `None`.
+    /// Must be positive.
+    pub rate: Option<f64>,
+    pub seed: u64,
+    pub retry: RetryPolicy,
+    /// Stop all workers at the first error that is not retried and return it.
+    pub fail_fast: bool,
+    /// Workers stop after their current iteration when this is signaled.
+    /// Ctrl-C stops them too, and so does a run that fails, so runs sharing
+    /// it stop together.
+    pub stop: Option<broadcast::Sender<()>>,
+    /// Checked while the workers run. A violation stops them if
+    /// `invariants.abort` is set, a failed ADMIN CHECK always does.
+    pub invariants: InvariantOpts,
+    /// Updated as iterations finish, for reporting while the workers run.
+    pub live: Option<Arc<LiveStats>>,
+    pub measure: MeasureWindow,
+    pub connection_mode: ConnectionMode,
+}
+
+/// Set up `workload`, run it with [`run_workers`], and tear it down, even if
+/// the workers failed. The first error is returned.
+pub async fn run(
+    pool: &MySqlPool,
+    workload: Arc<dyn Workload>,
+    opts: &RunOptions,
+) -> Result<RunStats> {
+    workload.setup(&mut *pool.acquire().await?).await?;
+    let res = run_workers(pool, &workload, opts).await;
+    let teardown = match pool.acquire().await {
+        Ok(mut conn) => workload.teardown(&mut conn).await,
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = &teardown {
+        if res.is_err() {
+            warn!("{} teardown failed: {:?}", workload.name(), e);
+        }
+    }
+    let stats = res?;
+    teardown?;
+    Ok(stats)
+}
+
+/// A connection for `workload`'s worker `id` from `pool`, backing off
+/// between failed attempts. Dedicated connections get the worker's session
+/// alias; pooled ones are shared by the workers. `None` if the workers are
+/// stopped meanwhile.
+async fn acquire(
+    pool: &MySqlPool,
+    mode: ConnectionMode,
+    workload: &str,
+    id: usize,
+    stop_rx: &mut broadcast::Receiver<()>,
+) -> Option<PoolConnection<MySql>> {
+    let mut backoff = RECONNECT_BACKOFF;
+    loop {
+        let res = async {
+            let mut conn = pool.acquire().await?;
+            if mode == ConnectionMode::Dedicated {
+                set_session_alias(&mut conn, workload, id).await?;
+            }
+            Result::Ok(conn)
+        };
+        match res.await {
+            Ok(conn) => return Some(conn),
+            Err(e) => warn!("{} worker {} failed to connect: {:?}", workload, id, e),
+        }
+        select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = stop_rx.recv() => return None,
+        }
+        backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
+    }
+}
+
+/// Replace `dead`, a connection of `workload`'s worker `id` that broke, e.g.
+/// because its server restarted, with a new one from `pool`, see
+/// [`acquire`].
+async fn reconnect(
+    pool: &MySqlPool,
+    mode: ConnectionMode,
+    dead: PoolConnection<MySql>,
+    workload: &str,
+    id: usize,
+    stop_rx: &mut broadcast::Receiver<()>,
+) -> Option<PoolConnection<MySql>> {
+    // closed rather than returned to the pool
+    drop(dead.detach());
+    let conn = acquire(pool, mode, workload, id, stop_rx).await?;
+    info!("{} worker {} reconnected", workload, id);
+    Some(conn)
+}
+
+/// Run `workload` on `opts.concurrency` workers until they are done.
+pub async fn run_workers(
+    pool: &MySqlPool,
+    workload: &Arc<dyn Workload>,
+    opts: &RunOptions,
+) -> Result<RunStats> {
+    // also rejects NaN and infinity, which the pacer can't use
+    if let Some(rate) = opts.rate.filter(|rate| !rate.is_finite() || *rate <= 0.0) {
+        return Err(MyError::StringError(format!(
+            "the rate of {} must be positive, got {}",
+            workload.name(),
+            rate
+        )));
+    }
+    let start = Instant::now();
+    let deadline = opts.duration.map(|d| start + d);
+    let stop = opts.stop.clone().unwrap_or_else(|| broadcast::channel(1).0);
+    let interrupt = {
+        let stop = stop.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                info!("interrupted");
+                let _ = stop.send(());
+            }
+        })
+    };
+    let violations = Arc::new(AtomicU64::new(0));
+    let checker = opts.invariants.enabled().then(|| {
+        let pool = pool.clone();
+        let invariants = opts.invariants.clone();
+        let violations = violations.clone();
+        let stop = stop.clone();
+        tokio::spawn(async move {
+            let msg = check_loop(&pool, &invariants, violations).await;
+            let _ = stop.send(());
+            msg
+        })
+    });
+
+    let mut handles = Vec::new();
+    let mut first_error = None;
+    for id in 0..opts.concurrency {
+        let acquire_begin = Instant::now();
+        let conn = pool.acquire().await;
+        let acquired = acquire_begin.elapsed();
+        let conn = match conn {
+            Ok(mut conn) if opts.connection_mode == ConnectionMode::Dedicated => {
+                set_session_alias(&mut conn, workload.name(), id)
+                    .await
+                    .map(|_| conn)
+            }
+            res => res.map_err(MyError::from),
+        };
+        let mut conn = match conn {
+            Ok(conn) => conn,
+            Err(e) => {
+                // the workers started so far, and those of other workloads
+                // sharing `stop`, must not outlive the run
+                let _ = stop.send(());
+                first_error = Some(e);
+                break;
+            }
+        };
+        let workload = workload.clone();
+        let worker_name = workload.name().to_owned();
+        let mut worker = Worker {
+            id,
+            rng: worker_rng(opts.seed, id),
+            effects: Effects::default(),
+            statements: StatementLatencies::default(),
+        };
+        let iterations = opts.iterations.unwrap_or(u64::MAX);
+        let retry = opts.retry;
+        let fail_fast = opts.fail_fast;
+        let measure = opts.measure;
+        let mode = opts.connection_mode;
+        let mut pacer = opts.rate.map(|rate| {
+            let mut pacer =
+                tokio::time::interval(Duration::from_secs_f64(opts.concurrency as f64 / rate));
+            pacer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            pacer
+        });
+        let live = opts.live.clone();
+        let pool = pool.clone();
+        let stop = stop.clone();
+        let mut stop_rx = stop.subscribe();
+        let mut paused = pause_switch();
+        handles.push(tokio::spawn(async move {
+            let mut stats = RunStats::default();
+            stats.acquire.record(acquired);
+            let mut no_ops_checked = false;
+            'iterations: for _ in 0..iterations {
+                if !no_ops_checked && start.elapsed() >= NO_OP_CHECK_AFTER {
+                    no_ops_checked = true;
+                    let no_ops = worker.effects.no_ops(NO_OP_MIN_EXECUTIONS);
+                    if !no_ops.is_empty() {
+                        let msg = format!(
+                            "{} worker {}: these statements affected or returned no rows in the \
+                            first {:?}, check the workload: {}",
+                            worker_name,
+                            id,
+                            NO_OP_CHECK_AFTER,
+                            no_ops.join("; ")
+                        );
+                        error!("{}", msg);
+                        let _ = stop.send(());
+                        return Err(MyError::StringError(msg));
+                    }
+                }
+                if *paused.borrow() {
+                    select! {
+                        _ = wait_resumed(&mut paused) => {}
+                        _ = stop_rx.recv() => break,
+                    }
+                }
+                if deadline.is_some_and(|d| Instant::now() >= d) {
+                    break;
+                }
+                if let Some(pacer) = &mut pacer {
+                    select! {
+                        _ = pacer.tick() => {}
+                        _ = stop_rx.recv() => break,
+                    }
+                }
+                if !matches!(
+                    stop_rx.try_recv(),
+                    Err(broadcast::error::TryRecvError::Empty)
+                ) {
+                    break;
+                }
+                if mode == ConnectionMode::Pooled {
+                    drop(conn);
+                    let acquire_begin = Instant::now();
+                    let Some(new) = acquire(&pool, mode, &worker_name, id, &mut stop_rx).await
+                    else {
+                        break 'iterations;
+                    };
+                    conn = new;
+                    stats.acquire.record(acquire_begin.elapsed());
+                }
+                let begin = Instant::now();
+                let mut attempt = 0;
+                loop {
+                    let attempt_begin = Instant::now();
+                    let attempt_start = SystemTime::now();
+                    let res = workload.run_iteration(&mut conn, &mut worker).await;
+                    if events::enabled() || otlp::enabled() {
+                        let class = res.as_ref().err().map(MyError::class);
+                        let latency = attempt_begin.elapsed();
+                        events::iteration(&worker_name, id, attempt + 1, latency, class);
+                        otlp::iteration(
+                            &worker_name,
+                            id,
+                            attempt + 1,
+                            attempt_start,
+                            latency,
+                            class,
+                            worker.statements.take_timed(),
+                        );
+                    }
+                    match res {
+                        Ok(()) => {
+                            if measure.contains(start.elapsed()) {
+                                stats.succeeded += 1;
+                                stats.latencies.record(begin.elapsed());
+                            }
+                            stats
+                                .latency_series
+                                .record(start.elapsed(), begin.elapsed());
+                            stats.per_second.record(start.elapsed());
+                            if let Some(live) = &live {
+                                live.succeeded(begin.elapsed());
+                            }
+                            break;
+                        }
+                        Err(e) => {
+                            let class = e.class();
+                            if class == ErrorClass::Connection {
+                                let Some(new) =
+                                    reconnect(&pool, mode, conn, &worker_name, id, &mut stop_rx)
+                                        .await
+                                else {
+                                    break 'iterations;
+                                };
+                                conn = new;
+                                stats.reconnects += 1;
+                            }
+                            if measure.contains(start.elapsed()) {
+                                stats.errors.add(class);
+                            }
+                            stats.error_series.record(start.elapsed(), class);
+                            if let Some(live) = &live {
+                                live.failed();
+                            }
+                            attempt += 1;
+                            match retry.backoff(attempt, class) {
+                                Some(backoff) => tokio::time::sleep(backoff).await,
+                                None if fail_fast => {
+                                    error!("worker {} failed: {:?}", id, e);
+                                    let _ = stop.send(());
+                                    return Err(e);
+                                }
+                                None => break,
+                            }
+                        }
+                    }
+                }
+            }
+            stats.statements = worker.statements;
+            Ok(stats)
+        }));
+    }
+
+    let mut stats = RunStats::default();
+    for res in join_all(handles).await {
+        match res.expect("worker panicked") {
+            Ok(worker_stats) => stats.merge(worker_stats),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    interrupt.abort();
+    if let Some(checker) = checker {
+        checker.abort();
+        // finished by itself only if it stopped the workers
+        if let Ok(msg) = checker.await {
+            return Err(MyError::StringError(msg));
+        }
+    }
+    if let Some(e) = first_error {
+        return Err(e);
+    }
+    stats.violations = violations.load(Ordering::SeqCst);
+    stats.elapsed = opts.measure.length(start.elapsed());
+    stats.measure = opts.measure;
+    if stats.elapsed.is_zero() {
+        warn!("{} ended before its measurement window", workload.name());
+    }
+    events::flush().await;
+    otlp::flush().await;
+    stats.latencies.sort();
+    stats.acquire.sort();
+    stats.statements.sort();
+    Ok(stats)
+}
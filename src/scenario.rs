@@ -0,0 +1,607 @@
+//! Phased scenarios.
+//!
+//! A scenario is an ordered list of phases. Each phase runs a mix of workloads
+//! side by side, each with its own number of workers, for a fixed time, with
+//! optional SQL run before and after it, e.g. to inject a fault or change a
+//! variable:
+//!
+//! ```ignore
+//! let scenario = Scenario::new()
+//!     .phase(Phase::new("baseline", Duration::from_secs(60)).with(read.clone(), 5))
+//!     .phase(
+//!         Phase::new("contention", Duration::from_secs(600))
+//!             .with(write, 15)
+//!             .with(read, 5)
+//!             .before("set @@global.tidb_enable_async_commit = 0"),
+//!     );
+//! ```
+//!
+//! Every workload is set up before the first phase that uses it, or before the
+//! first phase at all if passed to [`Scenario::setup`], and torn down after the
+//! last phase, even if a phase failed.
+//!
+//! SQL given to [`Scenario::setup_sql`] runs once before anything else, e.g. to
+//! set placement rules or split tables, and SQL given to
+//! [`Scenario::teardown_sql`] once at the end, even if a phase failed.
+//!
+//! A [`Placement`] given to [`Scenario::placement`] is applied right before
+//! the first phase, after setting up its workloads, and the phase only starts
+//! once PD has scheduled it.
+//!
+//! Scenarios can also be read from a file, see [`file`].
+use crate::assertions;
+use crate::charts;
+use crate::connection::ConnectionMode;
+use crate::invariant::InvariantOpts;
+use crate::metadata::Metadata;
+use crate::metrics::{LiveStats, RunStats, Spread};
+use crate::placement::Placement;
+use crate::pushgateway;
+use crate::regions::{self, RegionSnapshot};
+use crate::results;
+use crate::retry::RetryPolicy;
+use crate::rng::derive_seed;
+use crate::runner::{run_workers, MeasureWindow, RunOptions};
+use crate::server_metrics::{self, ServerMetrics};
+use crate::slow_query::{self, SlowQuery};
+use crate::tui;
+use crate::workload::Workload;
+use crate::Result;
+use futures::future::join_all;
+use log::{info, warn};
+use serde::Deserialize;
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use sqlx::Executor;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::select;
+use tokio::sync::broadcast;
+
+pub mod file;
+
+#[derive(Default)]
+pub struct Scenario {
+    setup: Vec<Arc<dyn Workload>>,
+    phases: Vec<Phase>,
+    invariants: InvariantOpts,
+    setup_sql: Vec<String>,
+    teardown_sql: Vec<String>,
+    on_sql_error: SqlErrorPolicy,
+    placement: Option<Placement>,
+    measure: MeasureWindow,
+    connection_mode: ConnectionMode,
+}
+
+/// What to do when a setup, teardown, before or after statement fails.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SqlErrorPolicy {
+    /// Fail the scenario.
+    #[default]
+    Abort,
+    /// Log the error and go on.
+    Continue,
+}
+
+pub struct Phase {
+    name: String,
+    duration: Duration,
+    // workload, concurrency, rate
+    mix: Vec<(Arc<dyn Workload>, usize, Option<f64>)>,
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+pub struct PhaseReport {
+    pub name: String,
+    pub elapsed: Duration,
+    /// Stats of each workload of the mix, in order.
+    pub workloads: Vec<(String, RunStats)>,
+    /// Averages of the server's metrics if `--tidb-status` is given.
+    pub server: Option<ServerMetrics>,
+    /// Those taken of start, middle and end if `--region-snapshots` is given.
+    pub regions: Vec<RegionSnapshot>,
+    /// The slowest plans if `--slow-queries` is given.
+    pub slow_queries: Vec<SlowQuery>,
+    /// Which run of the scenario this is, from 1, see [`repeat`].
+    pub repetition: usize,
+    /// With repetitions, in the report of the last one: throughput and p99
+    /// in ms of each workload over the reports of this phase of all of them.
+    pub spread: Vec<(String, Option<Spread>, Option<Spread>)>,
+}
+
+impl Scenario {
+    pub fn new() -> Scenario {
+        Scenario::default()
+    }
+
+    /// Set up `workload` before the first phase, whether or not it runs in
+    /// that phase, e.g. because other workloads read the data it creates.
+    pub fn setup(mut self, workload: Arc<dyn Workload>) -> Scenario {
+        self.setup.push(workload);
+        self
+    }
+
+    /// Check `invariants` during every phase.
+    pub fn invariants(mut self, invariants: InvariantOpts) -> Scenario {
+        self.invariants = invariants;
+        self
+    }
+
+    /// Execute `sql` once before setting up any workload.
+    pub fn setup_sql(mut self, sql: &str) -> Scenario {
+        self.setup_sql.push(sql.to_owned());
+        self
+    }
+
+    /// Execute `sql` once after tearing down the workloads.
+    pub fn teardown_sql(mut self, sql: &str) -> Scenario {
+        self.teardown_sql.push(sql.to_owned());
+        self
+    }
+
+    pub fn on_sql_error(mut self, policy: SqlErrorPolicy) -> Scenario {
+        self.on_sql_error = policy;
+        self
+    }
+
+    /// Count only the iterations in `window` in the stats of every workload.
+    pub fn measure(mut self, window: MeasureWindow) -> Scenario {
+        self.measure = window;
+        self
+    }
+
+    pub fn connection_mode(mut self, mode: ConnectionMode) -> Scenario {
+        self.connection_mode = mode;
+        self
+    }
+
+    pub fn placement(mut self, placement: Placement) -> Scenario {
+        self.placement = Some(placement);
+        self
+    }
+
+    pub fn phase(mut self, phase: Phase) -> Scenario {
+        self.phases.push(phase);
+        self
+    }
+}
+
+impl Phase {
+    pub fn new(name: &str, duration: Duration) -> Phase {
+        Phase {
+            name: name.to_owned(),
+            duration,
+            mix: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+
+    /// Run `workload` on `concurrency` workers during this phase.
+    pub fn with(mut self, workload: Arc<dyn Workload>, concurrency: usize) -> Phase {
+        self.mix.push((workload, concurrency, None));
+        self
+    }
+
+    /// Like [`Phase::with`], with the workers paced to `rate` iterations per
+    /// second over all of them.
+    pub fn with_rate(
+        mut self,
+        workload: Arc<dyn Workload>,
+        concurrency: usize,
+        rate: f64,
+    ) -> Phase {
+        self.mix.push((workload, concurrency, Some(rate)));
+        self
+    }
+
+    /// Execute `sql` before the workers of this phase start.
+    pub fn before(mut self, sql: &str) -> Phase {
+        self.before.push(sql.to_owned());
+        self
+    }
+
+    /// Execute `sql` after the workers of this phase stop.
+    pub fn after(mut self, sql: &str) -> Phase {
+        self.after.push(sql.to_owned());
+        self
+    }
+}
+
+static REPETITIONS: AtomicUsize = AtomicUsize::new(1);
+
+/// Run every scenario `n` times from now on, to tell changes from
+/// run-to-run variance.
+pub fn repeat(n: usize) {
+    REPETITIONS.store(n.max(1), Ordering::Relaxed);
+}
+
+/// How many times every scenario runs, see [`repeat`].
+pub fn repetitions() -> usize {
+    REPETITIONS.load(Ordering::Relaxed)
+}
+
+// in seconds, 0 for no interim reports
+static REPORT_INTERVAL: AtomicU64 = AtomicU64::new(0);
+
+/// Print interim stats of the running phase every `interval` from now on.
+pub fn report_every(interval: Duration) {
+    REPORT_INTERVAL.store(interval.as_secs(), Ordering::Relaxed);
+}
+
+fn report_interval() -> Option<Duration> {
+    match REPORT_INTERVAL.load(Ordering::Relaxed) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// Print a line per workload every `interval` with its throughput, p99 and
+/// errors since the previous line. Never returns.
+async fn report_interim(
+    phase: &str,
+    start: Instant,
+    interval: Duration,
+    workloads: &[(&str, Arc<LiveStats>)],
+) {
+    let mut last: Vec<_> = workloads.iter().map(|(_, l)| l.snapshot()).collect();
+    loop {
+        tokio::time::sleep(interval).await;
+        for ((name, live), last) in workloads.iter().zip(&mut last) {
+            let now = live.snapshot();
+            let ops = (now.succeeded - last.succeeded) as f64 / interval.as_secs_f64();
+            let p99 = now
+                .percentile_since(last, 99.0)
+                .map_or("-".to_string(), |p| format!("{:?}", p));
+            let line = format!(
+                "[{} {}s] {}: {:.1}/s, p99 {}, {} errors",
+                phase,
+                start.elapsed().as_secs(),
+                name,
+                ops,
+                p99,
+                now.errors - last.errors
+            );
+            info!("{}", line);
+            println!("{}", line);
+            *last = now;
+        }
+    }
+}
+
+/// Run the phases of `scenario` in order and report each of them. With
+/// [`repeat`], the whole scenario runs again from its setup each time, the
+/// first time with `seed`; the reports of the last repetition get the
+/// spread over all of them.
+pub async fn run(
+    pool: &MySqlPool,
+    scenario: &Scenario,
+    seed: u64,
+    retry: RetryPolicy,
+) -> Result<Vec<PhaseReport>> {
+    let repetitions = repetitions();
+    let mut conn = pool.acquire().await?;
+    let policy = scenario.on_sql_error;
+    let mut reports = Vec::new();
+    for r in 0..repetitions {
+        if repetitions > 1 {
+            info!("repetition {}/{}", r + 1, repetitions);
+        }
+        let seed = if r == 0 { seed } else { derive_seed(seed, r) };
+        execute_all(&mut conn, &scenario.setup_sql, policy).await?;
+        let res = run_phases(pool, &mut conn, scenario, seed, retry).await;
+        let teardown = execute_all(&mut conn, &scenario.teardown_sql, policy).await;
+        let mut run = res?;
+        teardown?;
+        for report in &mut run {
+            report.repetition = r + 1;
+        }
+        reports.extend(run);
+    }
+    if repetitions > 1 {
+        add_spread(&mut reports);
+    }
+    if let Some(path) = results::path() {
+        let metadata = Metadata::collect(&mut conn).await;
+        results::write(path, &metadata, &results::measurements(&reports))?;
+        if slow_query::top().is_some() {
+            results::write_slow_queries(path, &metadata, &reports)?;
+        }
+        info!("results written to {}", path);
+    }
+    if charts::enabled() {
+        charts::write(results::path(), &reports)?;
+    }
+    assertions::check(&reports);
+    Ok(reports)
+}
+
+/// Fill in the spread of the reports of the last repetition.
+fn add_spread(reports: &mut [PhaseReport]) {
+    let last = reports.iter().map(|r| r.repetition).max().unwrap_or(0);
+    for i in 0..reports.len() {
+        if reports[i].repetition != last {
+            continue;
+        }
+        let mut spread = Vec::new();
+        for (workload, _) in &reports[i].workloads {
+            let runs: Vec<&RunStats> = reports
+                .iter()
+                .filter(|r| r.name == reports[i].name)
+                .flat_map(|r| r.workloads.iter())
+                .filter(|(w, _)| w == workload)
+                .map(|(_, stats)| stats)
+                .collect();
+            let throughput: Vec<f64> = runs.iter().map(|s| s.throughput()).collect();
+            let p99: Vec<f64> = runs
+                .iter()
+                .filter_map(|s| s.percentile(99.0))
+                .map(|p99| p99.as_secs_f64() * 1000.0)
+                .collect();
+            spread.push((workload.clone(), Spread::of(&throughput), Spread::of(&p99)));
+        }
+        reports[i].spread = spread;
+    }
+}
+
+/// Run the phases of one repetition, and tear down the workloads set up for
+/// them even if one failed. The first error is returned.
+async fn run_phases(
+    pool: &MySqlPool,
+    conn: &mut MySqlConnection,
+    scenario: &Scenario,
+    seed: u64,
+    retry: RetryPolicy,
+) -> Result<Vec<PhaseReport>> {
+    let mut set_up = Vec::new();
+    let res = phases(pool, conn, scenario, seed, retry, &mut set_up).await;
+    let mut teardown = Ok(());
+    for workload in set_up.iter().rev() {
+        if let Err(e) = workload.teardown(conn).await {
+            warn!("{} teardown failed: {:?}", workload.name(), e);
+            teardown = teardown.and(Err(e));
+        }
+    }
+    let reports = res?;
+    teardown?;
+    Ok(reports)
+}
+
+/// Set up the workloads of each phase, adding them to `set_up`, and run the
+/// phases.
+async fn phases(
+    pool: &MySqlPool,
+    conn: &mut MySqlConnection,
+    scenario: &Scenario,
+    seed: u64,
+    retry: RetryPolicy,
+    set_up: &mut Vec<Arc<dyn Workload>>,
+) -> Result<Vec<PhaseReport>> {
+    let policy = scenario.on_sql_error;
+    for workload in &scenario.setup {
+        workload.setup(conn).await?;
+        set_up.push(workload.clone());
+    }
+    let mut reports = Vec::new();
+    for (i, phase) in scenario.phases.iter().enumerate() {
+        for (workload, _, _) in &phase.mix {
+            if !set_up.iter().any(|w| Arc::ptr_eq(w, workload)) {
+                workload.setup(conn).await?;
+                set_up.push(workload.clone());
+            }
+        }
+        if i == 0 {
+            if let Some(placement) = &scenario.placement {
+                placement.apply(conn).await?;
+            }
+        }
+        execute_all(conn, &phase.before, policy).await?;
+
+        info!("phase {} started", phase.name);
+        let window_start = match slow_query::top() {
+            Some(_) => Some(slow_query::now(conn).await?),
+            None => None,
+        };
+        let mut snapshots = Vec::new();
+        let mut mid_snapshot = None;
+        if regions::enabled() {
+            snapshots.extend(regions::snapshot(pool, "start").await);
+            let pool = pool.clone();
+            let half = phase.duration / 2;
+            mid_snapshot = Some(tokio::spawn(async move {
+                tokio::time::sleep(half).await;
+                regions::snapshot(&pool, "middle").await
+            }));
+        }
+        let start = Instant::now();
+        let collecting = server_metrics::get().map(|c| c.start());
+        let lives: Vec<_> = phase
+            .mix
+            .iter()
+            .map(|_| {
+                (pushgateway::get().is_some() || tui::enabled() || report_interval().is_some())
+                    .then(|| Arc::new(LiveStats::default()))
+            })
+            .collect();
+        // a failed workload or invariant stops the whole phase
+        let (stop, _) = broadcast::channel(1);
+        let runs = phase
+            .mix
+            .iter()
+            .enumerate()
+            .map(|(j, (workload, concurrency, rate))| {
+                let opts = RunOptions {
+                    concurrency: *concurrency,
+                    iterations: None,
+                    duration: Some(phase.duration),
+                    rate: *rate,
+                    seed: derive_seed(derive_seed(seed, i), j),
+                    retry,
+                    fail_fast: false,
+                    stop: Some(stop.clone()),
+                    // checked once per phase, not once per workload
+                    invariants: if j == 0 {
+                        scenario.invariants.clone()
+                    } else {
+                        InvariantOpts::default()
+                    },
+                    live: lives[j].clone(),
+                    measure: scenario.measure,
+                    connection_mode: scenario.connection_mode,
+                };
+                async move {
+                    let run = run_workers(pool, workload, &opts);
+                    let (Some(gateway), Some(live)) = (pushgateway::get(), &opts.live) else {
+                        return run.await;
+                    };
+                    let stats = select! {
+                        stats = run => stats?,
+                        _ = gateway.push_loop(&phase.name, workload.name(), live) => unreachable!(),
+                    };
+                    gateway
+                        .push_final(&phase.name, workload.name(), &stats)
+                        .await;
+                    Ok(stats)
+                }
+            });
+        let workloads: Vec<_> = phase
+            .mix
+            .iter()
+            .zip(&lives)
+            .filter_map(|((w, _, _), live)| Some((w.name(), live.clone()?)))
+            .collect();
+        // every run is awaited to the end, even after one failed, so that no
+        // worker outlives the phase into the next one or the teardown
+        let runs = async { join_all(runs).await.into_iter().collect::<Result<Vec<_>>>() };
+        let stats = match report_interval() {
+            // the dashboard already shows the interim stats
+            _ if tui::enabled() => select! {
+                stats = runs => stats?,
+                _ = tui::show(&phase.name, start, phase.duration, &workloads) => unreachable!(),
+            },
+            Some(interval) => select! {
+                stats = runs => stats?,
+                _ = report_interim(&phase.name, start, interval, &workloads) => unreachable!(),
+            },
+            None => runs.await?,
+        };
+        if let Some(mid) = mid_snapshot {
+            // the phase ended early if it isn't taken yet
+            if mid.is_finished() {
+                snapshots.extend(mid.await.ok().flatten());
+            } else {
+                mid.abort();
+            }
+            snapshots.extend(regions::snapshot(pool, "end").await);
+        }
+        let mut slow_queries = Vec::new();
+        if let (Some(top), Some(from)) = (slow_query::top(), &window_start) {
+            let to = slow_query::now(conn).await?;
+            slow_queries = slow_query::slowest(conn, from, &to, top).await;
+        }
+        let report = PhaseReport {
+            name: phase.name.clone(),
+            elapsed: start.elapsed(),
+            workloads: phase
+                .mix
+                .iter()
+                .map(|(w, _, _)| w.name().to_owned())
+                .zip(stats)
+                .collect(),
+            regions: snapshots,
+            slow_queries,
+            repetition: 1,
+            spread: Vec::new(),
+            server: match collecting {
+                Some(collecting) => Some(collecting.finish().await),
+                None => None,
+            },
+        };
+        info!("{}", report);
+        reports.push(report);
+
+        execute_all(conn, &phase.after, policy).await?;
+    }
+    Ok(reports)
+}
+
+async fn execute_all(
+    conn: &mut MySqlConnection,
+    sqls: &[String],
+    policy: SqlErrorPolicy,
+) -> Result<()> {
+    for sql in sqls {
+        if let Err(e) = conn.execute(sql.as_str()).await {
+            match policy {
+                SqlErrorPolicy::Abort => return Err(e.into()),
+                SqlErrorPolicy::Continue => warn!("`{}` failed: {:?}", sql, e),
+            }
+        }
+    }
+    Ok(())
+}
+
+impl PhaseReport {
+    /// The phase name, with the repetition if there are several, e.g.
+    /// `load#2`.
+    pub fn label(&self) -> String {
+        if repetitions() > 1 {
+            format!("{}#{}", self.name, self.repetition)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+impl fmt::Display for PhaseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "phase {} ({:.1?}):", self.label(), self.elapsed)?;
+        for (name, stats) in &self.workloads {
+            write!(f, "\n  {}: {}", name, stats)?;
+            if !stats.acquire.is_empty() {
+                write!(f, "\n    connection acquire: {}", stats.acquire)?;
+            }
+            if stats.errors.total() > 0 {
+                for (class, count) in stats.errors.iter() {
+                    write!(f, "\n    {}: {}", class, count)?;
+                }
+                let series = stats.error_series.to_string();
+                write!(f, "\n    {}", series.replace('\n', "\n    "))?;
+            }
+            if !stats.statements.is_empty() {
+                let statements = stats.statements.to_string();
+                write!(f, "\n    {}", statements.replace('\n', "\n    "))?;
+            }
+        }
+        for snapshot in &self.regions {
+            write!(f, "\n  {}", snapshot)?;
+        }
+        if let Some(server) = &self.server {
+            write!(f, "\n  server: {}", server)?;
+        }
+        for query in &self.slow_queries {
+            write!(f, "\n  slow: {}", query)?;
+        }
+        for (workload, throughput, p99) in &self.spread {
+            let repetitions = repetitions();
+            if let Some(throughput) = throughput {
+                write!(
+                    f,
+                    "\n  {} over {} repetitions, throughput: {}",
+                    workload, repetitions, throughput
+                )?;
+            }
+            if let Some(p99) = p99 {
+                write!(
+                    f,
+                    "\n  {} over {} repetitions, p99 ms: {}",
+                    workload, repetitions, p99
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,334 @@
+//! Scenarios described in a TOML file:
+//!
+//! ```toml
+//! # executed once before and after everything else
+//! setup_sql = ["set @@global.tidb_txn_assertion_level = strict"]
+//! teardown_sql = ["set @@global.tidb_txn_assertion_level = fast"]
+//! # "abort" (the default) or "continue" when hook SQL fails
+//! on_sql_error = "continue"
+//! # workloads set up before the first phase
+//! setup = ["contention-update"]
+//! # applied before the first phase, which waits until it is scheduled
+//! placement = { options = 'LEADER_CONSTRAINTS="[+zone=z1]"', tables = ["t"], timeout = 600 }
+//! invariants = ["select count(*) from t where v < 0"]
+//! # ADMIN CHECK TABLE every admin_check_secs (300 if not given)
+//! admin_check = ["t"]
+//!
+//! [[phase]]
+//! name = "baseline"
+//! duration = 60
+//! workloads = [{ name = "contention-read", concurrency = 5 }]
+//!
+//! [[phase]]
+//! name = "contention"
+//! duration = 600
+//! before = ["set @@global.tidb_enable_async_commit = 0"]
+//! after = ["set @@global.tidb_enable_async_commit = 1"]
+//! workloads = [
+//!     { name = "contention-update", concurrency = 15 },
+//!     { name = "contention-read", concurrency = 5 },
+//! ]
+//! ```
+//!
+//! A phase without `duration` lasts the default given to [`load`]. A workload
+//! entry can also have a `rate` in iterations per second over its workers,
+//! which must be positive.
+//! Workloads are looked up by name: contention-update, contention-read,
+//! single-row-update, soft-delete-write, soft-delete-read,
+//! soft-delete-cleanup (retention 600s), fk-dml, fk-ddl, partition-dml,
+//! partition-ddl, reorg-dml, reorg-ddl, gen-dml, gen-ddl, lossy-dml,
+//! lossy-ddl, insert-auto-increment, insert-scattered, insert-auto-random,
+//! insert-common-handle,
+//! lock-nowait-point, lock-nowait-range, lock-skip-point, lock-skip-range
+//! (whose outcomes are logged when torn down), upsert-on-duplicate and
+//! upsert-replace (half of them of existing keys, latencies logged when
+//! torn down), mix (weighted by an optional top-level `mix = "insert=30,update=50"`),
+//! batch-update, batch-delete, point-write and point-write-odd, the last four
+//! sized by an optional `[batch_dml]` table with `rows` and `batch_size`,
+//! and composite-write and composite-read on a table with a composite primary
+//! key described by an optional `[composite]` table:
+//!
+//! ```toml
+//! [composite]
+//! table = "composite"
+//! columns = [
+//!     { name = "tenant_id", dist = "hotspot:100:5:0.5" },
+//!     { name = "id", dist = "uniform:10000" },
+//!     { name = "k", dist = "uniform:10000" },
+//! ]
+//! primary_key = ["tenant_id", "id"]
+//! indexes = [["tenant_id", "k"]]
+//! ```
+//!
+//! A name used in several phases refers to the same workload.
+use crate::error::MyError;
+use crate::invariant::InvariantOpts;
+use crate::placement::Placement;
+use crate::scenario::{Phase, Scenario, SqlErrorPolicy};
+use crate::workload::batch_dml::{BatchDelete, BatchTable, BatchUpdate, PointWrite};
+use crate::workload::composite::{CompositeRead, CompositeWrite};
+use crate::workload::contention::{ContentionRead, ContentionUpdate};
+use crate::workload::foreign_key::{FkDdl, FkDml};
+use crate::workload::generated::{GenDdl, GenDml, GenTable};
+use crate::workload::insert::{Insert, PkKind};
+use crate::workload::keyspace::{KeyColumn, KeySpace};
+use crate::workload::locking::{LockMode, LockingRead};
+use crate::workload::lossy::{LossyDdl, LossyDml, LossyTable};
+use crate::workload::mix::{Mix, OpWeights};
+use crate::workload::partition::{PartitionDdl, PartitionDml, PartitionTable};
+use crate::workload::reorganize::{ReorgDdl, ReorgDml, ReorgTable};
+use crate::workload::single_row::SingleRowUpdate;
+use crate::workload::soft_delete::{SoftDeleteCleanup, SoftDeleteRead, SoftDeleteWrite};
+use crate::workload::upsert::{Upsert, UpsertKind};
+use crate::workload::Workload;
+use crate::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    #[serde(default)]
+    setup: Vec<String>,
+    #[serde(default)]
+    invariants: Vec<String>,
+    #[serde(default = "default_invariant_secs")]
+    invariant_secs: u64,
+    #[serde(default)]
+    abort_on_violation: bool,
+    #[serde(default)]
+    admin_check: Vec<String>,
+    #[serde(default = "default_admin_check_secs")]
+    admin_check_secs: u64,
+    #[serde(default)]
+    setup_sql: Vec<String>,
+    #[serde(default)]
+    teardown_sql: Vec<String>,
+    #[serde(default)]
+    on_sql_error: SqlErrorPolicy,
+    placement: Option<PlacementFile>,
+    #[serde(default)]
+    batch_dml: BatchDmlParams,
+    composite: Option<CompositeParams>,
+    /// Weights of the `mix` workload, e.g. "insert=30,update=50"
+    mix: Option<String>,
+    phase: Vec<PhaseFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PhaseFile {
+    name: String,
+    duration: Option<u64>,
+    #[serde(default)]
+    before: Vec<String>,
+    #[serde(default)]
+    after: Vec<String>,
+    workloads: Vec<MixFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MixFile {
+    name: String,
+    concurrency: usize,
+    /// Iterations per second over all workers of this workload
+    rate: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlacementFile {
+    options: String,
+    tables: Vec<String>,
+    #[serde(default = "default_placement_timeout")]
+    timeout: u64,
+}
+
+/// Parameters of the `batch-*` and `point-write*` workloads.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchDmlParams {
+    rows: u64,
+    batch_size: u64,
+}
+
+impl Default for BatchDmlParams {
+    fn default() -> Self {
+        BatchDmlParams {
+            rows: 1_000_000,
+            batch_size: 1000,
+        }
+    }
+}
+
+/// Key space of the `composite-*` workloads.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CompositeParams {
+    table: String,
+    columns: Vec<KeyColumn>,
+    primary_key: Vec<String>,
+    #[serde(default)]
+    indexes: Vec<Vec<String>>,
+}
+
+fn default_invariant_secs() -> u64 {
+    10
+}
+
+fn default_admin_check_secs() -> u64 {
+    300
+}
+
+fn default_placement_timeout() -> u64 {
+    600
+}
+
+/// Read the scenario in `path`.
+pub fn load(path: &str, default_duration: Duration) -> Result<Scenario> {
+    let file: ScenarioFile = toml::from_str(&std::fs::read_to_string(path)?)
+        .map_err(|e| MyError::StringError(format!("invalid scenario {}: {}", path, e)))?;
+    let keys = match &file.composite {
+        Some(c) => KeySpace::new(&c.table, c.columns.clone(), &c.primary_key, &c.indexes)?,
+        None => KeySpace::default(),
+    };
+    let mix = match &file.mix {
+        Some(mix) => mix.parse()?,
+        None => OpWeights::default(),
+    };
+    let mut registry = Registry::new(&file.batch_dml, keys, mix);
+    let mut scenario = Scenario::new()
+        .invariants(InvariantOpts {
+            queries: file.invariants,
+            interval_secs: file.invariant_secs,
+            abort: file.abort_on_violation,
+            admin_check: file.admin_check,
+            admin_check_secs: file.admin_check_secs,
+        })
+        .on_sql_error(file.on_sql_error);
+    if let Some(placement) = file.placement {
+        scenario = scenario.placement(Placement {
+            options: placement.options,
+            tables: placement.tables,
+            timeout: Duration::from_secs(placement.timeout),
+        });
+    }
+    for sql in &file.setup_sql {
+        scenario = scenario.setup_sql(sql);
+    }
+    for sql in &file.teardown_sql {
+        scenario = scenario.teardown_sql(sql);
+    }
+    for name in &file.setup {
+        scenario = scenario.setup(registry.get(name)?);
+    }
+    for phase in file.phase {
+        let duration = phase.duration.map_or(default_duration, Duration::from_secs);
+        let mut built = Phase::new(&phase.name, duration);
+        for mix in &phase.workloads {
+            let workload = registry.get(&mix.name)?;
+            built = match mix.rate {
+                // also rejects NaN and infinity, which the pacer can't use
+                Some(rate) if !rate.is_finite() || rate <= 0.0 => {
+                    return Err(MyError::StringError(format!(
+                        "invalid scenario {}: rate of {} in phase {} must be positive, got {}",
+                        path, mix.name, phase.name, rate
+                    )))
+                }
+                Some(rate) => built.with_rate(workload, mix.concurrency, rate),
+                None => built.with(workload, mix.concurrency),
+            };
+        }
+        for sql in &phase.before {
+            built = built.before(sql);
+        }
+        for sql in &phase.after {
+            built = built.after(sql);
+        }
+        scenario = scenario.phase(built);
+    }
+    Ok(scenario)
+}
+
+/// Workloads by name, each created once.
+struct Registry {
+    workloads: HashMap<String, Arc<dyn Workload>>,
+    batch_table: Arc<BatchTable>,
+    keys: Arc<KeySpace>,
+    partition_table: Arc<PartitionTable>,
+    reorg_table: Arc<ReorgTable>,
+    gen_table: Arc<GenTable>,
+    lossy_table: Arc<LossyTable>,
+    mix: OpWeights,
+}
+
+impl Registry {
+    fn new(params: &BatchDmlParams, keys: KeySpace, mix: OpWeights) -> Registry {
+        Registry {
+            workloads: HashMap::new(),
+            batch_table: Arc::new(BatchTable::new(params.rows, params.batch_size)),
+            keys: Arc::new(keys),
+            partition_table: Arc::default(),
+            reorg_table: Arc::default(),
+            gen_table: Arc::default(),
+            lossy_table: Arc::default(),
+            mix,
+        }
+    }
+
+    fn get(&mut self, name: &str) -> Result<Arc<dyn Workload>> {
+        if let Some(w) = self.workloads.get(name) {
+            return Ok(w.clone());
+        }
+        let table = self.batch_table.clone();
+        let w: Arc<dyn Workload> = match name {
+            "contention-update" => Arc::new(ContentionUpdate),
+            "contention-read" => Arc::new(ContentionRead),
+            "single-row-update" => Arc::new(SingleRowUpdate::default()),
+            "soft-delete-write" => Arc::new(SoftDeleteWrite),
+            "soft-delete-read" => Arc::new(SoftDeleteRead),
+            "soft-delete-cleanup" => Arc::new(SoftDeleteCleanup {
+                retention_secs: 600,
+            }),
+            "batch-update" => Arc::new(BatchUpdate(table)),
+            "batch-delete" => Arc::new(BatchDelete(table)),
+            "point-write" => Arc::new(PointWrite {
+                table,
+                odd_only: false,
+            }),
+            "point-write-odd" => Arc::new(PointWrite {
+                table,
+                odd_only: true,
+            }),
+            "fk-dml" => Arc::new(FkDml),
+            "fk-ddl" => Arc::new(FkDdl::default()),
+            "partition-dml" => Arc::new(PartitionDml(self.partition_table.clone())),
+            "partition-ddl" => Arc::new(PartitionDdl(self.partition_table.clone())),
+            "reorg-dml" => Arc::new(ReorgDml(self.reorg_table.clone())),
+            "reorg-ddl" => Arc::new(ReorgDdl(self.reorg_table.clone())),
+            "gen-dml" => Arc::new(GenDml(self.gen_table.clone())),
+            "gen-ddl" => Arc::new(GenDdl(self.gen_table.clone())),
+            "lossy-dml" => Arc::new(LossyDml(self.lossy_table.clone())),
+            "lossy-ddl" => Arc::new(LossyDdl(self.lossy_table.clone())),
+            "insert-auto-increment" => Arc::new(Insert::new(PkKind::AutoIncrement)),
+            "insert-scattered" => Arc::new(Insert::new(PkKind::Scattered)),
+            "insert-auto-random" => Arc::new(Insert::new(PkKind::AutoRandom)),
+            "insert-common-handle" => Arc::new(Insert::new(PkKind::CommonHandle)),
+            "lock-nowait-point" => Arc::new(LockingRead::new(LockMode::Nowait, false)),
+            "lock-nowait-range" => Arc::new(LockingRead::new(LockMode::Nowait, true)),
+            "lock-skip-point" => Arc::new(LockingRead::new(LockMode::SkipLocked, false)),
+            "lock-skip-range" => Arc::new(LockingRead::new(LockMode::SkipLocked, true)),
+            "upsert-on-duplicate" => Arc::new(Upsert::new(UpsertKind::OnDuplicate, 0.5)),
+            "upsert-replace" => Arc::new(Upsert::new(UpsertKind::Replace, 0.5)),
+            "mix" => Arc::new(Mix::new(&self.mix)),
+            "composite-write" => Arc::new(CompositeWrite(self.keys.clone())),
+            "composite-read" => Arc::new(CompositeRead(self.keys.clone())),
+            _ => return Err(MyError::StringError(format!("unknown workload {}", name))),
+        };
+        self.workloads.insert(name.to_owned(), w.clone());
+        Ok(w)
+    }
+}
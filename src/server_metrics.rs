@@ -0,0 +1,237 @@
+//! Metrics of the TiDB server under test, scraped from its status port during
+//! each scenario phase with `--tidb-status`, to explain the client-side
+//! numbers, e.g. a drop in throughput by a rise of the KV request duration.
+//!
+//! The collector scrapes `/metrics` every [`SCRAPE_INTERVAL`] and adds up the
+//! increments of a few counters and histograms, skipping those across a
+//! restart of the server. The phase report then shows their averages over
+//! the phase.
+use crate::Result;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+pub const SCRAPE_INTERVAL: Duration = Duration::from_secs(10);
+
+enum Kind {
+    /// Increments of a counter per second.
+    Rate,
+    /// Increments of the `_sum` of a histogram over those of its `_count`.
+    Mean,
+}
+
+/// Series summarized in reports: name, metric, a label all lines summed
+/// up must have, if any, and how to summarize it.
+const SERIES: &[(&str, &str, Option<&str>, Kind)] = &[
+    (
+        "kv request duration (s)",
+        "tidb_tikvclient_request_seconds",
+        None,
+        Kind::Mean,
+    ),
+    (
+        "txn retries/s",
+        "tidb_session_retry_num_sum",
+        None,
+        Kind::Rate,
+    ),
+    (
+        "write conflicts/s",
+        "tidb_server_execute_error_total",
+        Some("9007"),
+        Kind::Rate,
+    ),
+    (
+        "lock resolve backoffs/s",
+        "tidb_tikvclient_backoff_seconds_count",
+        Some("type=\"txnLock\""),
+        Kind::Rate,
+    ),
+];
+
+static COLLECTOR: OnceLock<Collector> = OnceLock::new();
+
+pub struct Collector {
+    client: reqwest::Client,
+    url: String,
+}
+
+/// Scrape the TiDB status address `addr`, e.g. `127.0.0.1:10080`, during
+/// the phases run from now on.
+pub fn init(addr: &str) {
+    let _ = COLLECTOR.set(Collector {
+        client: reqwest::Client::new(),
+        url: format!("http://{}/metrics", addr),
+    });
+}
+
+/// The collector set with [`init`], if any.
+pub fn get() -> Option<&'static Collector> {
+    COLLECTOR.get()
+}
+
+/// Increments of the scraped totals since the start, keyed by metric.
+#[derive(Default)]
+struct Increments {
+    last: Option<HashMap<String, f64>>,
+    total: HashMap<String, f64>,
+}
+
+impl Increments {
+    fn add(&mut self, now: HashMap<String, f64>) {
+        if let Some(last) = &self.last {
+            for (metric, value) in &now {
+                let delta = value - last.get(metric).copied().unwrap_or(0.0);
+                // a counter going back means the server restarted
+                if delta > 0.0 {
+                    *self.total.entry(metric.clone()).or_default() += delta;
+                }
+            }
+        }
+        self.last = Some(now);
+    }
+}
+
+/// Scraping of one phase, started by [`Collector::start`].
+pub struct Collecting {
+    start: Instant,
+    increments: Arc<Mutex<Increments>>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for Collecting {
+    // e.g. when a phase fails
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Averages of the [`SERIES`] over a phase, `None` where nothing was
+/// scraped or a histogram had no observations.
+#[derive(Debug, Clone)]
+pub struct ServerMetrics(Vec<(&'static str, Option<f64>)>);
+
+impl Collector {
+    pub fn start(&'static self) -> Collecting {
+        let increments = Arc::new(Mutex::new(Increments::default()));
+        let task = {
+            let increments = increments.clone();
+            tokio::spawn(async move {
+                loop {
+                    self.scrape_into(&increments).await;
+                    tokio::time::sleep(SCRAPE_INTERVAL).await;
+                }
+            })
+        };
+        Collecting {
+            start: Instant::now(),
+            increments,
+            task,
+        }
+    }
+
+    async fn scrape_into(&self, increments: &Mutex<Increments>) {
+        match self.scrape().await {
+            Ok(totals) => increments.lock().unwrap().add(totals),
+            Err(e) => warn!("failed to scrape {}: {:?}", self.url, e),
+        }
+    }
+
+    /// The current totals of the metrics of [`SERIES`].
+    async fn scrape(&self) -> Result<HashMap<String, f64>> {
+        let text = self
+            .client
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let mut totals = HashMap::new();
+        for (name, metric, filter, kind) in SERIES {
+            match kind {
+                Kind::Rate => {
+                    totals.insert(name.to_string(), total(&text, metric, *filter));
+                }
+                Kind::Mean => {
+                    let sum = total(&text, &format!("{}_sum", metric), *filter);
+                    let count = total(&text, &format!("{}_count", metric), *filter);
+                    totals.insert(name.to_string(), sum);
+                    totals.insert(count_key(name), count);
+                }
+            }
+        }
+        Ok(totals)
+    }
+}
+
+impl Collecting {
+    /// Stop scraping after a last scrape, and average the series over the
+    /// phase.
+    pub async fn finish(self) -> ServerMetrics {
+        self.task.abort();
+        let collector = get().expect("collecting without a collector");
+        collector.scrape_into(&self.increments).await;
+        let secs = self.start.elapsed().as_secs_f64();
+        let increments = self.increments.lock().unwrap();
+        let scraped = increments.last.is_some();
+        let total = |key: &str| increments.total.get(key).copied().unwrap_or(0.0);
+        let metrics = ServerMetrics(
+            SERIES
+                .iter()
+                .map(|(name, _, _, kind)| {
+                    let value = match kind {
+                        _ if !scraped => None,
+                        Kind::Rate => Some(total(name) / secs),
+                        Kind::Mean => {
+                            let count = total(&count_key(name));
+                            (count > 0.0).then(|| total(name) / count)
+                        }
+                    };
+                    (*name, value)
+                })
+                .collect(),
+        );
+        info!("server metrics: {}", metrics);
+        metrics
+    }
+}
+
+fn count_key(name: &str) -> String {
+    format!("{} count", name)
+}
+
+/// Sum of the samples of `metric` in the text exposition `text` whose labels
+/// contain `filter`.
+fn total(text: &str, metric: &str, filter: Option<&str>) -> f64 {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let (series, value) = line.rsplit_once(' ')?;
+            let (name, labels) = series.split_once('{').unwrap_or((series, ""));
+            if name != metric || filter.is_some_and(|f| !labels.contains(f)) {
+                return None;
+            }
+            value.parse::<f64>().ok()
+        })
+        .sum()
+}
+
+/// E.g. `kv request duration (s): 0.002, txn retries/s: 0.0, ...`.
+impl fmt::Display for ServerMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            match value {
+                Some(v) => write!(f, "{}: {:.4}", name, v)?,
+                None => write!(f, "{}: -", name)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! Helpers for per-connection session state.
+use crate::error::MyError;
+use crate::Result;
+use log::warn;
+use sqlx::mysql::{MySqlConnection, MySqlPoolOptions};
+use sqlx::{Executor, Row};
+use std::str::FromStr;
+
+/// Transaction isolation level of the worker connections. TiDB supports
+/// READ COMMITTED only in pessimistic transactions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Isolation {
+    RepeatableRead,
+    ReadCommitted,
+}
+
+impl FromStr for Isolation {
+    type Err = MyError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "repeatable-read" => Ok(Isolation::RepeatableRead),
+            "read-committed" => Ok(Isolation::ReadCommitted),
+            _ => Err(MyError::StringError(format!(
+                "unknown isolation level {}, expected repeatable-read or read-committed",
+                s
+            ))),
+        }
+    }
+}
+
+impl Isolation {
+    pub fn sql(self) -> &'static str {
+        match self {
+            Isolation::RepeatableRead => "set session transaction isolation level repeatable read",
+            Isolation::ReadCommitted => "set session transaction isolation level read committed",
+        }
+    }
+}
+
+/// Set `isolation`, if given, on every connection the pool opens, including
+/// the ones replacing broken connections.
+pub fn with_isolation(options: MySqlPoolOptions, isolation: Option<Isolation>) -> MySqlPoolOptions {
+    match isolation {
+        Some(isolation) => options.after_connect(move |conn| {
+            Box::pin(async move {
+                conn.execute(isolation.sql()).await?;
+                Ok(())
+            })
+        }),
+        None => options,
+    }
+}
+
+/// Label the session as `<workload>-<worker>` so the TiDB log and the
+/// processlist show which worker issued a statement.
+pub async fn set_session_alias(
+    conn: &mut MySqlConnection,
+    workload: &str,
+    worker: usize,
+) -> Result<()> {
+    conn.execute(format!("set @@tidb_session_alias = '{}-{}'", workload, worker).as_str())
+        .await?;
+    Ok(())
+}
+
+pub async fn set_session_vars(conn: &mut MySqlConnection, vars: &[(&str, &str)]) -> Result<()> {
+    for (name, value) in vars {
+        conn.execute(set_session_var_sql(name, value).as_str())
+            .await?;
+    }
+    Ok(())
+}
+
+pub fn set_session_var_sql(name: &str, value: &str) -> String {
+    format!("set @@session.{} = '{}'", name, value)
+}
+
+/// Re-apply the `vars` whose session value is no longer the expected one,
+/// e.g. after a reconnect, logging each drift. Returns how many drifted.
+pub async fn restore_session_vars(
+    conn: &mut MySqlConnection,
+    vars: &[(&str, &str)],
+) -> Result<usize> {
+    let mut drifted = 0;
+    for (name, value) in vars {
+        let row = conn
+            .fetch_one(format!("select @@session.{}", name).as_str())
+            .await?;
+        let actual: String = row.try_get_unchecked(0)?;
+        if normalize(&actual) != normalize(value) {
+            warn!(
+                "{} drifted to {}, setting it back to {}",
+                name, actual, value
+            );
+            set_session_vars(conn, &[(name, value)]).await?;
+            drifted += 1;
+        }
+    }
+    Ok(drifted)
+}
+
+fn normalize(value: &str) -> String {
+    match value.to_lowercase().as_str() {
+        "1" | "on" | "true" => "on".to_owned(),
+        "0" | "off" | "false" => "off".to_owned(),
+        v => v.to_owned(),
+    }
+}
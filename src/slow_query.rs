@@ -0,0 +1,103 @@
+//! The slowest statements of each scenario phase, with `--slow-queries N`:
+//! after the phase, `information_schema.cluster_slow_query` is read for the
+//! phase's window and aggregated by plan digest, and the N plans with the
+//! most total time go to the report.
+use crate::Result;
+use log::{info, warn};
+use sqlx::mysql::{MySqlConnection, MySqlRow};
+use sqlx::{Executor, Row};
+use std::fmt;
+use std::sync::OnceLock;
+
+// longest query text kept of a plan
+const QUERY_LEN: usize = 200;
+
+static TOP: OnceLock<u32> = OnceLock::new();
+
+/// Capture the `top` slowest plans of the phases run from now on.
+pub fn capture(top: u32) {
+    let _ = TOP.set(top);
+}
+
+/// How many plans to capture, if set with [`capture`].
+pub fn top() -> Option<u32> {
+    TOP.get().copied()
+}
+
+/// Slow executions of one plan.
+#[derive(Debug, Clone)]
+pub struct SlowQuery {
+    pub plan_digest: String,
+    pub count: u64,
+    pub total_secs: f64,
+    pub max_secs: f64,
+    /// One of the queries, cut to a few hundred characters.
+    pub query: String,
+}
+
+/// The server's current time, the start or end of a window.
+pub async fn now(conn: &mut MySqlConnection) -> Result<String> {
+    let row = conn.fetch_one("select cast(now(6) as char)").await?;
+    let now: Option<String> = row.try_get_unchecked(0)?;
+    Ok(now.unwrap_or_default())
+}
+
+/// The `top` plans with the most total time in the slow log between `from`
+/// and `to`, both from [`now`]. Failures only log a warning.
+pub async fn slowest(conn: &mut MySqlConnection, from: &str, to: &str, top: u32) -> Vec<SlowQuery> {
+    match fetch(conn, from, to, top).await {
+        Ok(queries) => queries,
+        Err(e) => {
+            warn!("failed to read the slow log: {:?}", e);
+            Vec::new()
+        }
+    }
+}
+
+async fn fetch(
+    conn: &mut MySqlConnection,
+    from: &str,
+    to: &str,
+    top: u32,
+) -> Result<Vec<SlowQuery>> {
+    let sql = format!(
+        "select plan_digest, cast(count(*) as char), cast(sum(query_time) as char), \
+        cast(max(query_time) as char), any_value(query) \
+        from information_schema.cluster_slow_query \
+        where time between '{}' and '{}' and is_internal = false \
+        group by plan_digest order by sum(query_time) desc limit {}",
+        from, to, top
+    );
+    let text = |row: &MySqlRow, i| -> Result<String> {
+        let v: Option<String> = row.try_get_unchecked(i)?;
+        Ok(v.unwrap_or_default())
+    };
+    let mut queries = Vec::new();
+    for row in conn.fetch_all(sql.as_str()).await? {
+        let query: String = text(&row, 4)?.chars().take(QUERY_LEN).collect();
+        queries.push(SlowQuery {
+            plan_digest: text(&row, 0)?,
+            count: text(&row, 1)?.parse().unwrap_or(0),
+            total_secs: text(&row, 2)?.parse().unwrap_or(0.0),
+            max_secs: text(&row, 3)?.parse().unwrap_or(0.0),
+            query,
+        });
+    }
+    info!("{} slow plans between {} and {}", queries.len(), from, to);
+    Ok(queries)
+}
+
+/// E.g. `3.2s in 12, max 0.9s, plan 5d3c...: select ...`.
+impl fmt::Display for SlowQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1}s in {}, max {:.1}s, plan {:.8}: {}",
+            self.total_secs,
+            self.count,
+            self.max_secs,
+            self.plan_digest,
+            self.query.replace('\n', " ")
+        )
+    }
+}
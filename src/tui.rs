@@ -0,0 +1,167 @@
+//! A live dashboard of the running scenario phase, enabled with `--tui`: per
+//! workload the throughput and p99 latency of the last second, a sparkline
+//! of the p99 and the errors so far, above the time left in the phase.
+//!
+//! The dashboard takes over the terminal while a phase runs and gives it back
+//! for the phase report. The terminal is not put in raw mode, so Ctrl-C
+//! still interrupts the run.
+use crate::metrics::{LiveSnapshot, LiveStats};
+use log::warn;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::cursor::{Hide, Show};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::widgets::{Block, Borders, Gauge, Paragraph, Sparkline};
+use ratatui::Terminal;
+use std::collections::VecDeque;
+use std::io::{stdout, Stdout};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+// points of a sparkline, one per refresh
+const HISTORY: usize = 120;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Show the dashboard during the phases run from now on.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+struct Row<'a> {
+    name: &'a str,
+    live: &'a LiveStats,
+    last: LiveSnapshot,
+    throughput: f64,
+    p99: Option<Duration>,
+    // p99 of each refresh in microseconds
+    history: VecDeque<u64>,
+}
+
+/// Draw the dashboard of phase `phase`, which started at `start` and lasts
+/// `duration`, until cancelled. Terminal errors only log a warning and end
+/// the dashboard, never the run.
+pub async fn show(
+    phase: &str,
+    start: Instant,
+    duration: Duration,
+    workloads: &[(&str, Arc<LiveStats>)],
+) {
+    let mut terminal = match open() {
+        Ok(terminal) => Closing(terminal),
+        Err(e) => {
+            warn!("can't show the dashboard: {:?}", e);
+            return std::future::pending().await;
+        }
+    };
+    let mut rows: Vec<Row> = workloads
+        .iter()
+        .map(|(name, live)| Row {
+            name,
+            live,
+            last: live.snapshot(),
+            throughput: 0.0,
+            p99: None,
+            history: VecDeque::with_capacity(HISTORY),
+        })
+        .collect();
+    let mut last_at = Instant::now();
+    loop {
+        tokio::time::sleep(REFRESH_INTERVAL).await;
+        let secs = last_at.elapsed().as_secs_f64();
+        last_at = Instant::now();
+        for row in &mut rows {
+            let now = row.live.snapshot();
+            row.throughput = (now.succeeded - row.last.succeeded) as f64 / secs;
+            row.p99 = now.percentile_since(&row.last, 99.0);
+            if row.history.len() == HISTORY {
+                row.history.pop_front();
+            }
+            row.history
+                .push_back(row.p99.map_or(0, |p99| p99.as_micros() as u64));
+            row.last = now;
+        }
+        if let Err(e) = draw(&mut terminal.0, phase, start, duration, &rows) {
+            warn!("can't show the dashboard: {:?}", e);
+            return std::future::pending().await;
+        }
+    }
+}
+
+/// Leaves the alternate screen when dropped, i.e. when the dashboard is
+/// cancelled.
+struct Closing(Terminal<CrosstermBackend<Stdout>>);
+
+impl Drop for Closing {
+    fn drop(&mut self) {
+        let _ = execute!(self.0.backend_mut(), LeaveAlternateScreen, Show);
+    }
+}
+
+fn open() -> std::io::Result<Terminal<CrosstermBackend<Stdout>>> {
+    let mut out = stdout();
+    execute!(out, EnterAlternateScreen, Hide)?;
+    Terminal::new(CrosstermBackend::new(out))
+}
+
+fn draw(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    phase: &str,
+    start: Instant,
+    duration: Duration,
+    rows: &[Row],
+) -> std::io::Result<()> {
+    terminal.draw(|frame| {
+        let mut constraints = vec![Constraint::Length(3)];
+        constraints.extend(rows.iter().map(|_| Constraint::Length(5)));
+        constraints.push(Constraint::Min(0));
+        let areas = Layout::vertical(constraints).split(frame.area());
+
+        let elapsed = start.elapsed().min(duration);
+        let ratio = elapsed.as_secs_f64() / duration.as_secs_f64().max(f64::MIN_POSITIVE);
+        let left = duration.saturating_sub(elapsed).as_secs();
+        frame.render_widget(
+            Gauge::default()
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(format!("phase {}", phase)),
+                )
+                .ratio(ratio.clamp(0.0, 1.0))
+                .label(format!("{}s left", left)),
+            areas[0],
+        );
+
+        for (row, area) in rows.iter().zip(areas.iter().skip(1)) {
+            let snapshot = &row.last;
+            let title = format!(
+                "{}: {:.1}/s, p99 {}, {} ok, {} errors",
+                row.name,
+                row.throughput,
+                row.p99.map_or("-".to_owned(), |p99| format!("{:.1?}", p99)),
+                snapshot.succeeded,
+                snapshot.errors
+            );
+            let history: Vec<u64> = row.history.iter().copied().collect();
+            frame.render_widget(
+                Sparkline::default()
+                    .block(Block::default().borders(Borders::ALL).title(title))
+                    .data(&history),
+                *area,
+            );
+        }
+
+        frame.render_widget(
+            Paragraph::new("p99 latency of each second; Ctrl-C to stop"),
+            areas[areas.len() - 1],
+        );
+    })?;
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Sampling of the timestamps of committed transactions, with
+//! `--sample-txn-ts <ratio>`, to find a client-side latency spike in the
+//! TiKV logs by its start_ts.
+//!
+//! Sampled transactions go to `txn_ts.tsv` (in the run's directory if it has
+//! one), a line each:
+//! `<unix micros>\t<workload>\t<start_ts>\t<commit_ts>\t<latency micros>`.
+use crate::output;
+use crate::Result;
+use log::warn;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{Executor, Row};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+struct Sampler {
+    ratio: f64,
+    file: Mutex<BufWriter<File>>,
+}
+
+static SAMPLER: OnceLock<Sampler> = OnceLock::new();
+
+/// Sample `ratio` of the transactions committed from now on.
+pub fn sample(ratio: f64) -> Result<()> {
+    let file = File::create(output::path("txn_ts.tsv"))?;
+    let _ = SAMPLER.set(Sampler {
+        ratio: ratio.clamp(0.0, 1.0),
+        file: Mutex::new(BufWriter::new(file)),
+    });
+    Ok(())
+}
+
+/// The ratio of transactions to sample, 0 if not sampling. Workloads only
+/// draw against it when it isn't 0, so their RNGs are unchanged otherwise.
+pub fn ratio() -> f64 {
+    SAMPLER.get().map_or(0.0, |s| s.ratio)
+}
+
+/// Record the timestamps of the transaction `conn` just committed, which
+/// took `latency` from its BEGIN. Failures only log a warning.
+pub async fn record(conn: &mut MySqlConnection, workload: &str, latency: Duration) {
+    let Some(sampler) = SAMPLER.get() else {
+        return;
+    };
+    let res = async {
+        let row = conn
+            .fetch_one(
+                "select cast(json_extract(@@tidb_last_txn_info, '$.start_ts') as char), \
+                cast(json_extract(@@tidb_last_txn_info, '$.commit_ts') as char)",
+            )
+            .await?;
+        let start_ts: Option<String> = row.try_get_unchecked(0)?;
+        let commit_ts: Option<String> = row.try_get_unchecked(1)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_micros());
+        let mut file = sampler.file.lock().unwrap();
+        writeln!(
+            file,
+            "{}\t{}\t{}\t{}\t{}",
+            now,
+            workload,
+            start_ts.unwrap_or_default(),
+            commit_ts.unwrap_or_default(),
+            latency.as_micros()
+        )?;
+        file.flush()?;
+        Result::Ok(())
+    }
+    .await;
+    if let Err(e) = res {
+        warn!("failed to sample the timestamps of a transaction: {:?}", e);
+    }
+}
@@ -0,0 +1,50 @@
+//! Aggregation of `SHOW WARNINGS` output.
+//!
+//! Truncation and implicit conversion only surface as warnings, so workers
+//! sample them after some statements and the counts are reported at the end.
+use crate::Result;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{Executor, Row};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Default)]
+pub struct Warnings {
+    // code -> (count, first message seen)
+    codes: BTreeMap<u64, (u64, String)>,
+}
+
+impl Warnings {
+    /// Fetch the warnings of the last statement executed on `conn`.
+    pub async fn sample(&mut self, conn: &mut MySqlConnection) -> Result<()> {
+        for row in conn.fetch_all("SHOW WARNINGS").await? {
+            let code: u64 = row.try_get_unchecked("Code")?;
+            let message: String = row.try_get_unchecked("Message")?;
+            self.codes.entry(code).or_insert((0, message)).0 += 1;
+        }
+        Ok(())
+    }
+
+    pub fn merge(&mut self, other: Warnings) {
+        for (code, (count, message)) in other.codes {
+            self.codes.entry(code).or_insert((0, message)).0 += count;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+}
+
+impl fmt::Display for Warnings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no warnings");
+        }
+        write!(f, "warnings:")?;
+        for (code, (count, message)) in &self.codes {
+            write!(f, "\n  {}: {} ({})", code, count, message)?;
+        }
+        Ok(())
+    }
+}
@@ -1,62 +1,220 @@
+use crate::control::{pause_switch, wait_resumed};
+use crate::error::{ErrorClass, ErrorCounts};
+use crate::metrics::StatementLatencies;
+use crate::record::ConnRecorder;
+use crate::rng::worker_rng;
+use crate::session::restore_session_vars;
+use crate::warnings::Warnings;
+use crate::workload::ddl_gen::{DdlGen, DdlWeights};
+use crate::workload::schema::Schema;
 use crate::Result;
+use async_trait::async_trait;
 use rand::prelude::StdRng;
+use rand::rngs::SmallRng;
 use rand::Rng;
-use rand::SeedableRng;
 use sqlx::mysql::MySqlConnection;
 use sqlx::Executor;
+use std::collections::HashMap;
 use std::time::Duration;
+use tokio::select;
 use tokio::sync::broadcast::Receiver;
 
-async fn insert(conn: &mut MySqlConnection) -> Result<()> {
-    conn.execute("INSERT INTO `473d9750-7369-4822-91b0-bc6705131333` SET `4af7ba24-c2fa-4deb-8af2-58d5f98783d0` = '2016-05-24 13:20:38', `c1c104bf-2899-4776-8a94-f01f9d728c74` = 'p8q1g'").await?;
-    Ok(())
+pub mod batch_dml;
+pub mod composite;
+pub mod contention;
+pub mod ddl_gen;
+pub mod foreign_key;
+pub mod generated;
+pub mod insert;
+pub mod keyspace;
+pub mod large_txn;
+pub mod locking;
+pub mod lossy;
+pub mod mix;
+pub mod partition;
+pub mod reorganize;
+pub mod schema;
+pub mod single_row;
+pub mod soft_delete;
+pub mod ttl;
+pub mod upsert;
+
+/// State a worker keeps across iterations.
+pub struct Worker {
+    pub id: usize,
+    pub rng: SmallRng,
+    pub effects: Effects,
+    /// Latencies of single statements, for workloads whose iterations are
+    /// transactions of several statements.
+    pub statements: StatementLatencies,
 }
 
-async fn delete(conn: &mut MySqlConnection) -> Result<()> {
-    conn.execute("DELETE FROM `473d9750-7369-4822-91b0-bc6705131333`")
-        .await?;
-    Ok(())
+/// Executions of statements and the rows they affected or returned, to catch
+/// statements that never do anything, e.g. because of a wrong bind.
+/// Workloads record only statements that are expected to touch rows most of
+/// the time.
+#[derive(Debug, Default)]
+pub struct Effects(HashMap<&'static str, (u64, u64)>);
+
+impl Effects {
+    pub fn record(&mut self, statement: &'static str, rows: u64) {
+        let e = self.0.entry(statement).or_default();
+        e.0 += 1;
+        e.1 += rows;
+    }
+
+    /// Statements executed at least `min_executions` times without touching
+    /// any row.
+    pub fn no_ops(&self, min_executions: u64) -> Vec<&'static str> {
+        let mut no_ops: Vec<_> = self
+            .0
+            .iter()
+            .filter(|(_, &(n, rows))| n >= min_executions && rows == 0)
+            .map(|(s, _)| *s)
+            .collect();
+        no_ops.sort();
+        no_ops
+    }
 }
 
-pub async fn create_table(conn: &mut MySqlConnection) -> Result<()> {
-    conn.execute("DROP TABLE IF EXISTS `473d9750-7369-4822-91b0-bc6705131333`")
-        .await?;
-    conn.execute("CREATE TABLE `473d9750-7369-4822-91b0-bc6705131333` (`c1c104bf-2899-4776-8a94-f01f9d728c74` SET('pwl', 'k6sg', 'f', '9rfx', 'o', '9ngz', 'p8q1g', 'kk8y', '5', 'lz', 'g'), `4af7ba24-c2fa-4deb-8af2-58d5f98783d0` TIMESTAMP, PRIMARY KEY (`4af7ba24-c2fa-4deb-8af2-58d5f98783d0`, `c1c104bf-2899-4776-8a94-f01f9d728c74`)) COMMENT '85575ad7-e373-49e7-adb0-dd10541d9478' CHARACTER SET 'utf8mb4' COLLATE 'utf8mb4_bin'").await?;
-    Ok(())
+/// A workload driven by [`crate::runner`]: `setup` runs once, then every
+/// worker calls `run_iteration` in a loop on its own connection, then
+/// `teardown` runs once.
+#[async_trait]
+pub trait Workload: Send + Sync {
+    /// Used as the session alias prefix and in reports.
+    fn name(&self) -> &str;
+
+    async fn setup(&self, _conn: &mut MySqlConnection) -> Result<()> {
+        Ok(())
+    }
+
+    /// One unit of work, typically a transaction. A failed iteration must
+    /// leave the connection usable, e.g. by rolling back.
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()>;
+
+    async fn teardown(&self, _conn: &mut MySqlConnection) -> Result<()> {
+        Ok(())
+    }
 }
 
-async fn add_index(conn: &mut MySqlConnection) -> Result<()> {
-    conn.execute("ALTER TABLE `473d9750-7369-4822-91b0-bc6705131333` ADD INDEX `ef9e02dc-578b-4e7f-acd6-0d0fbbe919f5` (`4af7ba24-c2fa-4deb-8af2-58d5f98783d0`)").await?;
-    Ok(())
+// check warnings after every N-th round of DML
+const WARNING_SAMPLE_INTERVAL: u64 = 100;
+// check session variables after every N-th round of DML
+const SESSION_VARS_CHECK_INTERVAL: u64 = 1000;
+
+/// Session variables the DML connection must keep for the whole run.
+pub const DML_SESSION_VARS: &[(&str, &str)] = &[("tidb_txn_assertion_level", "strict")];
+
+/// What a DML worker saw besides the statements that succeeded.
+#[derive(Debug, Default)]
+pub struct DmlOutcome {
+    pub warnings: Warnings,
+    /// Errors caused by other DML workers writing the same rows. The
+    /// statement is skipped and the round goes on.
+    pub conflicts: ErrorCounts,
+    /// Whether the last statement, the delete of all rows, failed, so rows
+    /// may be left behind.
+    pub left_rows: bool,
 }
 
-async fn drop_index(conn: &mut MySqlConnection) -> Result<()> {
-    conn.execute("ALTER TABLE `473d9750-7369-4822-91b0-bc6705131333` DROP INDEX `ef9e02dc-578b-4e7f-acd6-0d0fbbe919f5`").await?;
-    Ok(())
+impl DmlOutcome {
+    pub fn merge(&mut self, other: DmlOutcome) {
+        self.warnings.merge(other.warnings);
+        self.conflicts.merge(&other.conflicts);
+        self.left_rows |= other.left_rows;
+    }
 }
 
-pub async fn dml_worker(conn: &mut MySqlConnection, mut rx: Receiver<()>) -> Result<()> {
-    conn.execute("use test").await?;
-    loop {
+/// `recorder` records the statements of the rounds.
+pub async fn dml_worker(
+    conn: &mut MySqlConnection,
+    mut rx: Receiver<()>,
+    schema: &Schema,
+    recorder: Option<ConnRecorder>,
+) -> Result<DmlOutcome> {
+    let mut outcome = DmlOutcome::default();
+    let mut paused = pause_switch();
+    for i in 0.. {
         if rx.try_recv().is_ok() {
             break;
         }
-        insert(conn).await?;
-        delete(conn).await?;
+        if *paused.borrow() {
+            select! {
+                _ = wait_resumed(&mut paused) => {}
+                _ = rx.recv() => break,
+            }
+        }
+        if i % SESSION_VARS_CHECK_INTERVAL == 0 {
+            restore_session_vars(conn, DML_SESSION_VARS).await?;
+        }
+        let sample = i % WARNING_SAMPLE_INTERVAL == 0;
+        // the last delete removes whatever the statements before it missed
+        for (j, sql) in [
+            schema.insert(),
+            schema.update_indexed(),
+            schema.update_unindexed(),
+            schema.delete_by_index(),
+            schema.delete(),
+        ]
+        .iter()
+        .enumerate()
+        {
+            if let Some(r) = &recorder {
+                r.record(sql);
+            }
+            let res = conn.execute(sql.as_str()).await;
+            if j == 4 {
+                outcome.left_rows = res.is_err();
+            }
+            if let Err(e) = res {
+                let class = ErrorClass::of(&e);
+                match class {
+                    ErrorClass::DuplicateKey
+                    | ErrorClass::WriteConflict
+                    | ErrorClass::Deadlock
+                    | ErrorClass::LockWaitTimeout => {
+                        outcome.conflicts.add(class);
+                        continue;
+                    }
+                    _ => return Err(e.into()),
+                }
+            }
+            if sample {
+                outcome.warnings.sample(conn).await?;
+            }
+        }
     }
-    Ok(())
+    Ok(outcome)
 }
 
-pub async fn ddl_worker(conn: &mut MySqlConnection, mut rx: Receiver<()>) -> Result<()> {
-    conn.execute("use test").await?;
-    let mut rng = StdRng::from_rng(rand::thread_rng())?;
+pub async fn ddl_worker(
+    conn: &mut MySqlConnection,
+    mut rx: Receiver<()>,
+    seed: u64,
+    weights: DdlWeights,
+    schema: &Schema,
+    recorder: Option<ConnRecorder>,
+) -> Result<()> {
+    let mut rng: StdRng = worker_rng(seed, 0);
+    let mut ddl = DdlGen::new(weights, schema.clone());
+    let mut paused = pause_switch();
     loop {
         if rx.try_recv().is_ok() {
             break;
         }
-        add_index(conn).await?;
-        sleep(&mut rng).await;
-        drop_index(conn).await?;
+        if *paused.borrow() {
+            select! {
+                _ = wait_resumed(&mut paused) => {}
+                _ = rx.recv() => break,
+            }
+        }
+        let (kind, sql) = ddl.next(&mut rng);
+        if let Some(r) = &recorder {
+            r.record(&sql);
+        }
+        conn.execute(sql.as_str()).await?;
+        ddl.applied(kind);
         sleep(&mut rng).await;
     }
     Ok(())
@@ -0,0 +1,369 @@
+//! Non-transactional DML (`BATCH ON ... LIMIT ...`) over the whole table while
+//! point writes update single rows concurrently.
+//!
+//! Each row is `(id, v, w)`. Batch updates increment `v` of every row, point
+//! writes set `w` of one row to a new value, and batch deletes remove the rows
+//! with an even id. The client keeps the values `w` of every row may have,
+//! each row written by one point writer at a time, and counts the batch
+//! statements that succeeded and failed so the final state can be checked
+//! with [`BatchTable::verify`]. A batch statement isn't atomic: one that
+//! fails may have applied some of its batches.
+use crate::error::{ErrorClass, MyError};
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use log::{error, info};
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor, Row};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+const INSERT_BATCH: u64 = 1000;
+const MAX_LOGGED_MISMATCHES: u64 = 100;
+// rows share a lock if their ids are equal modulo this
+const ROW_LOCKS: usize = 1024;
+const POINT_UPDATE: &str = "update batch_dml set w = ? where id = ?";
+
+/// The table shared by the workloads of this module.
+pub struct BatchTable {
+    rows: u64,
+    batch_size: u64,
+    // last `w` committed to each row
+    w: Vec<AtomicU64>,
+    // values of the point writes to a row that failed with an unknown
+    // outcome since the last one committed to it
+    in_doubt: Mutex<HashMap<u64, Vec<u64>>>,
+    // held by point writes over the write and the model's update
+    locks: Vec<tokio::sync::Mutex<()>>,
+    // batch updates and deletes that succeeded, and that failed after
+    // applying any number of their batches
+    updates: AtomicU64,
+    deletes: AtomicU64,
+    failed_updates: AtomicU64,
+    failed_deletes: AtomicU64,
+}
+
+/// What the model allows the rows to be at the end of a run.
+struct Expected {
+    rows: u64,
+    // `v` of every row
+    v: RangeInclusive<u64>,
+    // whether the even rows must be gone, and whether some of them may be
+    deleted: bool,
+    maybe_deleted: bool,
+    // last `w` committed to each row, and those of the writes in doubt
+    w: Vec<u64>,
+    in_doubt: HashMap<u64, Vec<u64>>,
+}
+
+/// `BATCH ON id LIMIT n UPDATE`, incrementing `v` of every row. Also creates
+/// the table and resets the model.
+pub struct BatchUpdate(pub Arc<BatchTable>);
+
+/// `BATCH ON id LIMIT n DELETE` of the rows with an even id.
+pub struct BatchDelete(pub Arc<BatchTable>);
+
+/// Autocommit writes of a new `w` to one random row, only of odd ids if
+/// `odd_only`, which batch deletes leave alone. The table must have at least
+/// two rows.
+pub struct PointWrite {
+    pub table: Arc<BatchTable>,
+    pub odd_only: bool,
+}
+
+impl BatchTable {
+    pub fn new(rows: u64, batch_size: u64) -> BatchTable {
+        BatchTable {
+            rows,
+            batch_size,
+            w: (0..rows).map(|_| AtomicU64::new(0)).collect(),
+            in_doubt: Mutex::new(HashMap::new()),
+            locks: (0..ROW_LOCKS)
+                .map(|_| tokio::sync::Mutex::new(()))
+                .collect(),
+            updates: AtomicU64::new(0),
+            deletes: AtomicU64::new(0),
+            failed_updates: AtomicU64::new(0),
+            failed_deletes: AtomicU64::new(0),
+        }
+    }
+
+    fn reset(&self) {
+        for w in &self.w {
+            w.store(0, Ordering::SeqCst);
+        }
+        self.in_doubt.lock().unwrap().clear();
+        self.updates.store(0, Ordering::SeqCst);
+        self.deletes.store(0, Ordering::SeqCst);
+        self.failed_updates.store(0, Ordering::SeqCst);
+        self.failed_deletes.store(0, Ordering::SeqCst);
+    }
+
+    fn expected(&self) -> Expected {
+        let updates = self.updates.load(Ordering::SeqCst);
+        Expected {
+            rows: self.rows,
+            v: updates..=updates + self.failed_updates.load(Ordering::SeqCst),
+            deleted: self.deletes.load(Ordering::SeqCst) > 0,
+            maybe_deleted: self.failed_deletes.load(Ordering::SeqCst) > 0,
+            w: self.w.iter().map(|w| w.load(Ordering::SeqCst)).collect(),
+            in_doubt: self.in_doubt.lock().unwrap().clone(),
+        }
+    }
+
+    /// Compare the table with the client-side model, see [`Expected`].
+    pub async fn verify(&self, conn: &mut MySqlConnection) -> Result<()> {
+        let expected = self.expected();
+        let mut seen = vec![false; self.rows as usize];
+        let mut mismatches = 0;
+        let mut mismatch = |msg: String| {
+            if mismatches < MAX_LOGGED_MISMATCHES {
+                error!("{}", msg);
+            }
+            mismatches += 1;
+        };
+        for row in conn.fetch_all("select id, v, w from batch_dml").await? {
+            let id: u64 = row.try_get_unchecked("id")?;
+            let v: u64 = row.try_get_unchecked("v")?;
+            let w: u64 = row.try_get_unchecked("w")?;
+            if let Some(seen) = seen.get_mut(id as usize) {
+                *seen = true;
+            }
+            if let Some(msg) = expected.row(id, v, w) {
+                mismatch(msg);
+            }
+        }
+        for (id, _) in seen.iter().enumerate().filter(|(_, seen)| !**seen) {
+            if let Some(msg) = expected.absent(id as u64) {
+                mismatch(msg);
+            }
+        }
+        if mismatches > 0 {
+            return Err(MyError::StringError(format!(
+                "batch_dml diverged from the model in {} places",
+                mismatches
+            )));
+        }
+        info!(
+            "batch_dml verified: {} rows, {} rows in doubt",
+            seen.iter().filter(|seen| **seen).count(),
+            expected.in_doubt.len()
+        );
+        Ok(())
+    }
+}
+
+impl Expected {
+    /// Why the row `(id, v, w)` can't be in the table, if it can't: its id is
+    /// out of range, it is an even one that must be deleted, its `v` isn't
+    /// the number of batch updates or its `w` isn't one the point writes may
+    /// have left.
+    fn row(&self, id: u64, v: u64, w: u64) -> Option<String> {
+        let Some(&expected) = self.w.get(id as usize) else {
+            return Some(format!("row {} is beyond the {} rows", id, self.rows));
+        };
+        let in_doubt = self.in_doubt.get(&id).map_or(&[][..], |d| d.as_slice());
+        if self.deleted && id.is_multiple_of(2) {
+            Some(format!("row {} should have been deleted", id))
+        } else if !self.v.contains(&v) {
+            Some(format!("row {} has v = {}, expected {:?}", id, v, self.v))
+        } else if w != expected && !in_doubt.contains(&w) {
+            Some(format!(
+                "row {} has w = {}, expected {} or one of {:?}",
+                id, w, expected, in_doubt
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Why the row `id` must be in the table, if it must: odd ones always,
+    /// even ones unless a batch delete may have removed them.
+    fn absent(&self, id: u64) -> Option<String> {
+        let deletable = id.is_multiple_of(2) && (self.deleted || self.maybe_deleted);
+        (!deletable).then(|| format!("row {} is missing", id))
+    }
+}
+
+#[async_trait]
+impl Workload for BatchUpdate {
+    fn name(&self) -> &str {
+        "batch-update"
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        self.0.reset();
+        conn.execute("drop table if exists batch_dml").await?;
+        conn.execute("create table batch_dml (id bigint primary key, v bigint, w bigint)")
+            .await?;
+        for start in (0..self.0.rows).step_by(INSERT_BATCH as usize) {
+            let end = (start + INSERT_BATCH).min(self.0.rows);
+            conn.execute(
+                format!(
+                    "insert into batch_dml values {}",
+                    (start..end)
+                        .map(|id| format!("({}, 0, 0)", id))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )
+                .as_str(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, _: &mut Worker) -> Result<()> {
+        conn.execute(
+            format!(
+                "batch on id limit {} update batch_dml set v = v + 1",
+                self.0.batch_size
+            )
+            .as_str(),
+        )
+        .await
+        .inspect_err(|_| {
+            self.0.failed_updates.fetch_add(1, Ordering::SeqCst);
+        })?;
+        self.0.updates.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for BatchDelete {
+    fn name(&self) -> &str {
+        "batch-delete"
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, _: &mut Worker) -> Result<()> {
+        conn.execute(
+            format!(
+                "batch on id limit {} delete from batch_dml where id % 2 = 0",
+                self.0.batch_size
+            )
+            .as_str(),
+        )
+        .await
+        .inspect_err(|_| {
+            self.0.failed_deletes.fetch_add(1, Ordering::SeqCst);
+        })?;
+        self.0.deletes.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for PointWrite {
+    fn name(&self) -> &str {
+        if self.odd_only {
+            "point-write-odd"
+        } else {
+            "point-write"
+        }
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let mut id = worker.rng.gen_range(0..self.table.rows);
+        if self.odd_only {
+            id |= 1;
+            if id >= self.table.rows {
+                id -= 2;
+            }
+        }
+        let _row = self.table.locks[id as usize % ROW_LOCKS].lock().await;
+        // above every value the row may have, so that it is told apart
+        let w = self.table.w[id as usize].load(Ordering::SeqCst)
+            + self
+                .table
+                .in_doubt
+                .lock()
+                .unwrap()
+                .get(&id)
+                .map_or(0, |d| d.len() as u64)
+            + 1;
+        let res = conn.execute(query(POINT_UPDATE).bind(w).bind(id)).await;
+        match res {
+            Ok(res) => {
+                worker.effects.record(POINT_UPDATE, res.rows_affected());
+                self.table.w[id as usize].store(w, Ordering::SeqCst);
+                self.table.in_doubt.lock().unwrap().remove(&id);
+                Ok(())
+            }
+            Err(e) => {
+                if matches!(ErrorClass::of(&e), ErrorClass::Connection) {
+                    self.table
+                        .in_doubt
+                        .lock()
+                        .unwrap()
+                        .entry(id)
+                        .or_default()
+                        .push(w);
+                }
+                Err(e.into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expected() -> Expected {
+        Expected {
+            rows: 4,
+            v: 3..=4,
+            deleted: false,
+            maybe_deleted: false,
+            w: vec![0, 2, 5, 1],
+            in_doubt: HashMap::from([(2, vec![6, 7])]),
+        }
+    }
+
+    #[test]
+    fn rows_against_the_model() {
+        let e = expected();
+        assert_eq!(e.row(0, 3, 0), None);
+        // a batch update failed after applying some of its batches
+        assert_eq!(e.row(1, 4, 2), None);
+        assert!(e.row(1, 2, 2).is_some());
+        assert!(e.row(1, 5, 2).is_some());
+        assert!(e.row(3, 3, 2).is_some());
+        assert!(e.row(4, 3, 0).is_some());
+    }
+
+    #[test]
+    fn in_doubt_point_writes() {
+        let e = expected();
+        for w in [5, 6, 7] {
+            assert_eq!(e.row(2, 3, w), None);
+        }
+        assert!(e.row(2, 3, 4).is_some());
+        assert!(e.row(2, 3, 8).is_some());
+        // only the row written in doubt may have their values
+        assert!(e.row(1, 3, 6).is_some());
+    }
+
+    #[test]
+    fn deleted_rows() {
+        let mut e = expected();
+        for id in 0..4 {
+            assert!(e.absent(id).is_some());
+        }
+        // a batch delete failed after deleting any of the even rows
+        e.maybe_deleted = true;
+        assert_eq!(e.row(0, 3, 0), None);
+        assert_eq!(e.absent(0), None);
+        assert_eq!(e.absent(2), None);
+        assert!(e.absent(1).is_some());
+        e.deleted = true;
+        assert!(e.row(0, 3, 0).is_some());
+        assert_eq!(e.row(1, 3, 2), None);
+        assert_eq!(e.absent(0), None);
+        assert!(e.absent(3).is_some());
+    }
+}
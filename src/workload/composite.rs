@@ -0,0 +1,101 @@
+//! Writes and reads of a table with a composite primary key, see
+//! [`KeySpace`].
+use crate::workload::keyspace::KeySpace;
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::Executor;
+use std::sync::Arc;
+
+/// Upserts a random row. Also creates the table.
+pub struct CompositeWrite(pub Arc<KeySpace>);
+
+/// Reads a random row by its primary key or, if the table has indexes, by a
+/// random one of them.
+pub struct CompositeRead(pub Arc<KeySpace>);
+
+/// Upserts or reads by primary key, half and half, rows of one tenant: those
+/// whose first column is `tenant`. With a resource group, every statement
+/// runs in it.
+pub struct TenantMix {
+    keys: Arc<KeySpace>,
+    tenant: u64,
+    resource_group: Option<String>,
+    name: String,
+}
+
+impl TenantMix {
+    pub fn new(keys: Arc<KeySpace>, tenant: u64, resource_group: Option<String>) -> TenantMix {
+        TenantMix {
+            keys,
+            tenant,
+            resource_group,
+            name: format!("tenant-{}", tenant),
+        }
+    }
+}
+
+#[async_trait]
+impl Workload for CompositeWrite {
+    fn name(&self) -> &str {
+        "composite-write"
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        self.0.create_table(conn).await
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let row = self.0.row(&mut worker.rng);
+        conn.execute(self.0.upsert(&row).as_str()).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for CompositeRead {
+    fn name(&self) -> &str {
+        "composite-read"
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let keys = &self.0;
+        let row = keys.row(&mut worker.rng);
+        let sql = match worker.rng.gen_range(0..=keys.indexes.len()) {
+            0 => keys.point_select(&row),
+            i => keys.index_select(i - 1, &row),
+        };
+        conn.fetch_all(sql.as_str()).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for TenantMix {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let row = self.keys.row_in(&mut worker.rng, self.tenant);
+        let write = worker.rng.gen_bool(0.5);
+        let mut sql = if write {
+            self.keys.upsert(&row)
+        } else {
+            self.keys.point_select(&row)
+        };
+        if let Some(group) = &self.resource_group {
+            // the hint goes right after the first keyword
+            let (verb, rest) = sql.split_once(' ').unwrap();
+            sql = format!("{} /*+ RESOURCE_GROUP({}) */ {}", verb, group, rest);
+        }
+        if write {
+            conn.execute(sql.as_str()).await?;
+        } else {
+            conn.fetch_all(sql.as_str()).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+//! Write transactions keep updating a set of keys that reside in different
+//! regions, producing many rollback records. Read transactions read these keys.
+use crate::txn_ts;
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor};
+use std::time::Instant;
+
+const UPDATE: &str = "update t set v = v + 1 where id = ?";
+const READ: &str = "select * from t";
+
+pub struct ContentionUpdate;
+
+/// Reads all keys written by [`ContentionUpdate`], which must be set up first.
+pub struct ContentionRead;
+
+#[async_trait]
+impl Workload for ContentionUpdate {
+    fn name(&self) -> &str {
+        "contention-update"
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("drop table if exists t").await?;
+        conn.execute(
+            "create table t (id int, v int, primary key (id
+    ));",
+        )
+        .await?;
+        conn.execute(
+            format!(
+                "insert into t values {}",
+                (1..100)
+                    .map(|x| format!("({}, {})", x, 0))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            )
+            .as_str(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let res = txn(conn, worker).await;
+        if res.is_err() {
+            let _ = conn.execute(query("rollback")).await;
+        }
+        res
+    }
+}
+
+#[async_trait]
+impl Workload for ContentionRead {
+    fn name(&self) -> &str {
+        "contention-read"
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let rows = conn.fetch_all(READ).await?;
+        worker.effects.record(READ, rows.len() as u64);
+        Ok(())
+    }
+}
+
+async fn txn(conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+    let begin = Instant::now();
+    worker
+        .statements
+        .timed("begin", conn.execute(query("begin")))
+        .await?;
+    for _ in 0..10 {
+        let id = worker.rng.gen_range::<i32, _>(1..100);
+        let res = worker
+            .statements
+            .timed("update", conn.execute(query(UPDATE).bind(id)))
+            .await?;
+        worker.effects.record(UPDATE, res.rows_affected());
+    }
+    worker
+        .statements
+        .timed("commit", conn.execute(query("commit")))
+        .await?;
+    if txn_ts::ratio() > 0.0 && worker.rng.gen_bool(txn_ts::ratio()) {
+        txn_ts::record(conn, "contention-update", begin.elapsed()).await;
+    }
+    Ok(())
+}
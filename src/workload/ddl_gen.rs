@@ -0,0 +1,214 @@
+//! Random DDL on the dmlddl table.
+//!
+//! The generator tracks the schema so it only picks statements that apply to
+//! it: the secondary index can be added when it is missing, the extra column
+//! renamed or modified when it exists, and so on. A multi-schema change
+//! toggles the index, a second extra column and the type of the first in
+//! one ALTER.
+use crate::error::MyError;
+use crate::workload::schema::Schema;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DdlKind {
+    AddIndex,
+    DropIndex,
+    AddColumn,
+    DropColumn,
+    ModifyColumn,
+    RenameColumn,
+    ChangeCharset,
+    MultiChange,
+}
+
+const KINDS: [(DdlKind, &str); 8] = [
+    (DdlKind::AddIndex, "add-index"),
+    (DdlKind::DropIndex, "drop-index"),
+    (DdlKind::AddColumn, "add-column"),
+    (DdlKind::DropColumn, "drop-column"),
+    (DdlKind::ModifyColumn, "modify-column"),
+    (DdlKind::RenameColumn, "rename-column"),
+    (DdlKind::ChangeCharset, "change-charset"),
+    (DdlKind::MultiChange, "multi-change"),
+];
+
+/// Relative weights of the DDL kinds, parsed from e.g.
+/// `add-index=3,drop-index=3,modify-column=1`. Kinds not listed get 0.
+#[derive(Clone, Debug)]
+pub struct DdlWeights([u32; KINDS.len()]);
+
+impl Default for DdlWeights {
+    fn default() -> Self {
+        DdlWeights([1; KINDS.len()])
+    }
+}
+
+impl FromStr for DdlWeights {
+    type Err = MyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = [0; KINDS.len()];
+        for item in s.split(',') {
+            let (name, weight) = item
+                .split_once('=')
+                .ok_or_else(|| MyError::StringError(format!("expected kind=weight: {}", item)))?;
+            let i = KINDS
+                .iter()
+                .position(|(_, n)| *n == name.trim())
+                .ok_or_else(|| MyError::StringError(format!("unknown DDL kind: {}", name)))?;
+            weights[i] = weight
+                .trim()
+                .parse()
+                .map_err(|e| MyError::StringError(format!("invalid weight of {}: {}", name, e)))?;
+        }
+        Ok(DdlWeights(weights))
+    }
+}
+
+/// The schema the generator believes the table has.
+pub struct DdlGen {
+    weights: DdlWeights,
+    schema: Schema,
+    index: bool,
+    // (renamed, bigint) of the extra column if it exists
+    column: Option<(bool, bool)>,
+    // the column only multi-schema changes add and drop
+    multi_column: bool,
+    general_ci: bool,
+}
+
+impl DdlGen {
+    /// For the table as [`Schema::create_table`] creates it.
+    pub fn new(weights: DdlWeights, schema: Schema) -> DdlGen {
+        DdlGen {
+            weights,
+            schema,
+            index: false,
+            column: None,
+            multi_column: false,
+            general_ci: false,
+        }
+    }
+
+    /// Pick a statement applicable to the current schema. Call [`Self::applied`]
+    /// once it succeeds.
+    pub fn next(&self, rng: &mut impl Rng) -> (DdlKind, String) {
+        let applicable: Vec<usize> = (0..KINDS.len())
+            .filter(|&i| self.applies(KINDS[i].0))
+            .collect();
+        let weights: Vec<u32> = applicable.iter().map(|&i| self.weights.0[i]).collect();
+        // if all applicable kinds have weight 0, e.g. only add-index is
+        // enabled and the index exists, pick any of them to move on
+        let i = match WeightedIndex::new(&weights) {
+            Ok(dist) => applicable[dist.sample(rng)],
+            Err(_) => applicable[rng.gen_range(0..applicable.len())],
+        };
+        let kind = KINDS[i].0;
+        (kind, self.sql(kind))
+    }
+
+    pub fn applied(&mut self, kind: DdlKind) {
+        match kind {
+            DdlKind::AddIndex => self.index = true,
+            DdlKind::DropIndex => self.index = false,
+            DdlKind::AddColumn => self.column = Some((false, false)),
+            DdlKind::DropColumn => self.column = None,
+            DdlKind::ModifyColumn => self.modified(),
+            DdlKind::RenameColumn => {
+                if let Some((renamed, _)) = &mut self.column {
+                    *renamed = !*renamed;
+                }
+            }
+            DdlKind::ChangeCharset => self.general_ci = !self.general_ci,
+            DdlKind::MultiChange => {
+                self.index = !self.index;
+                self.multi_column = !self.multi_column;
+                self.modified();
+            }
+        }
+    }
+
+    fn modified(&mut self) {
+        if let Some((_, bigint)) = &mut self.column {
+            *bigint = !*bigint;
+        }
+    }
+
+    fn applies(&self, kind: DdlKind) -> bool {
+        match kind {
+            DdlKind::AddIndex => !self.index,
+            DdlKind::DropIndex => self.index,
+            DdlKind::AddColumn => self.column.is_none(),
+            DdlKind::DropColumn
+            | DdlKind::ModifyColumn
+            | DdlKind::RenameColumn
+            | DdlKind::MultiChange => self.column.is_some(),
+            DdlKind::ChangeCharset => true,
+        }
+    }
+
+    fn sql(&self, kind: DdlKind) -> String {
+        let (renamed, bigint) = self.column.unwrap_or_default();
+        let column = if renamed { "extra_b" } else { "extra_a" };
+        let table = self.schema.quoted_table();
+        match kind {
+            DdlKind::AddIndex => format!(
+                "ALTER TABLE {} ADD INDEX {} ({})",
+                table,
+                self.schema.quoted_index(),
+                self.schema.quoted_ts_column()
+            ),
+            DdlKind::DropIndex => format!(
+                "ALTER TABLE {} DROP INDEX {}",
+                table,
+                self.schema.quoted_index()
+            ),
+            DdlKind::AddColumn => format!("ALTER TABLE {} ADD COLUMN extra_a INT", table),
+            DdlKind::DropColumn => format!("ALTER TABLE {} DROP COLUMN {}", table, column),
+            // BIGINT to INT needs a reorg, INT to BIGINT doesn't
+            DdlKind::ModifyColumn => format!(
+                "ALTER TABLE {} MODIFY COLUMN {} {}",
+                table,
+                column,
+                if bigint { "INT" } else { "BIGINT" }
+            ),
+            DdlKind::MultiChange => format!(
+                "ALTER TABLE {} {}, {}, MODIFY COLUMN {} {}",
+                table,
+                if self.multi_column {
+                    "DROP COLUMN extra_m"
+                } else {
+                    "ADD COLUMN extra_m INT"
+                },
+                if self.index {
+                    format!("DROP INDEX {}", self.schema.quoted_index())
+                } else {
+                    format!(
+                        "ADD INDEX {} ({})",
+                        self.schema.quoted_index(),
+                        self.schema.quoted_ts_column()
+                    )
+                },
+                column,
+                if bigint { "INT" } else { "BIGINT" }
+            ),
+            DdlKind::RenameColumn => format!(
+                "ALTER TABLE {} RENAME COLUMN {} TO {}",
+                table,
+                column,
+                if renamed { "extra_a" } else { "extra_b" }
+            ),
+            DdlKind::ChangeCharset => format!(
+                "ALTER TABLE {} CHARACTER SET 'utf8mb4' COLLATE '{}'",
+                table,
+                if self.general_ci {
+                    "utf8mb4_bin"
+                } else {
+                    "utf8mb4_general_ci"
+                }
+            ),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Parent and child tables linked by a foreign key, with DML on both while
+//! the constraint and an index of the child come and go.
+//!
+//! The DML keeps the child rows referencing existing parents by itself: it
+//! locks the parent before inserting a child and deletes the children with
+//! their parent in one transaction. So there must be no orphan rows whether
+//! or not the constraint exists at the time, see [`check_orphans`].
+use crate::error::MyError;
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor, Row};
+use std::sync::Mutex;
+
+const PARENTS: i32 = 1000;
+const ADD_FK: &str = "alter table fk_child add constraint fk_child_parent_fk \
+    foreign key (parent_id) references fk_parent (id)";
+
+/// Inserts parents, inserts children of existing parents and deletes parents
+/// with their children. Also creates the tables, with the constraint.
+pub struct FkDml;
+
+/// Adds and drops the constraint and an index of the child. Meant to run on
+/// a single worker.
+#[derive(Default)]
+pub struct FkDdl {
+    // (constraint dropped, index added)
+    state: Mutex<(bool, bool)>,
+}
+
+#[async_trait]
+impl Workload for FkDml {
+    fn name(&self) -> &str {
+        "fk-dml"
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("set @@global.tidb_enable_foreign_key = 1")
+            .await?;
+        conn.execute("drop table if exists fk_child, fk_parent")
+            .await?;
+        conn.execute("create table fk_parent (id int primary key, v int)")
+            .await?;
+        conn.execute(
+            "create table fk_child ( \
+            id bigint auto_increment primary key, \
+            parent_id int not null, \
+            v int, \
+            key fk_child_parent (parent_id) \
+            )",
+        )
+        .await?;
+        conn.execute(ADD_FK).await?;
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let id = worker.rng.gen_range(0..PARENTS);
+        let v = worker.rng.gen::<i32>();
+        match worker.rng.gen_range(0..5) {
+            0 | 1 => {
+                conn.execute(
+                    query("insert ignore into fk_parent values (?, ?)")
+                        .bind(id)
+                        .bind(v),
+                )
+                .await?;
+            }
+            2 | 3 => {
+                conn.execute("begin pessimistic").await?;
+                let res = insert_child(conn, id, v).await;
+                finish(conn, res).await?;
+            }
+            _ => {
+                conn.execute("begin pessimistic").await?;
+                let res = delete_parent(conn, id).await;
+                finish(conn, res).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn insert_child(conn: &mut MySqlConnection, parent: i32, v: i32) -> Result<()> {
+    let locked = conn
+        .fetch_optional(query("select id from fk_parent where id = ? for update").bind(parent))
+        .await?;
+    if locked.is_some() {
+        conn.execute(
+            query("insert into fk_child (parent_id, v) values (?, ?)")
+                .bind(parent)
+                .bind(v),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn delete_parent(conn: &mut MySqlConnection, parent: i32) -> Result<()> {
+    conn.execute(query("select id from fk_parent where id = ? for update").bind(parent))
+        .await?;
+    conn.execute(query("delete from fk_child where parent_id = ?").bind(parent))
+        .await?;
+    conn.execute(query("delete from fk_parent where id = ?").bind(parent))
+        .await?;
+    Ok(())
+}
+
+/// Commit if `res` is ok, roll back otherwise.
+async fn finish(conn: &mut MySqlConnection, res: Result<()>) -> Result<()> {
+    match res {
+        Ok(()) => {
+            conn.execute("commit").await?;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = conn.execute("rollback").await;
+            Err(e)
+        }
+    }
+}
+
+#[async_trait]
+impl Workload for FkDdl {
+    fn name(&self) -> &str {
+        "fk-ddl"
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let (dropped, indexed) = *self.state.lock().unwrap();
+        if worker.rng.gen_bool(0.5) {
+            let sql = if dropped {
+                ADD_FK
+            } else {
+                "alter table fk_child drop foreign key fk_child_parent_fk"
+            };
+            conn.execute(sql).await?;
+            self.state.lock().unwrap().0 = !dropped;
+        } else {
+            let sql = if indexed {
+                "alter table fk_child drop index fk_child_v"
+            } else {
+                "alter table fk_child add index fk_child_v (v)"
+            };
+            conn.execute(sql).await?;
+            self.state.lock().unwrap().1 = !indexed;
+        }
+        Ok(())
+    }
+}
+
+/// Fail if some child row references no parent.
+pub async fn check_orphans(conn: &mut MySqlConnection) -> Result<()> {
+    let count: Option<String> = conn
+        .fetch_one(
+            "select count(*) from fk_child c left join fk_parent p on c.parent_id = p.id \
+            where p.id is null",
+        )
+        .await?
+        .try_get_unchecked(0)?;
+    let count: u64 = count.and_then(|c| c.parse().ok()).unwrap_or(0);
+    if count > 0 {
+        return Err(MyError::StringError(format!(
+            "{} child rows have no parent",
+            count
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,181 @@
+//! Generated columns coming and going while DML updates the base columns.
+//!
+//! `gen_t` has base columns `a` and `b`, a stored generated column created
+//! with the table, since TiDB cannot add stored generated columns later, and
+//! virtual generated columns the DDL worker adds and drops. Each generated
+//! column can also get an index. [`GenTable::verify`] recomputes every
+//! generated column that exists from its expression.
+use crate::error::MyError;
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use log::info;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor, Row};
+use std::sync::{Arc, Mutex};
+
+const ROWS: i32 = 10_000;
+const INSERT_BATCH: i32 = 1000;
+
+// name, type, expression, stored
+const COLUMNS: [(&str, &str, &str, bool); 3] = [
+    ("g_stored", "varchar(32)", "upper(b)", true),
+    ("g_sum", "bigint", "a * 2 + 1", false),
+    ("g_concat", "varchar(64)", "concat(b, '-', a)", false),
+];
+
+/// The generated columns and indexes `gen_t` has, shared by [`GenDml`] and
+/// [`GenDdl`].
+pub struct GenTable {
+    // (column exists, index exists) of each of COLUMNS
+    state: Mutex<[(bool, bool); COLUMNS.len()]>,
+}
+
+/// Updates `a` or `b` of a random row. Also creates the table.
+pub struct GenDml(pub Arc<GenTable>);
+
+/// Adds or drops a virtual generated column, or an index on a generated
+/// column. Meant to run on a single worker.
+pub struct GenDdl(pub Arc<GenTable>);
+
+impl Default for GenTable {
+    fn default() -> Self {
+        GenTable {
+            state: Mutex::new(COLUMNS.map(|(_, _, _, stored)| (stored, false))),
+        }
+    }
+}
+
+impl GenTable {
+    /// ADMIN CHECK, and every generated column equal to its expression.
+    pub async fn verify(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("admin check table gen_t").await?;
+        let state = *self.state.lock().unwrap();
+        for ((name, _, expr, _), (exists, _)) in COLUMNS.iter().zip(state) {
+            if !exists {
+                continue;
+            }
+            let wrong: Option<String> = conn
+                .fetch_one(
+                    format!(
+                        "select count(*) from gen_t where not ({} <=> ({}))",
+                        name, expr
+                    )
+                    .as_str(),
+                )
+                .await?
+                .try_get_unchecked(0)?;
+            let wrong: u64 = wrong.and_then(|w| w.parse().ok()).unwrap_or(0);
+            if wrong > 0 {
+                return Err(MyError::StringError(format!(
+                    "{} rows have {} different from {}",
+                    wrong, name, expr
+                )));
+            }
+        }
+        info!("gen_t verified");
+        Ok(())
+    }
+}
+
+fn random_b(worker: &mut Worker) -> String {
+    let len = worker.rng.gen_range(1..32);
+    (&mut worker.rng)
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+#[async_trait]
+impl Workload for GenDml {
+    fn name(&self) -> &str {
+        "gen-dml"
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("drop table if exists gen_t").await?;
+        let (name, ty, expr, _) = COLUMNS[0];
+        conn.execute(
+            format!(
+                "create table gen_t (id int primary key, a int, b varchar(32), \
+                {} {} as ({}) stored)",
+                name, ty, expr
+            )
+            .as_str(),
+        )
+        .await?;
+        for start in (0..ROWS).step_by(INSERT_BATCH as usize) {
+            conn.execute(
+                format!(
+                    "insert into gen_t (id, a, b) values {}",
+                    (start..start + INSERT_BATCH)
+                        .map(|id| format!("({}, {}, 'b{}')", id, id, id))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )
+                .as_str(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let id = worker.rng.gen_range(0..ROWS);
+        let res = if worker.rng.gen_bool(0.5) {
+            conn.execute(
+                query("update gen_t set a = ? where id = ?")
+                    .bind(worker.rng.gen::<i16>())
+                    .bind(id),
+            )
+            .await?
+        } else {
+            let b = random_b(worker);
+            conn.execute(
+                query("update gen_t set b = ? where id = ?")
+                    .bind(b)
+                    .bind(id),
+            )
+            .await?
+        };
+        worker.effects.record("update gen_t", res.rows_affected());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for GenDdl {
+    fn name(&self) -> &str {
+        "gen-ddl"
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let i = worker.rng.gen_range(0..COLUMNS.len());
+        let (name, ty, expr, stored) = COLUMNS[i];
+        let (exists, indexed) = self.0.state.lock().unwrap()[i];
+        // an index only exists on an existing column, and stored columns
+        // can't be added back once dropped
+        let (sql, next) = if !exists {
+            let sql = format!(
+                "alter table gen_t add column {} {} as ({}) virtual",
+                name, ty, expr
+            );
+            (sql, (true, false))
+        } else if indexed {
+            let sql = format!("alter table gen_t drop index gen_t_{}", name);
+            (sql, (true, false))
+        } else if stored || worker.rng.gen_bool(0.5) {
+            let sql = format!("alter table gen_t add index gen_t_{} ({})", name, name);
+            (sql, (true, true))
+        } else {
+            let sql = format!("alter table gen_t drop column {}", name);
+            (sql, (false, false))
+        };
+        conn.execute(sql.as_str()).await?;
+        self.0.state.lock().unwrap()[i] = next;
+        Ok(())
+    }
+}
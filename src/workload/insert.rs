@@ -0,0 +1,197 @@
+//! Single-row inserts into a fresh table, with the primary key chosen by
+//! TiDB or the client, to compare how well each spreads the writes over the
+//! regions.
+//!
+//! [`PkKind::AutoIncrement`] appends to the last region, the hotspot the
+//! others avoid. [`PkKind::Scattered`] bit-reverses a sequence number on
+//! the client, [`PkKind::AutoRandom`] lets TiDB shard the key with
+//! `AUTO_RANDOM`. [`PkKind::CommonHandle`] has a VARCHAR primary key of
+//! random UUIDs, to compare TiDB's encoding of string keys with that of
+//! integer handles.
+use crate::error::{ErrorClass, MyError};
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use log::info;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor, Row};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const PAYLOAD_LEN: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PkKind {
+    AutoIncrement,
+    Scattered,
+    AutoRandom,
+    CommonHandle,
+}
+
+impl FromStr for PkKind {
+    type Err = MyError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "auto-increment" => Ok(PkKind::AutoIncrement),
+            "scattered" => Ok(PkKind::Scattered),
+            "auto-random" => Ok(PkKind::AutoRandom),
+            "common-handle" => Ok(PkKind::CommonHandle),
+            _ => Err(MyError::StringError(format!(
+                "unknown primary key kind {}, expected auto-increment, scattered, auto-random \
+                or common-handle",
+                s
+            ))),
+        }
+    }
+}
+
+/// Inserts a row with a random payload. Also creates the table, named after
+/// the kind of key, e.g. `insert_auto_random`.
+pub struct Insert {
+    pk: PkKind,
+    table: String,
+    // next sequence number of Scattered
+    next: AtomicU64,
+    inserted: AtomicU64,
+    // inserts that failed with an unknown outcome
+    in_doubt: AtomicU64,
+}
+
+impl Insert {
+    pub fn new(pk: PkKind) -> Insert {
+        let table = match pk {
+            PkKind::AutoIncrement => "insert_auto_increment",
+            PkKind::Scattered => "insert_scattered",
+            PkKind::AutoRandom => "insert_auto_random",
+            PkKind::CommonHandle => "insert_common_handle",
+        };
+        Insert {
+            pk,
+            table: table.to_owned(),
+            next: AtomicU64::new(0),
+            inserted: AtomicU64::new(0),
+            in_doubt: AtomicU64::new(0),
+        }
+    }
+
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Check that the table has the rows that were inserted.
+    pub async fn verify(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute(format!("admin check table {}", self.table).as_str())
+            .await?;
+        let rows: Option<String> = conn
+            .fetch_one(format!("select count(*) from {}", self.table).as_str())
+            .await?
+            .try_get_unchecked(0)?;
+        let rows: u64 = rows.and_then(|r| r.parse().ok()).unwrap_or(0);
+        let inserted = self.inserted.load(Ordering::SeqCst);
+        let in_doubt = self.in_doubt.load(Ordering::SeqCst);
+        if rows < inserted || rows > inserted + in_doubt {
+            return Err(MyError::StringError(format!(
+                "{} has {} rows, expected {} and up to {} in doubt",
+                self.table, rows, inserted, in_doubt
+            )));
+        }
+        info!("{} verified: {} rows", self.table, rows);
+        Ok(())
+    }
+}
+
+/// `seq` with its bits reversed, so consecutive numbers land far apart.
+/// Distinct for distinct `seq` below 2^63, and never negative as a BIGINT.
+fn scatter(seq: u64) -> u64 {
+    seq.reverse_bits() >> 1
+}
+
+/// `bits` formatted like a UUID, e.g. `0f8fad5b-d9cb-469f-a165-70867728950e`.
+fn uuid(bits: u128) -> String {
+    let hex = format!("{:032x}", bits);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[async_trait]
+impl Workload for Insert {
+    fn name(&self) -> &str {
+        match self.pk {
+            PkKind::AutoIncrement => "insert-auto-increment",
+            PkKind::Scattered => "insert-scattered",
+            PkKind::AutoRandom => "insert-auto-random",
+            PkKind::CommonHandle => "insert-common-handle",
+        }
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute(format!("drop table if exists {}", self.table).as_str())
+            .await?;
+        let id = match self.pk {
+            PkKind::AutoIncrement => "bigint auto_increment",
+            PkKind::Scattered => "bigint",
+            PkKind::AutoRandom => "bigint auto_random",
+            PkKind::CommonHandle => "varchar(36)",
+        };
+        conn.execute(
+            format!(
+                "create table {} (id {} primary key clustered, payload varchar({}))",
+                self.table, id, PAYLOAD_LEN
+            )
+            .as_str(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let payload: String = (&mut worker.rng)
+            .sample_iter(&Alphanumeric)
+            .take(PAYLOAD_LEN)
+            .map(char::from)
+            .collect();
+        let res = if self.pk == PkKind::Scattered {
+            let id = scatter(self.next.fetch_add(1, Ordering::SeqCst));
+            conn.execute(
+                query(&format!("insert into {} values (?, ?)", self.table))
+                    .bind(id)
+                    .bind(payload),
+            )
+            .await
+        } else if self.pk == PkKind::CommonHandle {
+            let id = uuid(worker.rng.gen());
+            conn.execute(
+                query(&format!("insert into {} values (?, ?)", self.table))
+                    .bind(id)
+                    .bind(payload),
+            )
+            .await
+        } else {
+            conn.execute(
+                query(&format!("insert into {} (payload) values (?)", self.table)).bind(payload),
+            )
+            .await
+        };
+        match res {
+            Ok(_) => {
+                self.inserted.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+            Err(e) => {
+                if ErrorClass::of(&e) == ErrorClass::Connection {
+                    self.in_doubt.fetch_add(1, Ordering::SeqCst);
+                }
+                Err(e.into())
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Keys of tables with composite primary keys and multi-column secondary
+//! indexes, e.g. `primary key (tenant_id, id), key (tenant_id, k)`.
+//!
+//! Every key column is a BIGINT drawn from its own [`KeyDist`], so a
+//! multi-tenant table can have a few hot tenants while ids within a tenant
+//! are uniform.
+use crate::error::MyError;
+use crate::Result;
+use rand::Rng;
+use serde::Deserialize;
+use sqlx::mysql::MySqlConnection;
+use sqlx::Executor;
+use std::str::FromStr;
+
+/// Distribution of the values of a key column, parsed from `uniform:N` or
+/// `hotspot:N:HOT:FRACTION`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyDist {
+    /// Uniform over `0..n`.
+    Uniform(u64),
+    /// `fraction` of the values uniform over `0..hot`, the rest uniform over
+    /// `0..n`.
+    Hotspot { n: u64, hot: u64, fraction: f64 },
+}
+
+impl KeyDist {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> u64 {
+        match *self {
+            KeyDist::Uniform(n) => rng.gen_range(0..n),
+            KeyDist::Hotspot { n, hot, fraction } => {
+                if rng.gen_bool(fraction) {
+                    rng.gen_range(0..hot)
+                } else {
+                    rng.gen_range(0..n)
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for KeyDist {
+    type Err = MyError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || MyError::StringError(format!("invalid key distribution: {}", s));
+        let parts: Vec<&str> = s.split(':').map(str::trim).collect();
+        let dist = match parts.as_slice() {
+            ["uniform", n] => KeyDist::Uniform(n.parse().map_err(|_| invalid())?),
+            ["hotspot", n, hot, fraction] => KeyDist::Hotspot {
+                n: n.parse().map_err(|_| invalid())?,
+                hot: hot.parse().map_err(|_| invalid())?,
+                fraction: fraction.parse().map_err(|_| invalid())?,
+            },
+            _ => return Err(invalid()),
+        };
+        match dist {
+            KeyDist::Uniform(n) | KeyDist::Hotspot { n, .. } if n == 0 => Err(invalid()),
+            KeyDist::Hotspot { n, hot, fraction }
+                if hot == 0 || hot > n || !(0.0..=1.0).contains(&fraction) =>
+            {
+                Err(invalid())
+            }
+            _ => Ok(dist),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyDist {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(d)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyColumn {
+    pub name: String,
+    pub dist: KeyDist,
+}
+
+/// A table of key columns and a `v` BIGINT payload. The primary key and the
+/// indexes list positions in `columns`.
+#[derive(Clone, Debug)]
+pub struct KeySpace {
+    pub table: String,
+    pub columns: Vec<KeyColumn>,
+    pub primary_key: Vec<usize>,
+    pub indexes: Vec<Vec<usize>>,
+}
+
+impl Default for KeySpace {
+    /// `primary key (tenant_id, id)` and `key (tenant_id, k)` over 100
+    /// tenants of 10000 ids each.
+    fn default() -> Self {
+        KeySpace {
+            table: "composite".to_owned(),
+            columns: vec![
+                KeyColumn {
+                    name: "tenant_id".to_owned(),
+                    dist: KeyDist::Uniform(100),
+                },
+                KeyColumn {
+                    name: "id".to_owned(),
+                    dist: KeyDist::Uniform(10_000),
+                },
+                KeyColumn {
+                    name: "k".to_owned(),
+                    dist: KeyDist::Uniform(10_000),
+                },
+            ],
+            primary_key: vec![0, 1],
+            indexes: vec![vec![0, 2]],
+        }
+    }
+}
+
+impl KeySpace {
+    /// Build a key space from column names, failing on unknown or missing
+    /// columns.
+    pub fn new(
+        table: &str,
+        columns: Vec<KeyColumn>,
+        primary_key: &[String],
+        indexes: &[Vec<String>],
+    ) -> Result<KeySpace> {
+        let position = |name: &String| {
+            columns
+                .iter()
+                .position(|c| &c.name == name)
+                .ok_or_else(|| MyError::StringError(format!("unknown key column {}", name)))
+        };
+        let primary_key = primary_key
+            .iter()
+            .map(position)
+            .collect::<Result<Vec<_>>>()?;
+        if primary_key.is_empty() {
+            return Err(MyError::StringError(format!(
+                "{} has no primary key",
+                table
+            )));
+        }
+        let indexes = indexes
+            .iter()
+            .map(|index| index.iter().map(position).collect())
+            .collect::<Result<Vec<_>>>()?;
+        Ok(KeySpace {
+            table: table.to_owned(),
+            columns,
+            primary_key,
+            indexes,
+        })
+    }
+
+    pub async fn create_table(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute(format!("drop table if exists {}", self.table).as_str())
+            .await?;
+        let mut defs: Vec<String> = self
+            .columns
+            .iter()
+            .map(|c| format!("{} bigint not null", c.name))
+            .collect();
+        defs.push("v bigint not null".to_owned());
+        defs.push(format!("primary key ({})", self.names(&self.primary_key)));
+        for (i, index) in self.indexes.iter().enumerate() {
+            defs.push(format!("key {}_{}({})", self.table, i, self.names(index)));
+        }
+        conn.execute(format!("create table {} ({})", self.table, defs.join(", ")).as_str())
+            .await?;
+        Ok(())
+    }
+
+    /// A value for every column, in order.
+    pub fn row<R: Rng>(&self, rng: &mut R) -> Vec<u64> {
+        self.columns.iter().map(|c| c.dist.sample(rng)).collect()
+    }
+
+    /// Like [`KeySpace::row`], with the first column, the leading column of
+    /// the primary key, set to `prefix`.
+    pub fn row_in<R: Rng>(&self, rng: &mut R, prefix: u64) -> Vec<u64> {
+        let mut row = self.row(rng);
+        row[0] = prefix;
+        row
+    }
+
+    /// Insert `row`, or bump `v` if its primary key exists.
+    pub fn upsert(&self, row: &[u64]) -> String {
+        let values: Vec<String> = row.iter().map(u64::to_string).collect();
+        format!(
+            "insert into {} ({}, v) values ({}, 1) on duplicate key update v = v + 1",
+            self.table,
+            self.names(&(0..self.columns.len()).collect::<Vec<_>>()),
+            values.join(", ")
+        )
+    }
+
+    /// Read the row with the primary key of `row`.
+    pub fn point_select(&self, row: &[u64]) -> String {
+        format!(
+            "select v from {} where {}",
+            self.table,
+            self.predicate(&self.primary_key, row)
+        )
+    }
+
+    /// Read the rows with the `index`-th index's values of `row`.
+    pub fn index_select(&self, index: usize, row: &[u64]) -> String {
+        format!(
+            "select count(*) from {} use index({}_{}) where {}",
+            self.table,
+            self.table,
+            index,
+            self.predicate(&self.indexes[index], row)
+        )
+    }
+
+    fn names(&self, columns: &[usize]) -> String {
+        columns
+            .iter()
+            .map(|&i| self.columns[i].name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn predicate(&self, columns: &[usize], row: &[u64]) -> String {
+        columns
+            .iter()
+            .map(|&i| format!("{} = {}", self.columns[i].name, row[i]))
+            .collect::<Vec<_>>()
+            .join(" and ")
+    }
+}
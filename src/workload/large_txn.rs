@@ -0,0 +1,119 @@
+//! Transactions writing a configurable number of rows, 100k to millions, to
+//! exercise TiDB's big-transaction path, and point reads that may run into
+//! their locks while they commit or have to resolve them afterwards.
+//!
+//! A transaction upserts a contiguous block of rows in batches and is then
+//! committed; [`LargeTxn::commit_summary`] tells how long the commits took.
+//! Size and memory limit errors are counted as [`ErrorClass::TooLarge`].
+//!
+//! [`ErrorClass::TooLarge`]: crate::error::ErrorClass::TooLarge
+use crate::metrics::Latencies;
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// blocks of rows a transaction picks from, so concurrent ones rarely overlap
+const BLOCKS: u64 = 4;
+const BATCH: u64 = 1000;
+const PAYLOAD_LEN: usize = 64;
+
+/// Upserts a random block of `rows` rows in one transaction. Also creates
+/// the table.
+pub struct LargeTxn {
+    rows: u64,
+    payload: String,
+    commits: Mutex<Latencies>,
+}
+
+/// Point selects of random rows of the blocks.
+pub struct LargeTxnRead(pub Arc<LargeTxn>);
+
+impl LargeTxn {
+    pub fn new(rows: u64) -> LargeTxn {
+        LargeTxn {
+            rows,
+            payload: "x".repeat(PAYLOAD_LEN),
+            commits: Mutex::default(),
+        }
+    }
+
+    /// Number, median, p99 and maximum duration of the commits so far.
+    pub fn commit_summary(&self) -> String {
+        let mut commits = self.commits.lock().unwrap().clone();
+        commits.sort();
+        format!("commits: {}", commits)
+    }
+
+    async fn write(&self, conn: &mut MySqlConnection, block: u64) -> Result<()> {
+        let first = block * self.rows;
+        for start in (first..first + self.rows).step_by(BATCH as usize) {
+            let end = (start + BATCH).min(first + self.rows);
+            conn.execute(
+                format!(
+                    "insert into large_txn_t values {} on duplicate key update v = v + 1",
+                    (start..end)
+                        .map(|id| format!("({}, 0, '{}')", id, self.payload))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )
+                .as_str(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for LargeTxn {
+    fn name(&self) -> &str {
+        "large-txn"
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("drop table if exists large_txn_t").await?;
+        conn.execute(
+            format!(
+                "create table large_txn_t (id bigint primary key, v bigint not null, \
+                payload varchar({}))",
+                PAYLOAD_LEN
+            )
+            .as_str(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let block = worker.rng.gen_range(0..BLOCKS);
+        conn.execute("begin").await?;
+        if let Err(e) = self.write(conn, block).await {
+            let _ = conn.execute("rollback").await;
+            return Err(e);
+        }
+        let begin = Instant::now();
+        conn.execute("commit").await?;
+        self.commits.lock().unwrap().record(begin.elapsed());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for LargeTxnRead {
+    fn name(&self) -> &str {
+        "large-txn-read"
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        // nothing to read until the first commit, so no effects are recorded
+        let id = worker.rng.gen_range(0..BLOCKS * self.0.rows);
+        conn.fetch_optional(query("select v from large_txn_t where id = ?").bind(id))
+            .await?;
+        Ok(())
+    }
+}
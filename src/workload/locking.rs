@@ -0,0 +1,181 @@
+//! Queue-style locking reads: `SELECT ... FOR UPDATE NOWAIT` or
+//! `SKIP LOCKED` of one row or of the next rows from a random id, then an
+//! update of what was locked. Every worker counts the rows it acquired and
+//! the ones it skipped or failed to lock in [`LockOutcomes`].
+use crate::error::{ErrorClass, MyError};
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use log::info;
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor, Row};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+const ROWS: i64 = 1000;
+// rows a range read locks
+const RANGE: i64 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockMode {
+    /// Fail right away if a row is locked.
+    Nowait,
+    /// Leave locked rows out of the result.
+    SkipLocked,
+}
+
+impl FromStr for LockMode {
+    type Err = MyError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "nowait" => Ok(LockMode::Nowait),
+            "skip-locked" => Ok(LockMode::SkipLocked),
+            _ => Err(MyError::StringError(format!(
+                "unknown lock mode {}, expected nowait or skip-locked",
+                s
+            ))),
+        }
+    }
+}
+
+/// Rows the locking reads acquired, left out because they were locked, and
+/// statements that failed on a locked row.
+#[derive(Debug, Default)]
+pub struct LockOutcomes {
+    pub acquired: AtomicU64,
+    pub skipped: AtomicU64,
+    pub failed: AtomicU64,
+}
+
+impl fmt::Display for LockOutcomes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rows acquired: {}, skipped: {}, reads failed on a lock: {}",
+            self.acquired.load(Ordering::SeqCst),
+            self.skipped.load(Ordering::SeqCst),
+            self.failed.load(Ordering::SeqCst)
+        )
+    }
+}
+
+/// A locking read in a pessimistic transaction, and an update of the rows it
+/// locked. Also creates the table, `queue_t`.
+pub struct LockingRead {
+    name: String,
+    mode: LockMode,
+    range: bool,
+    pub outcomes: Arc<LockOutcomes>,
+}
+
+impl LockingRead {
+    /// Of the row with a random id, or of the next [`RANGE`] rows from it if
+    /// `range`.
+    pub fn new(mode: LockMode, range: bool) -> LockingRead {
+        let name = format!(
+            "lock-{}-{}",
+            match mode {
+                LockMode::Nowait => "nowait",
+                LockMode::SkipLocked => "skip",
+            },
+            if range { "range" } else { "point" }
+        );
+        LockingRead {
+            name,
+            mode,
+            range,
+            outcomes: Arc::default(),
+        }
+    }
+
+    async fn lock(&self, conn: &mut MySqlConnection, id: i64) -> Result<()> {
+        let suffix = match self.mode {
+            LockMode::Nowait => "for update nowait",
+            LockMode::SkipLocked => "for update skip locked",
+        };
+        let (sql, wanted) = if self.range {
+            (
+                format!(
+                    "select id from queue_t where id >= ? order by id limit {} {}",
+                    RANGE, suffix
+                ),
+                RANGE.min(ROWS - id) as u64,
+            )
+        } else {
+            (format!("select id from queue_t where id = ? {}", suffix), 1)
+        };
+        let rows = match conn.fetch_all(query(&sql).bind(id)).await {
+            Ok(rows) => rows,
+            Err(e) if ErrorClass::of(&e) == ErrorClass::LockNowait => {
+                self.outcomes.failed.fetch_add(1, Ordering::SeqCst);
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let ids = rows
+            .iter()
+            .map(|row| row.try_get_unchecked::<i64, _>(0))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let acquired = ids.len() as u64;
+        self.outcomes.acquired.fetch_add(acquired, Ordering::SeqCst);
+        self.outcomes
+            .skipped
+            .fetch_add(wanted.saturating_sub(acquired), Ordering::SeqCst);
+        if !ids.is_empty() {
+            conn.execute(
+                format!(
+                    "update queue_t set v = v + 1 where id in ({})",
+                    ids.iter().map(i64::to_string).collect::<Vec<_>>().join(",")
+                )
+                .as_str(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for LockingRead {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("drop table if exists queue_t").await?;
+        conn.execute("create table queue_t (id bigint primary key, v bigint not null)")
+            .await?;
+        conn.execute(
+            format!(
+                "insert into queue_t values {}",
+                (0..ROWS)
+                    .map(|id| format!("({}, 0)", id))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            )
+            .as_str(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let id = worker.rng.gen_range(0..ROWS);
+        conn.execute("begin pessimistic").await?;
+        if let Err(e) = self.lock(conn, id).await {
+            let _ = conn.execute("rollback").await;
+            return Err(e);
+        }
+        conn.execute("commit").await?;
+        Ok(())
+    }
+
+    async fn teardown(&self, _: &mut MySqlConnection) -> Result<()> {
+        info!("{}: {}", self.name, self.outcomes);
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+//! Lossy column type changes (INT to TINYINT, VARCHAR(64) to VARCHAR(8)) and
+//! back while DML writes values around the narrow types' limits.
+//!
+//! A narrowing change must fail if a row doesn't fit, and a write that
+//! doesn't fit the narrow type must fail while it is in place; both count as
+//! rejected rather than as errors. The client keeps the last value written to
+//! every row, each row written by one worker at a time, so
+//! [`LossyTable::verify`] can tell whether a change that went through
+//! altered any data.
+use crate::error::{ErrorClass, MyError};
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use log::{error, info};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor, Row};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+const ROWS: i32 = 1000;
+const MAX_LOGGED_MISMATCHES: u64 = 100;
+// (column, wide type, narrow type)
+const CHANGES: [(&str, &str, &str); 2] =
+    [("n", "int", "tinyint"), ("s", "varchar(64)", "varchar(8)")];
+
+/// The table shared by [`LossyDml`] and [`LossyDdl`].
+pub struct LossyTable {
+    // last (n, s) written to each row
+    rows: Vec<tokio::sync::Mutex<(i32, String)>>,
+    // rows whose last write failed with an unknown outcome
+    in_doubt: Mutex<HashSet<i32>>,
+    // whether each of CHANGES is in place
+    narrow: Mutex<[bool; CHANGES.len()]>,
+    pub rejected_writes: AtomicU64,
+    pub rejected_changes: AtomicU64,
+    pub applied_changes: AtomicU64,
+}
+
+/// Writes `n` near the limits of TINYINT, or `s` near 8 characters, to a
+/// random row. Also creates the table.
+pub struct LossyDml(pub Arc<LossyTable>);
+
+/// Narrows or widens one of the columns. Meant to run on a single worker.
+pub struct LossyDdl(pub Arc<LossyTable>);
+
+impl Default for LossyTable {
+    fn default() -> Self {
+        LossyTable {
+            rows: (0..ROWS)
+                .map(|_| tokio::sync::Mutex::new((0, String::new())))
+                .collect(),
+            in_doubt: Mutex::new(HashSet::new()),
+            narrow: Mutex::new([false; CHANGES.len()]),
+            rejected_writes: AtomicU64::new(0),
+            rejected_changes: AtomicU64::new(0),
+            applied_changes: AtomicU64::new(0),
+        }
+    }
+}
+
+impl LossyTable {
+    /// ADMIN CHECK, the column types the DDL worker left, and every row
+    /// holding the last value written to it.
+    pub async fn verify(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("admin check table lossy_t").await?;
+        let narrow = *self.narrow.lock().unwrap();
+        for ((column, wide, narrow_type), narrow) in CHANGES.iter().zip(narrow) {
+            let ty: String = conn
+                .fetch_one(
+                    query(
+                        "select column_type from information_schema.columns \
+                        where table_schema = database() and table_name = 'lossy_t' \
+                        and column_name = ?",
+                    )
+                    .bind(column),
+                )
+                .await?
+                .try_get_unchecked(0)?;
+            let expected = if narrow { narrow_type } else { wide };
+            // integer types may come with a display width
+            if ty != *expected && !ty.starts_with(&format!("{}(", expected)) {
+                return Err(MyError::StringError(format!(
+                    "{} has type {}, expected {}",
+                    column, ty, expected
+                )));
+            }
+        }
+
+        let in_doubt = self.in_doubt.lock().unwrap().clone();
+        let mut mismatches = 0;
+        for row in conn.fetch_all("select id, n, s from lossy_t").await? {
+            let id: i32 = row.try_get_unchecked(0)?;
+            let n: Option<String> = row.try_get_unchecked(1)?;
+            let n: i32 = n.and_then(|n| n.parse().ok()).unwrap_or(0);
+            let s: String = row.try_get_unchecked(2)?;
+            let expected = self.rows[id as usize].lock().await.clone();
+            if (n, &s) != (expected.0, &expected.1) && !in_doubt.contains(&id) {
+                if mismatches < MAX_LOGGED_MISMATCHES {
+                    error!(
+                        "row {} has ({}, {:?}), expected ({}, {:?})",
+                        id, n, s, expected.0, expected.1
+                    );
+                }
+                mismatches += 1;
+            }
+        }
+        if mismatches > 0 {
+            return Err(MyError::StringError(format!(
+                "{} rows of lossy_t differ from what was written",
+                mismatches
+            )));
+        }
+        info!("lossy_t verified");
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for LossyDml {
+    fn name(&self) -> &str {
+        "lossy-dml"
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("drop table if exists lossy_t").await?;
+        conn.execute("create table lossy_t (id int primary key, n int, s varchar(64))")
+            .await?;
+        conn.execute(
+            format!(
+                "insert into lossy_t values {}",
+                (0..ROWS)
+                    .map(|id| format!("({}, 0, '')", id))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            )
+            .as_str(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let id = worker.rng.gen_range(0..ROWS);
+        let mut row = self.0.rows[id as usize].lock().await;
+        let i = worker.rng.gen_range(0..CHANGES.len());
+        // values that don't fit mostly while the narrow type is in place, so
+        // that narrowing usually succeeds
+        let fits = if self.0.narrow.lock().unwrap()[i] {
+            worker.rng.gen_bool(0.9)
+        } else {
+            worker.rng.gen_bool(0.999)
+        };
+        let mut next = row.clone();
+        let sql = if i == 0 {
+            next.0 = match (fits, worker.rng.gen_bool(0.5)) {
+                (true, true) => worker.rng.gen_range(120..=127),
+                (true, false) => worker.rng.gen_range(-128..=-120),
+                (false, true) => worker.rng.gen_range(128..=135),
+                (false, false) => worker.rng.gen_range(-136..=-129),
+            };
+            query("update lossy_t set n = ? where id = ?").bind(next.0)
+        } else {
+            let len = if fits {
+                worker.rng.gen_range(5..=8)
+            } else {
+                worker.rng.gen_range(9..=12)
+            };
+            next.1 = (&mut worker.rng)
+                .sample_iter(&Alphanumeric)
+                .take(len)
+                .map(char::from)
+                .collect();
+            query("update lossy_t set s = ? where id = ?").bind(next.1.clone())
+        };
+        match conn.execute(sql.bind(id)).await {
+            Ok(res) => {
+                worker.effects.record("update lossy_t", res.rows_affected());
+                *row = next;
+                Ok(())
+            }
+            Err(e) => match ErrorClass::of(&e) {
+                ErrorClass::Truncated if !fits => {
+                    self.0.rejected_writes.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                }
+                class => {
+                    if class == ErrorClass::Connection {
+                        self.0.in_doubt.lock().unwrap().insert(id);
+                    }
+                    Err(e.into())
+                }
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl Workload for LossyDdl {
+    fn name(&self) -> &str {
+        "lossy-ddl"
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let i = worker.rng.gen_range(0..CHANGES.len());
+        let (column, wide, narrow_type) = CHANGES[i];
+        let narrow = self.0.narrow.lock().unwrap()[i];
+        let ty = if narrow { wide } else { narrow_type };
+        let res = conn
+            .execute(format!("alter table lossy_t modify column {} {}", column, ty).as_str())
+            .await;
+        match res {
+            Ok(_) => {
+                self.0.narrow.lock().unwrap()[i] = !narrow;
+                self.0.applied_changes.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+            Err(e) if !narrow && ErrorClass::of(&e) == ErrorClass::Truncated => {
+                self.0.rejected_changes.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
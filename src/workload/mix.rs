@@ -0,0 +1,133 @@
+//! A blend of point inserts, updates, deletes and selects on one table, each
+//! worker picking the operation of every iteration by weight, so conflicts
+//! between different kinds of statements show up as they do in applications.
+use crate::error::MyError;
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor};
+use std::str::FromStr;
+
+const ROWS: i64 = 100_000;
+const INSERT_BATCH: i64 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Insert,
+    Update,
+    Delete,
+    Select,
+}
+
+const OPS: [(Op, &str); 4] = [
+    (Op::Insert, "insert"),
+    (Op::Update, "update"),
+    (Op::Delete, "delete"),
+    (Op::Select, "select"),
+];
+
+/// Relative weights of the operations, parsed from e.g.
+/// `insert=30,update=50,delete=10,select=10`. Operations not listed get 0.
+#[derive(Clone, Debug)]
+pub struct OpWeights([u32; OPS.len()]);
+
+impl Default for OpWeights {
+    fn default() -> Self {
+        OpWeights([1; OPS.len()])
+    }
+}
+
+impl FromStr for OpWeights {
+    type Err = MyError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut weights = [0; OPS.len()];
+        for item in s.split(',') {
+            let (name, weight) = item
+                .split_once('=')
+                .ok_or_else(|| MyError::StringError(format!("expected op=weight: {}", item)))?;
+            let i = OPS
+                .iter()
+                .position(|(_, n)| *n == name.trim())
+                .ok_or_else(|| MyError::StringError(format!("unknown operation: {}", name)))?;
+            weights[i] = weight
+                .trim()
+                .parse()
+                .map_err(|e| MyError::StringError(format!("invalid weight of {}: {}", name, e)))?;
+        }
+        if weights.iter().all(|&w| w == 0) {
+            return Err(MyError::StringError("all weights are 0".to_owned()));
+        }
+        Ok(OpWeights(weights))
+    }
+}
+
+/// One operation on a random row of `mix_t`, picked by weight. Also creates
+/// the table with every row present.
+pub struct Mix {
+    dist: WeightedIndex<u32>,
+}
+
+impl Mix {
+    pub fn new(weights: &OpWeights) -> Mix {
+        Mix {
+            // FromStr and Default rule out all zeros
+            dist: WeightedIndex::new(weights.0).unwrap(),
+        }
+    }
+}
+
+#[async_trait]
+impl Workload for Mix {
+    fn name(&self) -> &str {
+        "mix"
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("drop table if exists mix_t").await?;
+        conn.execute("create table mix_t (id bigint primary key, v bigint not null)")
+            .await?;
+        for start in (0..ROWS).step_by(INSERT_BATCH as usize) {
+            conn.execute(
+                format!(
+                    "insert into mix_t values {}",
+                    (start..start + INSERT_BATCH)
+                        .map(|id| format!("({}, 0)", id))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )
+                .as_str(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let id = worker.rng.gen_range(0..ROWS);
+        // rows come and go with the mix, so no statement is recorded as an
+        // effect
+        match OPS[self.dist.sample(&mut worker.rng)].0 {
+            Op::Insert => {
+                conn.execute(query("insert ignore into mix_t values (?, 0)").bind(id))
+                    .await?;
+            }
+            Op::Update => {
+                conn.execute(query("update mix_t set v = v + 1 where id = ?").bind(id))
+                    .await?;
+            }
+            Op::Delete => {
+                conn.execute(query("delete from mix_t where id = ?").bind(id))
+                    .await?;
+            }
+            Op::Select => {
+                conn.fetch_optional(query("select v from mix_t where id = ?").bind(id))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
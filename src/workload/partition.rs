@@ -0,0 +1,228 @@
+//! Partition DDL on a range-partitioned table while DML writes to it.
+//!
+//! Partition `pN` of `part_t` holds ids `N * WIDTH .. (N + 1) * WIDTH`. The
+//! DDL worker adds partitions at the top, drops them at the bottom, truncates
+//! them, and exchanges one with the plain table `part_x` and later back, so
+//! the rows of the partition are not lost. The DML upserts and deletes ids
+//! in the partitions that currently exist.
+use crate::error::MyError;
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use log::info;
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor, Row};
+use std::sync::{Arc, Mutex};
+
+const WIDTH: i64 = 10_000;
+const INITIAL_PARTITIONS: i64 = 4;
+// drop a partition only when more than this many exist
+const MIN_PARTITIONS: i64 = 2;
+
+/// The partitions `part_t` has, shared by [`PartitionDml`] and
+/// [`PartitionDdl`].
+pub struct PartitionTable {
+    state: Mutex<PartitionState>,
+}
+
+struct PartitionState {
+    // partitions low..high exist
+    low: i64,
+    high: i64,
+    // partition whose rows are in part_x
+    exchanged: Option<i64>,
+}
+
+/// Upserts and deletes ids of existing partitions. Also creates the tables.
+pub struct PartitionDml(pub Arc<PartitionTable>);
+
+/// Adds, drops, truncates and exchanges partitions. Meant to run on a single
+/// worker.
+pub struct PartitionDdl(pub Arc<PartitionTable>);
+
+impl Default for PartitionTable {
+    fn default() -> Self {
+        PartitionTable {
+            state: Mutex::new(PartitionState {
+                low: 0,
+                high: INITIAL_PARTITIONS,
+                exchanged: None,
+            }),
+        }
+    }
+}
+
+impl PartitionTable {
+    /// Check the tables with ADMIN CHECK and that every partition, and
+    /// `part_x`, only holds ids of its range.
+    pub async fn verify(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("admin check table part_t").await?;
+        conn.execute("admin check table part_x").await?;
+        let (low, high, exchanged) = {
+            let state = self.state.lock().unwrap();
+            (state.low, state.high, state.exchanged)
+        };
+        let mut total = 0;
+        for p in low..high {
+            let (lo, hi) = (p * WIDTH, (p + 1) * WIDTH);
+            let rows = count(
+                conn,
+                &format!("select count(*) from part_t partition (p{})", p),
+            )
+            .await?;
+            let misplaced = count(
+                conn,
+                &format!(
+                    "select count(*) from part_t partition (p{}) where id < {} or id >= {}",
+                    p, lo, hi
+                ),
+            )
+            .await?;
+            if misplaced > 0 {
+                return Err(MyError::StringError(format!(
+                    "partition p{} has {} rows outside {}..{}",
+                    p, misplaced, lo, hi
+                )));
+            }
+            total += rows;
+        }
+        let all = count(conn, "select count(*) from part_t").await?;
+        if all != total {
+            return Err(MyError::StringError(format!(
+                "part_t has {} rows, its partitions {}",
+                all, total
+            )));
+        }
+        if let Some(p) = exchanged {
+            let misplaced = count(
+                conn,
+                &format!(
+                    "select count(*) from part_x where id < {} or id >= {}",
+                    p * WIDTH,
+                    (p + 1) * WIDTH
+                ),
+            )
+            .await?;
+            if misplaced > 0 {
+                return Err(MyError::StringError(format!(
+                    "part_x has {} rows outside the range of p{}",
+                    misplaced, p
+                )));
+            }
+        }
+        info!("part_t verified: {} rows in p{}..p{}", total, low, high);
+        Ok(())
+    }
+}
+
+async fn count(conn: &mut MySqlConnection, sql: &str) -> Result<u64> {
+    let value: Option<String> = conn.fetch_one(sql).await?.try_get_unchecked(0)?;
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(0))
+}
+
+#[async_trait]
+impl Workload for PartitionDml {
+    fn name(&self) -> &str {
+        "partition-dml"
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("drop table if exists part_t, part_x").await?;
+        conn.execute(
+            format!(
+                "create table part_t (id bigint primary key, v bigint, key part_t_v (v)) \
+                partition by range (id) ({})",
+                (0..INITIAL_PARTITIONS)
+                    .map(|p| format!("partition p{} values less than ({})", p, (p + 1) * WIDTH))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .as_str(),
+        )
+        .await?;
+        conn.execute("create table part_x (id bigint primary key, v bigint, key part_t_v (v))")
+            .await?;
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let (low, high) = {
+            let state = self.0.state.lock().unwrap();
+            (state.low, state.high)
+        };
+        let id = worker.rng.gen_range(low * WIDTH..high * WIDTH);
+        if worker.rng.gen_bool(0.8) {
+            conn.execute(
+                query("insert into part_t values (?, 0) on duplicate key update v = v + 1")
+                    .bind(id),
+            )
+            .await?;
+        } else {
+            conn.execute(query("delete from part_t where id = ?").bind(id))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for PartitionDdl {
+    fn name(&self) -> &str {
+        "partition-ddl"
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let (low, high, exchanged) = {
+            let state = self.0.state.lock().unwrap();
+            (state.low, state.high, state.exchanged)
+        };
+        match worker.rng.gen_range(0..4) {
+            0 => {
+                conn.execute(
+                    format!(
+                        "alter table part_t add partition (partition p{} values less than ({}))",
+                        high,
+                        (high + 1) * WIDTH
+                    )
+                    .as_str(),
+                )
+                .await?;
+                self.0.state.lock().unwrap().high += 1;
+            }
+            1 if high - low > MIN_PARTITIONS && exchanged != Some(low) => {
+                // the DML stops picking the partition's ids before it is gone
+                self.0.state.lock().unwrap().low += 1;
+                let res = conn
+                    .execute(format!("alter table part_t drop partition p{}", low).as_str())
+                    .await;
+                if let Err(e) = res {
+                    self.0.state.lock().unwrap().low -= 1;
+                    return Err(e.into());
+                }
+            }
+            2 => {
+                let p = worker.rng.gen_range(low..high);
+                conn.execute(format!("alter table part_t truncate partition p{}", p).as_str())
+                    .await?;
+            }
+            3 => {
+                let p = exchanged.unwrap_or_else(|| worker.rng.gen_range(low..high));
+                conn.execute(
+                    format!(
+                        "alter table part_t exchange partition p{} with table part_x",
+                        p
+                    )
+                    .as_str(),
+                )
+                .await?;
+                self.0.state.lock().unwrap().exchanged = match exchanged {
+                    Some(_) => None,
+                    None => Some(p),
+                };
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
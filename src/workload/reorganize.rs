@@ -0,0 +1,230 @@
+//! `REORGANIZE PARTITION` of a range-partitioned table under inserts and
+//! updates.
+//!
+//! The DDL worker splits a partition in two at its middle or merges two
+//! adjacent ones, both of which copy the rows of the old partitions into the
+//! new ones. The DML inserts ids that are new to the table and updates
+//! existing ones. The client remembers every id it inserted, so
+//! [`ReorgTable::verify`] can compare the rows of each partition exactly.
+use crate::error::{ErrorClass, MyError};
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use log::{error, info};
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor, Row};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+const ROWS: i64 = 1_000_000;
+const INITIAL_PARTITIONS: i64 = 4;
+const MAX_PARTITIONS: usize = 16;
+// don't split partitions narrower than this
+const MIN_WIDTH: i64 = 1000;
+
+/// The table shared by the workloads of this module.
+pub struct ReorgTable {
+    inserted: Vec<AtomicBool>,
+    // ids whose insert failed with an unknown outcome
+    in_doubt: Mutex<HashSet<i64>>,
+    // (name, upper bound) of each partition, in order
+    partitions: Mutex<Partitions>,
+}
+
+struct Partitions {
+    bounds: Vec<(String, i64)>,
+    // suffix of the next new partition's name
+    next: u64,
+}
+
+/// Inserts a random id with `insert ignore` and increments `v` of another
+/// one. Also creates the table.
+pub struct ReorgDml(pub Arc<ReorgTable>);
+
+/// Splits or merges partitions. Meant to run on a single worker.
+pub struct ReorgDdl(pub Arc<ReorgTable>);
+
+impl Default for ReorgTable {
+    fn default() -> Self {
+        let width = ROWS / INITIAL_PARTITIONS;
+        ReorgTable {
+            inserted: (0..ROWS).map(|_| AtomicBool::new(false)).collect(),
+            in_doubt: Mutex::new(HashSet::new()),
+            partitions: Mutex::new(Partitions {
+                bounds: (0..INITIAL_PARTITIONS)
+                    .map(|p| (format!("r{}", p), (p + 1) * width))
+                    .collect(),
+                next: INITIAL_PARTITIONS as u64,
+            }),
+        }
+    }
+}
+
+impl ReorgTable {
+    /// Check that the table has the partitions the DDL worker created, and
+    /// each of them the ids the DML inserted into its range.
+    pub async fn verify(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("admin check table reorg_t").await?;
+        let expected = self.partitions.lock().unwrap().bounds.clone();
+        let actual = conn
+            .fetch_all(
+                "select partition_name, partition_description from information_schema.partitions \
+                where table_schema = database() and table_name = 'reorg_t' \
+                order by partition_ordinal_position",
+            )
+            .await?
+            .iter()
+            .map(|row| {
+                let name: String = row.try_get_unchecked(0)?;
+                let bound: String = row.try_get_unchecked(1)?;
+                Ok((name, bound.parse().unwrap_or(-1)))
+            })
+            .collect::<std::result::Result<Vec<(String, i64)>, sqlx::Error>>()?;
+        if actual != expected {
+            return Err(MyError::StringError(format!(
+                "reorg_t has partitions {:?}, expected {:?}",
+                actual, expected
+            )));
+        }
+
+        let in_doubt = self.in_doubt.lock().unwrap().clone();
+        let mut mismatches = 0;
+        let mut lower = 0;
+        for (name, upper) in &expected {
+            let rows: Option<String> = conn
+                .fetch_one(format!("select count(*) from reorg_t partition ({})", name).as_str())
+                .await?
+                .try_get_unchecked(0)?;
+            let rows: i64 = rows.and_then(|r| r.parse().ok()).unwrap_or(0);
+            let inserted = (lower..*upper)
+                .filter(|&id| self.inserted[id as usize].load(Ordering::SeqCst))
+                .count() as i64;
+            let doubtful = in_doubt
+                .iter()
+                .filter(|&&id| id >= lower && id < *upper)
+                .count() as i64;
+            if rows < inserted || rows > inserted + doubtful {
+                error!(
+                    "partition {} ({}..{}) has {} rows, expected {} and up to {} in doubt",
+                    name, lower, upper, rows, inserted, doubtful
+                );
+                mismatches += 1;
+            }
+            lower = *upper;
+        }
+        if mismatches > 0 {
+            return Err(MyError::StringError(format!(
+                "{} partitions of reorg_t diverged from the model",
+                mismatches
+            )));
+        }
+        info!("reorg_t verified: {} partitions", expected.len());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for ReorgDml {
+    fn name(&self) -> &str {
+        "reorg-dml"
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("drop table if exists reorg_t").await?;
+        let bounds = self.0.partitions.lock().unwrap().bounds.clone();
+        conn.execute(
+            format!(
+                "create table reorg_t (id bigint primary key, v bigint, key reorg_t_v (v)) \
+                partition by range (id) ({})",
+                bounds
+                    .iter()
+                    .map(|(name, upper)| format!("partition {} values less than ({})", name, upper))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .as_str(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let id = worker.rng.gen_range(0..ROWS);
+        match conn
+            .execute(query("insert ignore into reorg_t values (?, 0)").bind(id))
+            .await
+        {
+            Ok(res) => {
+                if res.rows_affected() > 0 {
+                    self.0.inserted[id as usize].store(true, Ordering::SeqCst);
+                }
+            }
+            Err(e) => {
+                if matches!(ErrorClass::of(&e), ErrorClass::Connection) {
+                    self.0.in_doubt.lock().unwrap().insert(id);
+                }
+                return Err(e.into());
+            }
+        }
+        conn.execute(
+            query("update reorg_t set v = v + 1 where id = ?").bind(worker.rng.gen_range(0..ROWS)),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for ReorgDdl {
+    fn name(&self) -> &str {
+        "reorg-ddl"
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let (bounds, next) = {
+            let partitions = self.0.partitions.lock().unwrap();
+            (partitions.bounds.clone(), partitions.next)
+        };
+        let lower = |i: usize| if i == 0 { 0 } else { bounds[i - 1].1 };
+        let splittable: Vec<usize> = (0..bounds.len())
+            .filter(|&i| bounds[i].1 - lower(i) >= 2 * MIN_WIDTH)
+            .collect();
+        let split = bounds.len() < MAX_PARTITIONS
+            && !splittable.is_empty()
+            && (bounds.len() < 2 || worker.rng.gen_bool(0.5));
+        let (sql, i, replaced, new) = if split {
+            let i = splittable[worker.rng.gen_range(0..splittable.len())];
+            let (name, upper) = bounds[i].clone();
+            let mid = (lower(i) + upper) / 2;
+            let new = vec![
+                (format!("r{}", next), mid),
+                (format!("r{}", next + 1), upper),
+            ];
+            let sql = format!(
+                "alter table reorg_t reorganize partition {} into (\
+                partition {} values less than ({}), partition {} values less than ({}))",
+                name, new[0].0, mid, new[1].0, upper
+            );
+            (sql, i, 1, new)
+        } else {
+            let i = worker.rng.gen_range(0..bounds.len() - 1);
+            let new = vec![(format!("r{}", next), bounds[i + 1].1)];
+            let sql = format!(
+                "alter table reorg_t reorganize partition {}, {} into (\
+                partition {} values less than ({}))",
+                bounds[i].0,
+                bounds[i + 1].0,
+                new[0].0,
+                new[0].1
+            );
+            (sql, i, 2, new)
+        };
+        conn.execute(sql.as_str()).await?;
+        let mut partitions = self.0.partitions.lock().unwrap();
+        partitions.next += new.len() as u64;
+        partitions.bounds.splice(i..i + replaced, new);
+        Ok(())
+    }
+}
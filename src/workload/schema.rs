@@ -0,0 +1,225 @@
+//! The table the dmlddl workers run against.
+use crate::error::MyError;
+use crate::Result;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{Executor, Row};
+
+/// Names of the table, its columns and its secondary index, and the values
+/// the DML writes. The default is the schema the original issue was found
+/// with; [`Schema::numbered`] gives distinct tables of the same shape.
+#[derive(Clone, Debug)]
+pub struct Schema {
+    pub table: String,
+    /// A SET column, the second column of the primary key.
+    pub set_column: String,
+    pub set_members: Vec<String>,
+    /// A TIMESTAMP column, the first column of the primary key and the one
+    /// the secondary index is on.
+    pub ts_column: String,
+    pub index: String,
+    pub comment: String,
+    /// The values a row is inserted with and then updated to.
+    pub set_values: [String; 2],
+    pub ts_values: [String; 2],
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Schema {
+            table: "473d9750-7369-4822-91b0-bc6705131333".to_owned(),
+            set_column: "c1c104bf-2899-4776-8a94-f01f9d728c74".to_owned(),
+            set_members: [
+                "pwl", "k6sg", "f", "9rfx", "o", "9ngz", "p8q1g", "kk8y", "5", "lz", "g",
+            ]
+            .map(str::to_owned)
+            .to_vec(),
+            ts_column: "4af7ba24-c2fa-4deb-8af2-58d5f98783d0".to_owned(),
+            index: "ef9e02dc-578b-4e7f-acd6-0d0fbbe919f5".to_owned(),
+            comment: "85575ad7-e373-49e7-adb0-dd10541d9478".to_owned(),
+            set_values: ["p8q1g".to_owned(), "k6sg".to_owned()],
+            ts_values: [
+                "2016-05-24 13:20:38".to_owned(),
+                "2016-05-24 13:20:39".to_owned(),
+            ],
+        }
+    }
+}
+
+impl Schema {
+    /// The default schema with the table and index names suffixed by `i`.
+    pub fn numbered(i: usize) -> Schema {
+        let mut schema = Schema::default();
+        schema.table = format!("{}-{}", schema.table, i);
+        schema.index = format!("{}-{}", schema.index, i);
+        schema
+    }
+
+    pub fn quoted_table(&self) -> String {
+        quote(&self.table)
+    }
+
+    pub fn quoted_ts_column(&self) -> String {
+        quote(&self.ts_column)
+    }
+
+    pub fn quoted_index(&self) -> String {
+        quote(&self.index)
+    }
+
+    pub async fn create_table(&self, conn: &mut MySqlConnection) -> Result<()> {
+        for sql in self.create_table_sql() {
+            conn.execute(sql.as_str()).await?;
+        }
+        Ok(())
+    }
+
+    /// The statements [`Self::create_table`] executes.
+    pub fn create_table_sql(&self) -> [String; 2] {
+        [
+            format!("DROP TABLE IF EXISTS {}", self.quoted_table()),
+            format!(
+                "CREATE TABLE {} ({} SET({}), {} TIMESTAMP, PRIMARY KEY ({}, {})) COMMENT '{}' CHARACTER SET 'utf8mb4' COLLATE 'utf8mb4_bin'",
+                self.quoted_table(),
+                quote(&self.set_column),
+                self.set_members
+                    .iter()
+                    .map(|m| format!("'{}'", m))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                self.quoted_ts_column(),
+                self.quoted_ts_column(),
+                quote(&self.set_column),
+                self.comment
+            ),
+        ]
+    }
+
+    pub fn insert(&self) -> String {
+        format!(
+            "INSERT INTO {} SET {} = '{}', {} = '{}'",
+            self.quoted_table(),
+            self.quoted_ts_column(),
+            self.ts_values[0],
+            quote(&self.set_column),
+            self.set_values[0]
+        )
+    }
+
+    /// Moves the inserted row, changing the indexed column.
+    pub fn update_indexed(&self) -> String {
+        format!(
+            "UPDATE {} SET {} = '{}' WHERE {} = '{}'",
+            self.quoted_table(),
+            self.quoted_ts_column(),
+            self.ts_values[1],
+            self.quoted_ts_column(),
+            self.ts_values[0]
+        )
+    }
+
+    /// Finds the row by the indexed column, changes the other one.
+    pub fn update_unindexed(&self) -> String {
+        format!(
+            "UPDATE {} SET {} = '{}' WHERE {} = '{}'",
+            self.quoted_table(),
+            quote(&self.set_column),
+            self.set_values[1],
+            self.quoted_ts_column(),
+            self.ts_values[1]
+        )
+    }
+
+    pub fn delete_by_index(&self) -> String {
+        format!(
+            "DELETE FROM {} WHERE {} = '{}'",
+            self.quoted_table(),
+            self.quoted_ts_column(),
+            self.ts_values[1]
+        )
+    }
+
+    pub fn delete(&self) -> String {
+        format!("DELETE FROM {}", self.quoted_table())
+    }
+
+    /// Check the table after the DML stopped: ADMIN CHECK, the same row count
+    /// through the secondary index, if it exists, as without it, and only the
+    /// values the DML writes. If every DML worker's last delete of all rows
+    /// succeeded, `empty` is set and the table must have no rows.
+    pub async fn verify(&self, conn: &mut MySqlConnection, empty: bool) -> Result<()> {
+        conn.execute(format!("ADMIN CHECK TABLE {}", self.quoted_table()).as_str())
+            .await?;
+        let indexed = count(
+            conn,
+            &format!(
+                "SELECT COUNT(*) FROM information_schema.tidb_indexes \
+                WHERE table_schema = DATABASE() AND table_name = '{}' AND key_name = '{}'",
+                self.table, self.index
+            ),
+        )
+        .await?
+            > 0;
+        // the DDL may have left the index dropped
+        let rows = if indexed {
+            format!(
+                "SELECT COUNT(*) FROM {} IGNORE INDEX ({})",
+                self.quoted_table(),
+                self.quoted_index()
+            )
+        } else {
+            format!("SELECT COUNT(*) FROM {}", self.quoted_table())
+        };
+        let rows = count(conn, &rows).await?;
+        if indexed {
+            let index_rows = count(
+                conn,
+                &format!(
+                    "SELECT COUNT(*) FROM {} USE INDEX ({})",
+                    self.quoted_table(),
+                    self.quoted_index()
+                ),
+            )
+            .await?;
+            if index_rows != rows {
+                return fail(format!(
+                    "{} rows through the index, {} without it",
+                    index_rows, rows
+                ));
+            }
+        }
+        let unexpected = count(
+            conn,
+            &format!(
+                "SELECT COUNT(*) FROM {} WHERE {} NOT IN ('{}', '{}') OR {} NOT IN ('{}', '{}')",
+                self.quoted_table(),
+                self.quoted_ts_column(),
+                self.ts_values[0],
+                self.ts_values[1],
+                quote(&self.set_column),
+                self.set_values[0],
+                self.set_values[1]
+            ),
+        )
+        .await?;
+        if unexpected > 0 {
+            return fail(format!("{} rows with values never written", unexpected));
+        }
+        if empty && rows > 0 {
+            return fail(format!("{} rows left after the last delete", rows));
+        }
+        Ok(())
+    }
+}
+
+async fn count(conn: &mut MySqlConnection, sql: &str) -> Result<u64> {
+    let value: Option<String> = conn.fetch_one(sql).await?.try_get_unchecked(0)?;
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(0))
+}
+
+fn fail(msg: String) -> Result<()> {
+    Err(MyError::StringError(msg))
+}
+
+fn quote(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
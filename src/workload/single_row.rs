@@ -0,0 +1,67 @@
+//! Pessimistic updates of a single row by its unique key, each after a read
+//! through the primary index.
+//!
+//! To reproduce https://github.com/pingcap/tidb/issues/25659, https://github.com/pingcap/tidb/issues/33393
+use crate::txn_ts;
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Instant;
+
+#[derive(Default)]
+pub struct SingleRowUpdate {
+    v: AtomicI64,
+}
+
+#[async_trait]
+impl Workload for SingleRowUpdate {
+    fn name(&self) -> &str {
+        "single-row-update"
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("drop table if exists t").await?;
+        conn.execute(
+            "create table t (pk int, id int, v int, primary key (pk), unique key i1(id));",
+        )
+        .await?;
+        // conn.execute("insert into t values (1,1);").await?;
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let v = self.v.fetch_add(1, Ordering::SeqCst) + 2;
+        let statements = &mut worker.statements;
+        statements
+            .timed(
+                "select",
+                conn.execute(query("select * from t use index(primary) where id = 1")),
+            )
+            .await?;
+        let begin = Instant::now();
+        statements
+            .timed("begin", conn.execute(query("begin pessimistic")))
+            .await?;
+        let res = statements
+            .timed(
+                "update",
+                conn.execute(query("update t set v = ? where id = 1;").bind(v)),
+            )
+            .await;
+        if let Err(e) = res {
+            let _ = conn.execute(query("rollback")).await;
+            return Err(e.into());
+        }
+        statements
+            .timed("commit", conn.execute(query("commit")))
+            .await?;
+        if txn_ts::ratio() > 0.0 && worker.rng.gen_bool(txn_ts::ratio()) {
+            txn_ts::record(conn, self.name(), begin.elapsed()).await;
+        }
+        Ok(())
+    }
+}
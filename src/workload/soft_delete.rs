@@ -0,0 +1,112 @@
+//! Application-level soft deletes: rows are marked deleted instead of being
+//! removed, reads filter them out, and a cleanup hard-deletes old tombstones.
+//!
+//! TiDB has no partial indexes, so the filtered reads go through an index
+//! with `deleted` as its first column, which keeps live and deleted rows of a
+//! key apart.
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor};
+
+const KEYS: i32 = 10_000;
+const CLEANUP_BATCH: u64 = 1000;
+
+/// Inserts a row and soft-deletes a live row of a random key, so the number of
+/// live rows stays about the same while tombstones accumulate. Also creates
+/// the table.
+pub struct SoftDeleteWrite;
+
+/// Reads the live rows of a random key.
+pub struct SoftDeleteRead;
+
+/// Hard-deletes up to 1000 tombstones older than `retention_secs`.
+pub struct SoftDeleteCleanup {
+    pub retention_secs: u64,
+}
+
+#[async_trait]
+impl Workload for SoftDeleteWrite {
+    fn name(&self) -> &str {
+        "soft-delete-write"
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("drop table if exists soft_delete").await?;
+        conn.execute(
+            "create table soft_delete ( \
+            id bigint not null auto_increment primary key, \
+            k int not null, \
+            v varchar(64), \
+            deleted tinyint not null default 0, \
+            deleted_at timestamp null, \
+            key soft_delete_live(deleted, k) \
+            )",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let v: String = (&mut worker.rng)
+            .sample_iter(&Alphanumeric)
+            .take(64)
+            .map(char::from)
+            .collect();
+        conn.execute(
+            query("insert into soft_delete (k, v) values (?, ?)")
+                .bind(worker.rng.gen_range(0..KEYS))
+                .bind(v),
+        )
+        .await?;
+        conn.execute(
+            query(
+                "update soft_delete set deleted = 1, deleted_at = now() \
+                where deleted = 0 and k = ? limit 1",
+            )
+            .bind(worker.rng.gen_range(0..KEYS)),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for SoftDeleteRead {
+    fn name(&self) -> &str {
+        "soft-delete-read"
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        query(
+            "select id, v from soft_delete use index(soft_delete_live) where deleted = 0 and k = ?",
+        )
+        .bind(worker.rng.gen_range(0..KEYS))
+        .fetch_all(conn)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for SoftDeleteCleanup {
+    fn name(&self) -> &str {
+        "soft-delete-cleanup"
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, _: &mut Worker) -> Result<()> {
+        conn.execute(
+            query(
+                "delete from soft_delete \
+                where deleted = 1 and deleted_at < now() - interval ? second limit ?",
+            )
+            .bind(self.retention_secs)
+            .bind(CLEANUP_BATCH),
+        )
+        .await?;
+        Ok(())
+    }
+}
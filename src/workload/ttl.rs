@@ -0,0 +1,162 @@
+//! Inserts into a table with a TTL attribute, with `created_at` spread so
+//! that about half the rows are already expired when inserted, and updates
+//! of random rows, while TiDB's TTL job deletes the expired ones.
+//!
+//! The client remembers when each row it inserted expires, so
+//! [`TtlTable::verify`] can check that the job deleted no row too early, and
+//! [`TtlTable::backlog`] measures how far the job lags behind.
+use crate::error::MyError;
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use log::info;
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor, Row};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// rows expiring within this much of the check may be gone already
+const CLOCK_MARGIN: Duration = Duration::from_secs(10);
+const CHECK_BATCH: usize = 1000;
+
+/// The TTL table shared by [`TtlWrite`] and [`TtlBacklog`].
+pub struct TtlTable {
+    ttl: Duration,
+    job_interval: String,
+    max_id: AtomicU64,
+    // id and expiry of the rows inserted unexpired
+    live: Mutex<Vec<(u64, Instant)>>,
+}
+
+/// Inserts a row and increments `v` of a random one. Also creates the table.
+pub struct TtlWrite(pub Arc<TtlTable>);
+
+/// Logs the number of expired rows not deleted yet.
+pub struct TtlBacklog(pub Arc<TtlTable>);
+
+impl TtlTable {
+    /// `job_interval` is the table's TTL_JOB_INTERVAL, e.g. `'1m'`.
+    pub fn new(ttl: Duration, job_interval: &str) -> TtlTable {
+        TtlTable {
+            ttl,
+            job_interval: job_interval.to_owned(),
+            max_id: AtomicU64::new(0),
+            live: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Rows past their TTL that the job has not deleted yet.
+    pub async fn backlog(&self, conn: &mut MySqlConnection) -> Result<u64> {
+        let rows: Option<String> = conn
+            .fetch_one(
+                query("select count(*) from ttl_t where created_at < now() - interval ? second")
+                    .bind(self.ttl.as_secs()),
+            )
+            .await?
+            .try_get_unchecked(0)?;
+        Ok(rows.and_then(|r| r.parse().ok()).unwrap_or(0))
+    }
+
+    /// Check that every row inserted unexpired that has not expired yet
+    /// still exists.
+    pub async fn verify(&self, conn: &mut MySqlConnection) -> Result<()> {
+        let now = Instant::now();
+        let ids: Vec<u64> = self
+            .live
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, expiry)| *expiry > now + CLOCK_MARGIN)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut missing = 0;
+        for chunk in ids.chunks(CHECK_BATCH) {
+            let found: Option<String> = conn
+                .fetch_one(
+                    format!(
+                        "select count(*) from ttl_t where id in ({})",
+                        chunk
+                            .iter()
+                            .map(u64::to_string)
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    )
+                    .as_str(),
+                )
+                .await?
+                .try_get_unchecked(0)?;
+            let found: usize = found.and_then(|f| f.parse().ok()).unwrap_or(0);
+            missing += chunk.len() - found;
+        }
+        if missing > 0 {
+            return Err(MyError::StringError(format!(
+                "{} of {} unexpired rows were deleted",
+                missing,
+                ids.len()
+            )));
+        }
+        info!("ttl_t verified: {} unexpired rows exist", ids.len());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for TtlWrite {
+    fn name(&self) -> &str {
+        "ttl-write"
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("drop table if exists ttl_t").await?;
+        conn.execute(
+            format!(
+                "create table ttl_t (id bigint auto_increment primary key, \
+                created_at timestamp not null, v int not null default 0) \
+                ttl = created_at + interval {} second ttl_enable = 'ON' ttl_job_interval = {}",
+                self.0.ttl.as_secs(),
+                self.0.job_interval
+            )
+            .as_str(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let table = &self.0;
+        let age = worker.rng.gen_range(0..2 * table.ttl.as_secs());
+        let res = conn
+            .execute(
+                query("insert into ttl_t (created_at) values (now() - interval ? second)")
+                    .bind(age),
+            )
+            .await?;
+        let id = res.last_insert_id();
+        table.max_id.fetch_max(id, Ordering::SeqCst);
+        if age < table.ttl.as_secs() {
+            let expiry = Instant::now() + table.ttl - Duration::from_secs(age);
+            table.live.lock().unwrap().push((id, expiry));
+        }
+        let max_id = table.max_id.load(Ordering::SeqCst);
+        conn.execute(
+            query("update ttl_t set v = v + 1 where id = ?").bind(worker.rng.gen_range(1..=max_id)),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Workload for TtlBacklog {
+    fn name(&self) -> &str {
+        "ttl-backlog"
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, _: &mut Worker) -> Result<()> {
+        let backlog = self.0.backlog(conn).await?;
+        info!("ttl backlog: {} expired rows", backlog);
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+//! `INSERT ... ON DUPLICATE KEY UPDATE` and `REPLACE` of single rows, each of
+//! an existing key with the conflict probability and of a new key otherwise.
+//! The table has a unique key besides the primary key, so both constraints
+//! are checked.
+//!
+//! Whether a statement took the insert or the update path is told by its
+//! affected rows: 1 for an insert, 2 for an update or a replaced row, and 0
+//! for an update that changed nothing. [`Upsert::summary`] gives the latency
+//! of each path.
+use crate::error::MyError;
+use crate::metrics::Latencies;
+use crate::workload::{Worker, Workload};
+use crate::Result;
+use async_trait::async_trait;
+use log::info;
+use rand::Rng;
+use sqlx::mysql::MySqlConnection;
+use sqlx::{query, Executor};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+// rows the table starts with, the keys a conflicting upsert picks from
+const EXISTING: i64 = 100_000;
+const INSERT_BATCH: i64 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpsertKind {
+    OnDuplicate,
+    Replace,
+}
+
+impl FromStr for UpsertKind {
+    type Err = MyError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "on-duplicate" => Ok(UpsertKind::OnDuplicate),
+            "replace" => Ok(UpsertKind::Replace),
+            _ => Err(MyError::StringError(format!(
+                "unknown upsert {}, expected on-duplicate or replace",
+                s
+            ))),
+        }
+    }
+}
+
+/// An upsert of one row. Also creates the table, `upsert_t`.
+pub struct Upsert {
+    kind: UpsertKind,
+    conflict: f64,
+    next: AtomicI64,
+    // latencies of the statements that inserted and that updated
+    inserted: Mutex<Latencies>,
+    updated: Mutex<Latencies>,
+}
+
+impl Upsert {
+    /// `conflict` is the probability that the key exists.
+    pub fn new(kind: UpsertKind, conflict: f64) -> Upsert {
+        Upsert {
+            kind,
+            conflict,
+            next: AtomicI64::new(EXISTING),
+            inserted: Mutex::default(),
+            updated: Mutex::default(),
+        }
+    }
+
+    /// Latencies of the insert and the update path.
+    pub fn summary(&self) -> String {
+        let mut inserted = self.inserted.lock().unwrap().clone();
+        let mut updated = self.updated.lock().unwrap().clone();
+        inserted.sort();
+        updated.sort();
+        format!("inserted: {}; updated: {}", inserted, updated)
+    }
+}
+
+#[async_trait]
+impl Workload for Upsert {
+    fn name(&self) -> &str {
+        match self.kind {
+            UpsertKind::OnDuplicate => "upsert-on-duplicate",
+            UpsertKind::Replace => "upsert-replace",
+        }
+    }
+
+    async fn setup(&self, conn: &mut MySqlConnection) -> Result<()> {
+        conn.execute("drop table if exists upsert_t").await?;
+        conn.execute(
+            "create table upsert_t (id bigint primary key, v bigint not null, \
+            k bigint not null, unique key upsert_t_k (k))",
+        )
+        .await?;
+        for start in (0..EXISTING).step_by(INSERT_BATCH as usize) {
+            conn.execute(
+                format!(
+                    "insert into upsert_t values {}",
+                    (start..start + INSERT_BATCH)
+                        .map(|id| format!("({}, 0, {})", id, id))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )
+                .as_str(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn run_iteration(&self, conn: &mut MySqlConnection, worker: &mut Worker) -> Result<()> {
+        let id = if worker.rng.gen_bool(self.conflict) {
+            worker.rng.gen_range(0..EXISTING)
+        } else {
+            self.next.fetch_add(1, Ordering::SeqCst)
+        };
+        let sql = match self.kind {
+            UpsertKind::OnDuplicate => {
+                "insert into upsert_t values (?, 0, ?) on duplicate key update v = v + 1"
+            }
+            UpsertKind::Replace => "replace into upsert_t values (?, ?, ?)",
+        };
+        let mut q = query(sql).bind(id);
+        if self.kind == UpsertKind::Replace {
+            q = q.bind(worker.rng.gen::<i32>());
+        }
+        let begin = Instant::now();
+        let res = conn.execute(q.bind(id)).await?;
+        let elapsed = begin.elapsed();
+        worker.effects.record(sql, res.rows_affected());
+        match res.rows_affected() {
+            1 => self.inserted.lock().unwrap().record(elapsed),
+            _ => self.updated.lock().unwrap().record(elapsed),
+        }
+        Ok(())
+    }
+
+    async fn teardown(&self, _: &mut MySqlConnection) -> Result<()> {
+        info!("{}: {}", self.name(), self.summary());
+        Ok(())
+    }
+}